@@ -3,15 +3,109 @@ use status;
 use optflags;
 use pixelformat::{Pixel, PixelFormatChan};
 
+use std::any::Any;
 use std::cell::Cell;
 use status::Status;
+use std::io::{self, Write};
 use std::os::raw;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::Path;
+use std::rc::Rc;
 
 use crate::pixelformat;
 
 pub struct Encoder {
     encoder: *mut sixel::Encoder,
+    // libsixel's encoder only writes to a file descriptor, so `encode_to` drives the
+    // dither and output objects itself. The options it needs are mirrored here.
+    settings: Rc<Cell<WriterSettings>>,
+}
+
+#[derive(Copy, Clone)]
+struct WriterSettings {
+    reqcolors: raw::c_int,
+    color: WriterColor,
+    invert: bool,
+    diffusion: DiffusionMethod,
+    largest: MethodForLargest,
+    rep: MethodForRepColor,
+    quality: QualityMode,
+    eight_bit: bool,
+    gri_arg_limit: bool,
+    palette_type: PaletteType,
+    encode_policy: EncodePolicy,
+    penetrate: bool,
+    complexion: raw::c_int,
+    // Scaling, cropping, mapfiles and macros are only applied by libsixel's encoder
+    unsupported: bool,
+}
+
+#[derive(Copy, Clone)]
+enum WriterColor {
+    Default,
+    Highcolor,
+    Monochrome,
+    Builtin(BuiltinDither),
+}
+
+impl WriterSettings {
+    // Same defaults as sixel_encoder_new, including ${SIXEL_COLORS}
+    fn new() -> WriterSettings {
+        let reqcolors = std::env::var("SIXEL_COLORS")
+            .ok()
+            .and_then(|colors| colors.trim().parse::<raw::c_int>().ok())
+            .filter(|&colors| colors > 1 && colors <= 256)
+            .unwrap_or(-1);
+
+        WriterSettings {
+            reqcolors,
+            color: WriterColor::Default,
+            invert: false,
+            diffusion: DiffusionMethod::Auto,
+            largest: MethodForLargest::Auto,
+            rep: MethodForRepColor::Auto,
+            quality: QualityMode::Auto,
+            eight_bit: false,
+            gri_arg_limit: false,
+            palette_type: PaletteType::Auto,
+            encode_policy: EncodePolicy::Auto,
+            penetrate: false,
+            complexion: 1,
+            unsupported: false,
+        }
+    }
+}
+
+// State handed to libsixel as the `priv` pointer of the output
+struct WriterContext<'a> {
+    writer: &'a mut dyn Write,
+    error: Option<io::Error>,
+    // A panic must not unwind through libsixel's C frames: it is kept here and
+    // resumed once `sixel_encode` has returned
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+unsafe extern "C" fn write_callback(data: *mut raw::c_char, size: raw::c_int, priv_: *mut raw::c_void) -> raw::c_int {
+    let context = &mut *(priv_ as *mut WriterContext);
+
+    // libsixel ignores the return value: keep the first error and drop what follows
+    if context.error.is_some() || context.panic.is_some() {
+        return -1;
+    }
+
+    let bytes = std::slice::from_raw_parts(data as *const u8, size.max(0) as usize);
+    let writer = &mut context.writer;
+    match panic::catch_unwind(AssertUnwindSafe(|| writer.write_all(bytes))) {
+        Ok(Ok(())) => size,
+        Ok(Err(e)) => {
+            context.error = Some(e);
+            -1
+        }
+        Err(payload) => {
+            context.panic = Some(payload);
+            -1
+        }
+    }
 }
 
 impl Encoder {
@@ -28,7 +122,7 @@ impl Encoder {
         }
 
 
-        Ok(Encoder { encoder })
+        Ok(Encoder { encoder, settings: Rc::new(Cell::new(WriterSettings::new())) })
     }
 
     #[deprecated]
@@ -38,7 +132,7 @@ impl Encoder {
             encoder = sixel_encoder_create();
         }
 
-        Encoder { encoder }
+        Encoder { encoder, settings: Rc::new(Cell::new(WriterSettings::new())) }
 
     }
 
@@ -90,6 +184,149 @@ impl Encoder {
         status::from_libsixel(result)
     }
 
+    /// Encodes like `encode_bytes_ext`, but hands the sixel sequence to `writer`
+    /// instead of stdout or the file given to `set_output`.
+    ///
+    /// The first error returned by the writer is reported as `Error::Io`; a panic in the
+    /// writer is resumed once libsixel is done. Scaling, cropping, mapfiles and macros are
+    /// not supported here (`Error::NotImplemented`).
+    pub fn encode_to<W: Write>(&self, writer: &mut W, width: usize, height: usize, pixels: &[u8], format: PixelFormat) -> Status<()> {
+        use std::os::raw::c_int;
+        use std::ptr;
+
+        let settings = self.settings.get();
+        if settings.unsupported {
+            return Err(status::Error::NotImplemented);
+        }
+
+        let depth = format.channels_per_pixel();
+        if depth <= 0 || (format as c_int) & (FormatType::Palette as c_int) != 0 {
+            return Err(status::Error::BadArgument);
+        }
+        if width == 0 || height == 0 || width > c_int::max_value() as usize || height > c_int::max_value() as usize {
+            return Err(status::Error::BadArgument);
+        }
+        let size = match width.checked_mul(height).and_then(|n| n.checked_mul(depth as usize)) {
+            Some(size) if pixels.len() >= size => size,
+            _ => return Err(status::Error::BadArgument),
+        };
+
+        // sixel_encode diffuses errors in place
+        let mut buffer = pixels[..size].to_vec();
+        let mut context = WriterContext { writer, error: None, panic: None };
+
+        unsafe {
+            let dither = self.prepare_dither(&settings, &mut buffer, width, height, format)?;
+
+            let mut output: *mut Output = ptr::null_mut();
+            let result = sixel_output_new(&mut output,
+                                          Some(write_callback),
+                                          &mut context as *mut WriterContext as *mut raw::c_void,
+                                          ptr::null_mut());
+            if let Err(e) = status::from_libsixel(result) {
+                sixel_dither_unref(dither);
+                return Err(e);
+            }
+
+            let character_size = if settings.eight_bit { CharacterSize::EightBit } else { CharacterSize::SevenBit };
+            sixel_output_set_8bit_availability(output, character_size);
+            sixel_output_set_gri_arg_limit(output, settings.gri_arg_limit as c_int);
+            sixel_output_set_palette_type(output, settings.palette_type);
+            sixel_output_set_penetrate_multiplexer(output, settings.penetrate as c_int);
+            sixel_output_set_encode_policy(output, settings.encode_policy);
+
+            let result = sixel_encode(buffer.as_mut_ptr(),
+                                      width as c_int,
+                                      height as c_int,
+                                      depth,
+                                      dither,
+                                      output);
+
+            sixel_output_unref(output);
+            sixel_dither_unref(dither);
+            if let Some(payload) = context.panic.take() {
+                panic::resume_unwind(payload);
+            }
+            status::from_libsixel(result)?;
+        }
+
+        match context.error {
+            Some(e) => Err(status::Error::Io(e)),
+            None => Ok(()),
+        }
+    }
+
+    // Same palette and dithering as sixel_encoder_encode_bytes would pick for this frame
+    unsafe fn prepare_dither(&self, settings: &WriterSettings, pixels: &mut [u8], width: usize, height: usize, format: PixelFormat) -> Status<*mut Dither> {
+        use std::os::raw::c_int;
+        use std::ptr;
+
+        let mut dither: *mut Dither = ptr::null_mut();
+        let mut diffusion = settings.diffusion;
+
+        let builtin = match settings.color {
+            WriterColor::Monochrome if settings.invert => Some(BuiltinDither::MonoLight),
+            WriterColor::Monochrome => Some(BuiltinDither::MonoDark),
+            WriterColor::Builtin(palette) => Some(palette),
+            WriterColor::Default if (format as c_int) & (FormatType::Grayscale as c_int) != 0 => match format {
+                PixelFormat::G1 => Some(BuiltinDither::G1),
+                PixelFormat::G2 => Some(BuiltinDither::G2),
+                PixelFormat::G4 => Some(BuiltinDither::G4),
+                PixelFormat::G8 => Some(BuiltinDither::G8),
+                _ => return Err(status::Error::Logic),
+            },
+            _ => None,
+        };
+
+        if let Some(palette) = builtin {
+            dither = sixel_dither_get(palette);
+            if dither.is_null() {
+                return Err(status::Error::Runtime);
+            }
+            if let WriterColor::Default = settings.color {
+                sixel_dither_set_pixelformat(dither, format);
+            }
+        } else if let WriterColor::Highcolor = settings.color {
+            status::from_libsixel(sixel_dither_new(&mut dither, -1, ptr::null_mut()))?;
+        } else {
+            status::from_libsixel(sixel_dither_new(&mut dither, settings.reqcolors, ptr::null_mut()))?;
+            let result = sixel_dither_initialize(dither,
+                                                 pixels.as_mut_ptr(),
+                                                 width as c_int,
+                                                 height as c_int,
+                                                 format,
+                                                 settings.largest,
+                                                 settings.rep,
+                                                 settings.quality);
+            if let Err(e) = status::from_libsixel(result) {
+                sixel_dither_unref(dither);
+                return Err(e);
+            }
+
+            // Few enough colours for an exact palette: nothing to diffuse
+            if sixel_dither_get_num_of_histogram_colors(dither) <= settings.reqcolors {
+                diffusion = DiffusionMethod::None;
+            }
+            sixel_dither_set_pixelformat(dither, format);
+        }
+
+        sixel_dither_set_diffusion_type(dither, diffusion);
+        if settings.complexion > 1 {
+            sixel_dither_set_complexion_score(dither, settings.complexion);
+        }
+        if let WriterColor::Default = settings.color {
+            sixel_dither_set_optimize_palette(dither, 1);
+        }
+
+        Ok(dither)
+    }
+
+    fn update_settings<F: FnOnce(&mut WriterSettings)>(&self, f: F) {
+        let mut settings = self.settings.get();
+        f(&mut settings);
+        self.settings.set(settings);
+    }
+
 }
 
 // Optflags
@@ -122,13 +359,17 @@ impl Encoder {
             BitMode::EightBit => Optflag::UseEightBitMode,
         };
 
-        self.set_opt(mode_flag, ptr::null())
+        self.set_opt(mode_flag, ptr::null())?;
+        self.update_settings(|settings| settings.eight_bit = mode == BitMode::EightBit);
+        Ok(())
     }
 
     pub fn enable_gri_arg_limit(&self) -> Status<()> {
         use std::ptr;
 
-        self.set_opt(Optflag::HasGRIArgLimit, ptr::null())
+        self.set_opt(Optflag::HasGRIArgLimit, ptr::null())?;
+        self.update_settings(|settings| settings.gri_arg_limit = true);
+        Ok(())
     }
 
     pub fn set_num_colors_str(&self, num_colors: &str) -> Status<()> {
@@ -139,7 +380,9 @@ impl Encoder {
             Err(_) => return Err(status::Error::BadArgument),
         };
 
-        self.set_opt(Optflag::NumColors, cstr.as_ptr())
+        self.set_opt(Optflag::NumColors, cstr.as_ptr())?;
+        self.update_settings(|settings| settings.reqcolors = atoi(num_colors));
+        Ok(())
     }
 
     // Calls Encoder::set_colors, but allocates a new String
@@ -163,19 +406,25 @@ impl Encoder {
 
         let cstr = msc::path_to_c_str(file)?;
 
-        self.set_opt(Optflag::Mapfile, cstr.as_ptr())
+        self.set_opt(Optflag::Mapfile, cstr.as_ptr())?;
+        self.update_settings(|settings| settings.unsupported = true);
+        Ok(())
     }
 
     fn use_monochrome(&self) -> Status<()> {
         use std::ptr;
 
-        self.set_opt(Optflag::Monochrome, ptr::null())
+        self.set_opt(Optflag::Monochrome, ptr::null())?;
+        self.update_settings(|settings| settings.color = WriterColor::Monochrome);
+        Ok(())
     }
 
     fn use_high_color(&self) -> Status<()> {
         use std::ptr;
 
-        self.set_opt(Optflag::UseHighColor, ptr::null())
+        self.set_opt(Optflag::UseHighColor, ptr::null())?;
+        self.update_settings(|settings| settings.color = WriterColor::Highcolor);
+        Ok(())
     }
 
     fn use_builtin_palette(&self, option: &str) -> Status<()> {
@@ -186,7 +435,9 @@ impl Encoder {
             Err(_) => return Err(status::Error::BadArgument),
         };
 
-        self.set_opt(Optflag::BuiltinPalette, cstr.as_ptr())
+        self.set_opt(Optflag::BuiltinPalette, cstr.as_ptr())?;
+        self.update_settings(|settings| if let Some(palette) = builtin_from_str(option) { settings.color = WriterColor::Builtin(palette) });
+        Ok(())
     }
 
     pub fn set_diffusion_str(&self, method: &str) -> Status<()> {
@@ -197,7 +448,9 @@ impl Encoder {
             Err(_) => return Err(status::Error::BadArgument),
         };
 
-        self.set_opt(Optflag::Diffusion, cstr.as_ptr())
+        self.set_opt(Optflag::Diffusion, cstr.as_ptr())?;
+        self.update_settings(|settings| if let Some(method) = diffusion_from_str(method) { settings.diffusion = method });
+        Ok(())
     }
 
     pub fn set_diffusion(&self, method: optflags::DiffusionMethod) -> Status<()> {
@@ -212,7 +465,9 @@ impl Encoder {
             Err(_) => return Err(status::Error::BadArgument),
         };
 
-        self.set_opt(Optflag::FindLargest, cstr.as_ptr())
+        self.set_opt(Optflag::FindLargest, cstr.as_ptr())?;
+        self.update_settings(|settings| if let Some(method) = largest_from_str(option) { settings.largest = method });
+        Ok(())
     }
 
     pub fn set_find_largest(&self, opt: optflags::FindLargestOpt) -> Status<()> {
@@ -227,7 +482,9 @@ impl Encoder {
             Err(_) => return Err(status::Error::BadArgument),
         };
 
-        self.set_opt(Optflag::SelectColor, cstr.as_ptr())
+        self.set_opt(Optflag::SelectColor, cstr.as_ptr())?;
+        self.update_settings(|settings| if let Some(method) = rep_from_str(opt) { settings.rep = method });
+        Ok(())
     }
 
     pub fn set_color_select(&self, meth: optflags::ColorSelectionMethod) -> Status<()> {
@@ -242,7 +499,9 @@ impl Encoder {
             Err(_) => return Err(status::Error::BadArgument),
         };
 
-        self.set_opt(Optflag::CropRegion, cstr.as_ptr())
+        self.set_opt(Optflag::CropRegion, cstr.as_ptr())?;
+        self.update_settings(|settings| settings.unsupported = true);
+        Ok(())
     }
 
     pub fn set_crop(&self, width: i64, height: i64, x: i64, y: i64) -> Status<()> {
@@ -258,7 +517,9 @@ impl Encoder {
             Err(_) => return Err(status::Error::BadArgument),
         };
 
-        self.set_opt(Optflag::Width, cstr.as_ptr())
+        self.set_opt(Optflag::Width, cstr.as_ptr())?;
+        self.update_settings(|settings| settings.unsupported |= width != "auto");
+        Ok(())
     }
 
     pub fn set_width(&self, width: optflags::SizeSpecification) -> Status<()> {
@@ -273,7 +534,9 @@ impl Encoder {
             Err(_) => return Err(status::Error::BadArgument),
         };
 
-        self.set_opt(Optflag::Height, cstr.as_ptr())
+        self.set_opt(Optflag::Height, cstr.as_ptr())?;
+        self.update_settings(|settings| settings.unsupported |= height != "auto");
+        Ok(())
     }
 
     pub fn set_height(&self, height: optflags::SizeSpecification) -> Status<()> {
@@ -303,7 +566,9 @@ impl Encoder {
             Err(_) => return Err(status::Error::BadArgument),
         };
 
-        self.set_opt(Optflag::QualityMode, cstr.as_ptr())
+        self.set_opt(Optflag::QualityMode, cstr.as_ptr())?;
+        self.update_settings(|settings| if let Some(quality) = quality_from_str(opt) { settings.quality = quality });
+        Ok(())
     }
 
     pub fn set_quality(&self, opt: optflags::Quality) -> Status<()> {
@@ -333,7 +598,9 @@ impl Encoder {
             Err(_) => return Err(status::Error::BadArgument),
         };
 
-        self.set_opt(Optflag::PaletteType, cstr.as_ptr())
+        self.set_opt(Optflag::PaletteType, cstr.as_ptr())?;
+        self.update_settings(|settings| if let Some(palette_type) = palette_type_from_str(opt) { settings.palette_type = palette_type });
+        Ok(())
     }
 
     pub fn set_palette_type(&self, opt: optflags::PaletteType) -> Status<()> {
@@ -366,13 +633,17 @@ impl Encoder {
     pub fn use_invert(&self) -> Status<()> {
         use std::ptr;
 
-        self.set_opt(Optflag::InvertBackground, ptr::null())
+        self.set_opt(Optflag::InvertBackground, ptr::null())?;
+        self.update_settings(|settings| settings.invert = true);
+        Ok(())
     }
 
     pub fn use_macro(&self) -> Status<()> {
         use std::ptr;
 
-        self.set_opt(Optflag::UseMacro, ptr::null())
+        self.set_opt(Optflag::UseMacro, ptr::null())?;
+        self.update_settings(|settings| settings.unsupported = true);
+        Ok(())
     }
 
     pub fn set_macro_number_str(&self, num: &str) -> Status<()> {
@@ -383,7 +654,9 @@ impl Encoder {
             Err(_) => return Err(status::Error::BadArgument),
         };
 
-        self.set_opt(Optflag::UseMacro, cstr.as_ptr())
+        self.set_opt(Optflag::UseMacro, cstr.as_ptr())?;
+        self.update_settings(|settings| settings.unsupported = true);
+        Ok(())
     }
 
     pub fn set_macro_number(&self, num: i64) -> Status<()> {
@@ -412,7 +685,9 @@ impl Encoder {
     pub fn use_penetrate(&self) -> Status<()> {
         use std::ptr;
 
-        self.set_opt(Optflag::PenetrateScreen, ptr::null())
+        self.set_opt(Optflag::PenetrateScreen, ptr::null())?;
+        self.update_settings(|settings| settings.penetrate = true);
+        Ok(())
     }
 
     pub fn set_encode_policy_str(&self, pol: &str) -> Status<()> {
@@ -423,7 +698,9 @@ impl Encoder {
             Err(_) => return Err(status::Error::BadArgument),
         };
 
-        self.set_opt(Optflag::EncodingPolicy, cstr.as_ptr())
+        self.set_opt(Optflag::EncodingPolicy, cstr.as_ptr())?;
+        self.update_settings(|settings| if let Some(policy) = encode_policy_from_str(pol) { settings.encode_policy = policy });
+        Ok(())
     }
 
     pub fn set_encode_policy(&self, pol: optflags::EncodePolicy) -> Status<()> {
@@ -438,7 +715,9 @@ impl Encoder {
             Err(_) => return Err(status::Error::BadArgument),
         };
 
-        self.set_opt(Optflag::ComplexionScore, cstr.as_ptr())
+        self.set_opt(Optflag::ComplexionScore, cstr.as_ptr())?;
+        self.update_settings(|settings| settings.complexion = atoi(score));
+        Ok(())
     }
 
     pub fn set_complexion_score(&self, score: i64) -> Status<()> {
@@ -459,7 +738,7 @@ impl Clone for Encoder {
             sixel_encoder_ref(self.encoder);
         }
 
-        Encoder { encoder: self.encoder }
+        Encoder { encoder: self.encoder, settings: self.settings.clone() }
     }
 }
 
@@ -472,6 +751,89 @@ impl Drop for Encoder {
 }
 
 
+// Option strings as sixel_encoder_setopt parses them, for `encode_to`
+fn atoi(value: &str) -> raw::c_int {
+    let value = value.trim_start();
+    let end = value.char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || (i == 0 && (c == '-' || c == '+'))))
+        .map_or(value.len(), |(i, _)| i);
+    value[..end].parse().unwrap_or(0)
+}
+
+fn builtin_from_str(value: &str) -> Option<BuiltinDither> {
+    Some(match value {
+        "xterm16" => BuiltinDither::XTerm16,
+        "xterm256" => BuiltinDither::XTerm256,
+        "vt340mono" => BuiltinDither::VT340Mono,
+        "vt340color" => BuiltinDither::VT340Color,
+        "gray1" => BuiltinDither::G1,
+        "gray2" => BuiltinDither::G2,
+        "gray4" => BuiltinDither::G4,
+        "gray8" => BuiltinDither::G8,
+        _ => return None,
+    })
+}
+
+fn diffusion_from_str(value: &str) -> Option<DiffusionMethod> {
+    Some(match value {
+        "auto" => DiffusionMethod::Auto,
+        "none" => DiffusionMethod::None,
+        "fs" => DiffusionMethod::FS,
+        "atkinson" => DiffusionMethod::Atkinson,
+        "jajuni" => DiffusionMethod::JaJuNi,
+        "stucki" => DiffusionMethod::Stucki,
+        "burkes" => DiffusionMethod::Burkes,
+        _ => return None,
+    })
+}
+
+fn largest_from_str(value: &str) -> Option<MethodForLargest> {
+    Some(match value {
+        "auto" => MethodForLargest::Auto,
+        "norm" => MethodForLargest::Normal,
+        "lum" => MethodForLargest::Luminosity,
+        _ => return None,
+    })
+}
+
+fn rep_from_str(value: &str) -> Option<MethodForRepColor> {
+    Some(match value {
+        "auto" => MethodForRepColor::Auto,
+        "center" => MethodForRepColor::CenterOfBox,
+        "average" => MethodForRepColor::AverageColor,
+        "histogram" | "histgram" => MethodForRepColor::AveragePixels,
+        _ => return None,
+    })
+}
+
+fn quality_from_str(value: &str) -> Option<QualityMode> {
+    Some(match value {
+        "auto" => QualityMode::Auto,
+        "high" => QualityMode::High,
+        "low" => QualityMode::Low,
+        "full" => QualityMode::Full,
+        _ => return None,
+    })
+}
+
+fn palette_type_from_str(value: &str) -> Option<PaletteType> {
+    Some(match value {
+        "auto" => PaletteType::Auto,
+        "hls" => PaletteType::HLS,
+        "rgb" => PaletteType::RGB,
+        _ => return None,
+    })
+}
+
+fn encode_policy_from_str(value: &str) -> Option<EncodePolicy> {
+    Some(match value {
+        "auto" => EncodePolicy::Auto,
+        "fast" => EncodePolicy::Fast,
+        "size" => EncodePolicy::Size,
+        _ => return None,
+    })
+}
+
 // TODO: Get working with stack values
 pub struct Canceller {
     flag: Box<Cell<raw::c_int>>,
@@ -18,6 +18,109 @@ pub mod pixelformat;
 
 #[cfg(test)]
 mod tests {
+    use encoder::Encoder;
+    use optflags::{BitMode, DiffusionMethod};
+    use sixel::PixelFormat;
+    use std::fs;
+    use std::io::{self, Write};
+
     #[test]
     fn it_works() {}
+
+    fn gradient(width: usize, height: usize) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(width * height * 3);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.push((x * 255 / width) as u8);
+                pixels.push((y * 255 / height) as u8);
+                pixels.push(((x + y) * 127 / (width + height)) as u8);
+            }
+        }
+        pixels
+    }
+
+    // encode_to must produce exactly what libsixel's encoder writes to a file
+    fn assert_same_as_file<F: Fn(&Encoder)>(name: &str, configure: F) {
+        let (width, height) = (61, 37);
+        let pixels = gradient(width, height);
+
+        let path = std::env::temp_dir().join(format!("sixel-rs-{}-{}.six", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        {
+            let encoder = Encoder::new().unwrap();
+            configure(&encoder);
+            encoder.set_output(&path).unwrap();
+            encoder.encode_bytes_ext(width, height, &pixels, PixelFormat::RGB888).unwrap();
+        }
+        let expected = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let encoder = Encoder::new().unwrap();
+        configure(&encoder);
+        let mut written = Vec::new();
+        encoder.encode_to(&mut written, width, height, &pixels, PixelFormat::RGB888).unwrap();
+
+        assert!(!expected.is_empty());
+        assert!(written == expected, "{}: {} bytes written, {} expected", name, written.len(), expected.len());
+    }
+
+    #[test]
+    fn encode_to_matches_encoder_output() {
+        assert_same_as_file("default", |_| {});
+        assert_same_as_file("options", |encoder| {
+            encoder.set_num_colors(16).unwrap();
+            encoder.set_diffusion(DiffusionMethod::Atkinson).unwrap();
+            encoder.set_bit_mode(BitMode::EightBit).unwrap();
+        });
+    }
+
+    struct FailingWriter;
+
+    impl Write for FailingWriter {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn encode_to_reports_writer_errors() {
+        let encoder = Encoder::new().unwrap();
+        match encoder.encode_to(&mut FailingWriter, 8, 8, &gradient(8, 8), PixelFormat::RGB888) {
+            Err(::status::Error::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::BrokenPipe),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    struct PanickingWriter;
+
+    impl Write for PanickingWriter {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            panic!("writer panicked")
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn encode_to_resumes_writer_panics() {
+        let encoder = Encoder::new().unwrap();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _ = encoder.encode_to(&mut PanickingWriter, 8, 8, &gradient(8, 8), PixelFormat::RGB888);
+        }));
+        let payload = result.unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"writer panicked"));
+    }
+
+    #[test]
+    fn encode_to_rejects_overflowing_sizes() {
+        let encoder = Encoder::new().unwrap();
+        match encoder.encode_to(&mut Vec::new(), 1 << 30, 1 << 30, &[0; 16], PixelFormat::RGB888) {
+            Err(::status::Error::BadArgument) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
 }
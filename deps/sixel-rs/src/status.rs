@@ -22,6 +22,8 @@ pub enum Error {
     LibC,
     Curl,
     Other,
+    /// The `Write` sink given to `Encoder::encode_to` failed
+    Io(std::io::Error),
 }
 
 #[doc(hidden)]
@@ -0,0 +1,74 @@
+// Animations : "platine" (le modèle fait un tour complet autour d'un axe) ou
+// interpolation entre des poses clés. Dans les deux cas une Transform est
+// ajoutée en fin de chaîne pour chaque image.
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+
+use crate::math_3d::{Quat, Transform, Vec3};
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    pub fn vector(self) -> Vec3 {
+        match self {
+            Axis::X => Vec3::new(1.0, 0.0, 0.0),
+            Axis::Y => Vec3::new(0.0, 1.0, 0.0),
+            Axis::Z => Vec3::new(0.0, 0.0, 1.0),
+        }
+    }
+}
+
+pub struct Turntable {
+    pub frames: usize,
+    pub axis: Axis,
+}
+
+impl Turntable {
+    pub fn angle(&self, frame: usize) -> f32 {
+        2.0 * std::f32::consts::PI * (frame % self.frames.max(1)) as f32 / self.frames.max(1) as f32
+    }
+
+    pub fn transform(&self, frame: usize) -> Transform {
+        let q = Quat::from_axis_angle(self.axis.vector(), self.angle(frame));
+        Transform::IDENTITY.with_orientation(q)
+    }
+}
+
+// Poses clés régulièrement espacées sur la durée de l'animation
+pub struct Keyframes {
+    pub keys: Vec<Transform>,
+}
+
+impl Keyframes {
+    // t dans [0, 1] : début -> fin de l'animation
+    pub fn sample(&self, t: f32) -> Transform {
+        match self.keys.len() {
+            0 => Transform::IDENTITY,
+            1 => self.keys[0],
+            n => {
+                let pos = t.clamp(0.0, 1.0) * (n - 1) as f32;
+                let i = (pos.floor() as usize).min(n - 2);
+                self.keys[i].interpolate(&self.keys[i + 1], pos - i as f32)
+            }
+        }
+    }
+
+    // Image `frame` sur `frames` : la première et la dernière tombent sur les poses extrêmes
+    pub fn transform(&self, frame: usize, frames: usize) -> Transform {
+        let t = if frames > 1 { frame as f32 / (frames - 1) as f32 } else { 0.0 };
+        self.sample(t)
+    }
+}
+
+// "out/spin", "six" -> "out/spin_0007.six"
+pub fn frame_path(prefix: &Path, frame: usize, extension: &str) -> PathBuf {
+    let mut name = prefix.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(format!("_{:04}.{}", frame, extension));
+    prefix.with_file_name(name)
+}
@@ -0,0 +1,68 @@
+// Base64 standard (RFC 4648, avec remplissage '='), pour les protocoles d'image kitty et iTerm2
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+// Décodage, pour vérifier les sorties kitty et iTerm2 dans les tests
+#[cfg(test)]
+pub fn decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return None;
+    }
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    for chunk in text.chunks(4) {
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        let mut n = 0u32;
+        for &c in &chunk[..4 - padding] {
+            n = n << 6 | ALPHABET.iter().position(|&a| a == c)? as u32;
+        }
+        n <<= 6 * padding;
+        out.extend_from_slice(&n.to_be_bytes()[1..4 - padding]);
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc4648_vectors() {
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (data, expected) in vectors {
+            assert_eq!(encode(data.as_bytes()), expected);
+            assert_eq!(decode(expected).unwrap(), data.as_bytes());
+        }
+    }
+
+    #[test]
+    fn all_bytes() {
+        let data: Vec<u8> = (0..=255).collect();
+        let text = encode(&data);
+        assert!(text.bytes().all(|c| ALPHABET.contains(&c) || c == b'='));
+        assert_eq!(decode(&text).unwrap(), data);
+        assert!(text.starts_with("AAECAwQF") && text.ends_with("+/w=="), "{}", text);
+    }
+}
@@ -0,0 +1,479 @@
+// Hiérarchie de volumes englobants (BVH) des triangles du lancer de rayons.
+// Construite une fois (heuristique SAH évaluée par classes), parcourue de l'avant vers
+// l'arrière en ignorant les boîtes au-delà de l'impact le plus proche, et réajustable (refit)
+// quand les sommets bougent sans que la liste des triangles change (animation).
+use crate::math_3d::Vec3;
+use crate::math_3d::raytrace::intersect_triangle_barycentric;
+
+// Classes (bins) par axe pour évaluer la SAH
+const BINS: usize = 12;
+// Coût d'une traversée de nœud, relatif au test d'un triangle
+const TRAVERSAL_COST: f32 = 1.0;
+// Au-delà, une feuille est découpée même si la SAH ne le conseille pas
+const MAX_LEAF: usize = 8;
+// Profondeur maximale : borne la pile du parcours
+const MAX_DEPTH: usize = 48;
+
+#[derive(Debug, Clone, Copy)]
+struct Aabb {
+    min: Vec3,
+    max: Vec3,
+}
+
+impl Aabb {
+    const EMPTY: Aabb = Aabb {
+        min: Vec3 { x: f32::MAX, y: f32::MAX, z: f32::MAX },
+        max: Vec3 { x: f32::MIN, y: f32::MIN, z: f32::MIN },
+    };
+
+    fn of_triangle(t: &[Vec3; 3]) -> Aabb {
+        let mut b = Aabb::EMPTY;
+        for p in t {
+            b.grow_point(*p);
+        }
+        b
+    }
+
+    fn grow_point(&mut self, p: Vec3) {
+        self.min = Vec3::new(self.min.x.min(p.x), self.min.y.min(p.y), self.min.z.min(p.z));
+        self.max = Vec3::new(self.max.x.max(p.x), self.max.y.max(p.y), self.max.z.max(p.z));
+    }
+
+    // Union (une boîte vide ne change rien)
+    fn grow(&mut self, other: &Aabb) {
+        let (a, b) = (other.min, other.max);
+        self.min = Vec3::new(self.min.x.min(a.x), self.min.y.min(a.y), self.min.z.min(a.z));
+        self.max = Vec3::new(self.max.x.max(b.x), self.max.y.max(b.y), self.max.z.max(b.z));
+    }
+
+    // Demi-surface : seul le rapport entre boîtes compte pour la SAH
+    fn area(&self) -> f32 {
+        let e = self.max.sub(self.min);
+        if e.x < 0.0 {
+            return 0.0;
+        }
+        e.x * e.y + e.y * e.z + e.z * e.x
+    }
+
+    // Test des plans (slab) : distance d'entrée dans la boîte si elle coupe [t_min, t_max]
+    fn entry(&self, origin: Vec3, inv_dir: Vec3, t_min: f32, t_max: f32) -> Option<f32> {
+        let (tx0, tx1) = ((self.min.x - origin.x) * inv_dir.x, (self.max.x - origin.x) * inv_dir.x);
+        let (ty0, ty1) = ((self.min.y - origin.y) * inv_dir.y, (self.max.y - origin.y) * inv_dir.y);
+        let (tz0, tz1) = ((self.min.z - origin.z) * inv_dir.z, (self.max.z - origin.z) * inv_dir.z);
+        let near = tx0.min(tx1).max(ty0.min(ty1)).max(tz0.min(tz1)).max(t_min);
+        let far = tx0.max(tx1).min(ty0.max(ty1)).min(tz0.max(tz1)).min(t_max);
+        (near <= far).then_some(near)
+    }
+}
+
+fn axis(v: Vec3, a: usize) -> f32 {
+    match a {
+        0 => v.x,
+        1 => v.y,
+        _ => v.z,
+    }
+}
+
+// count = 0 : nœud interne dont les enfants sont first et first + 1,
+// sinon feuille des triangles first..first + count (ordre des feuilles)
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    bounds: Aabb,
+    first: usize,
+    count: usize,
+}
+
+impl Node {
+    fn is_leaf(&self) -> bool {
+        self.count > 0
+    }
+}
+
+pub struct Bvh {
+    nodes: Vec<Node>,
+    // Triangles rangés dans l'ordre des feuilles, et leur index dans la liste d'origine
+    triangles: Vec<[Vec3; 3]>,
+    indices: Vec<usize>,
+}
+
+// Impact le plus proche : index du triangle dans la liste d'origine, barycentriques (u, v)
+#[derive(Debug, Clone, Copy)]
+pub struct BvhHit {
+    pub index: usize,
+    pub t: f32,
+    pub u: f32,
+    pub v: f32,
+}
+
+impl Bvh {
+    pub fn build(triangles: &[[Vec3; 3]]) -> Bvh {
+        let mut indices: Vec<usize> = (0..triangles.len()).collect();
+        let mut nodes = Vec::with_capacity(triangles.len() * 2);
+        if !triangles.is_empty() {
+            let bounds: Vec<Aabb> = triangles.iter().map(Aabb::of_triangle).collect();
+            let centroids: Vec<Vec3> = triangles
+                .iter()
+                .map(|[a, b, c]| a.add(*b).add(*c).mul(1.0 / 3.0))
+                .collect();
+            nodes.push(Node { bounds: Aabb::EMPTY, first: 0, count: triangles.len() });
+            subdivide(&mut nodes, 0, &mut indices, &bounds, &centroids, 0);
+        }
+        let triangles = indices.iter().map(|&i| triangles[i]).collect();
+        Bvh { nodes, triangles, indices }
+    }
+
+    pub fn len(&self) -> usize {
+        self.triangles.len()
+    }
+
+    // Sommets déplacés, mêmes triangles dans le même ordre qu'à la construction :
+    // seules les boîtes sont recalculées, des feuilles vers la racine
+    pub fn refit(&mut self, triangles: &[[Vec3; 3]]) {
+        debug_assert_eq!(triangles.len(), self.indices.len());
+        for (slot, &i) in self.triangles.iter_mut().zip(&self.indices) {
+            *slot = triangles[i];
+        }
+        // Les enfants sont toujours après leur parent
+        for idx in (0..self.nodes.len()).rev() {
+            let node = self.nodes[idx];
+            let mut bounds = Aabb::EMPTY;
+            if node.is_leaf() {
+                for t in &self.triangles[node.first..node.first + node.count] {
+                    bounds.grow(&Aabb::of_triangle(t));
+                }
+            } else {
+                bounds.grow(&self.nodes[node.first].bounds);
+                bounds.grow(&self.nodes[node.first + 1].bounds);
+            }
+            self.nodes[idx].bounds = bounds;
+        }
+    }
+
+    // Impact le plus proche dans ]t_min, t_max[ : l'enfant le plus proche est visité d'abord
+    // et une boîte dont l'entrée est au-delà du meilleur impact n'est pas ouverte
+    pub fn intersect(&self, origin: Vec3, dir: Vec3, t_min: f32, t_max: f32) -> Option<BvhHit> {
+        let inv_dir = Vec3::new(1.0 / dir.x, 1.0 / dir.y, 1.0 / dir.z);
+        let mut best: Option<BvhHit> = None;
+        let mut t_max = t_max;
+
+        let root = self.nodes.first()?;
+        let entry = root.bounds.entry(origin, inv_dir, t_min, t_max)?;
+        let mut stack = [(0usize, 0f32); MAX_DEPTH + 2];
+        stack[0] = (0, entry);
+        let mut len = 1;
+
+        while len > 0 {
+            len -= 1;
+            let (idx, entry) = stack[len];
+            if entry > t_max {
+                continue;
+            }
+            let node = &self.nodes[idx];
+            if node.is_leaf() {
+                for slot in node.first..node.first + node.count {
+                    let [v0, v1, v2] = self.triangles[slot];
+                    if let Some((t, u, v)) = intersect_triangle_barycentric(origin, dir, v0, v1, v2)
+                        && t > t_min
+                        && t < t_max
+                    {
+                        t_max = t;
+                        best = Some(BvhHit { index: self.indices[slot], t, u, v });
+                    }
+                }
+                continue;
+            }
+
+            let (left, right) = (node.first, node.first + 1);
+            let hit_left = self.nodes[left].bounds.entry(origin, inv_dir, t_min, t_max);
+            let hit_right = self.nodes[right].bounds.entry(origin, inv_dir, t_min, t_max);
+            // Le plus proche est empilé en dernier pour être dépilé en premier
+            let mut children = [(left, hit_left), (right, hit_right)];
+            if let (Some(l), Some(r)) = (hit_left, hit_right)
+                && r > l
+            {
+                children.swap(0, 1);
+            }
+            for (child, hit) in children {
+                if let Some(entry) = hit {
+                    stack[len] = (child, entry);
+                    len += 1;
+                }
+            }
+        }
+        best
+    }
+
+    // Tous les impacts dans ]t_min, t_max[, dans un ordre quelconque (rayons d'ombre) :
+    // `visit(index, t)` renvoie false pour arrêter le parcours
+    pub fn for_each_hit(&self, origin: Vec3, dir: Vec3, t_min: f32, t_max: f32, mut visit: impl FnMut(usize, f32) -> bool) {
+        let inv_dir = Vec3::new(1.0 / dir.x, 1.0 / dir.y, 1.0 / dir.z);
+        if self.nodes.is_empty() {
+            return;
+        }
+        let mut stack = [0usize; MAX_DEPTH + 2];
+        let mut len = 1;
+
+        while len > 0 {
+            len -= 1;
+            let node = &self.nodes[stack[len]];
+            if node.bounds.entry(origin, inv_dir, t_min, t_max).is_none() {
+                continue;
+            }
+            if node.is_leaf() {
+                for slot in node.first..node.first + node.count {
+                    let [v0, v1, v2] = self.triangles[slot];
+                    if let Some((t, _, _)) = intersect_triangle_barycentric(origin, dir, v0, v1, v2)
+                        && t > t_min
+                        && t < t_max
+                        && !visit(self.indices[slot], t)
+                    {
+                        return;
+                    }
+                }
+            } else {
+                stack[len] = node.first;
+                stack[len + 1] = node.first + 1;
+                len += 2;
+            }
+        }
+    }
+}
+
+// Découpe le nœud idx (triangles indices[first..first + count]) selon la meilleure coupe SAH
+fn subdivide(nodes: &mut Vec<Node>, idx: usize, indices: &mut [usize], bounds: &[Aabb], centroids: &[Vec3], depth: usize) {
+    let Node { first, count, .. } = nodes[idx];
+    let range = &mut indices[first..first + count];
+
+    let mut node_bounds = Aabb::EMPTY;
+    let mut centroid_bounds = Aabb::EMPTY;
+    for &i in range.iter() {
+        node_bounds.grow(&bounds[i]);
+        centroid_bounds.grow_point(centroids[i]);
+    }
+    nodes[idx].bounds = node_bounds;
+    if count <= 2 || depth >= MAX_DEPTH {
+        return;
+    }
+
+    // Coût de chaque coupe entre deux classes, sur les trois axes
+    let mut best: Option<(f32, usize, usize)> = None;
+    for a in 0..3 {
+        let (lo, hi) = (axis(centroid_bounds.min, a), axis(centroid_bounds.max, a));
+        if hi - lo <= 0.0 {
+            continue;
+        }
+        let scale = BINS as f32 / (hi - lo);
+        let mut bin_bounds = [Aabb::EMPTY; BINS];
+        let mut bin_counts = [0usize; BINS];
+        for &i in range.iter() {
+            let b = (((axis(centroids[i], a) - lo) * scale) as usize).min(BINS - 1);
+            bin_bounds[b].grow(&bounds[i]);
+            bin_counts[b] += 1;
+        }
+
+        // Balayage de droite à gauche pour les surfaces et effectifs cumulés à droite
+        let mut right_area = [0.0f32; BINS];
+        let mut right_count = [0usize; BINS];
+        let (mut acc, mut n) = (Aabb::EMPTY, 0);
+        for b in (1..BINS).rev() {
+            acc.grow(&bin_bounds[b]);
+            n += bin_counts[b];
+            right_area[b] = acc.area();
+            right_count[b] = n;
+        }
+        let (mut acc, mut n) = (Aabb::EMPTY, 0);
+        for b in 0..BINS - 1 {
+            acc.grow(&bin_bounds[b]);
+            n += bin_counts[b];
+            if n == 0 || right_count[b + 1] == 0 {
+                continue;
+            }
+            let cost = acc.area() * n as f32 + right_area[b + 1] * right_count[b + 1] as f32;
+            if best.is_none_or(|(c, _, _)| cost < c) {
+                best = Some((cost, a, b));
+            }
+        }
+    }
+
+    let area = node_bounds.area().max(f32::MIN_POSITIVE);
+    let split = match best {
+        Some((cost, a, b)) if TRAVERSAL_COST + cost / area < count as f32 || count > MAX_LEAF => {
+            // Partition en place : classes 0..=b à gauche
+            let (lo, hi) = (axis(centroid_bounds.min, a), axis(centroid_bounds.max, a));
+            let scale = BINS as f32 / (hi - lo);
+            let mut left = 0;
+            for j in 0..count {
+                let bin = (((axis(centroids[range[j]], a) - lo) * scale) as usize).min(BINS - 1);
+                if bin <= b {
+                    range.swap(left, j);
+                    left += 1;
+                }
+            }
+            left
+        }
+        // Centres confondus : coupe au milieu pour ne pas garder une feuille trop grosse
+        None if count > MAX_LEAF => count / 2,
+        _ => return,
+    };
+    if split == 0 || split == count {
+        return;
+    }
+
+    let left = nodes.len();
+    nodes.push(Node { bounds: Aabb::EMPTY, first, count: split });
+    nodes.push(Node { bounds: Aabb::EMPTY, first: first + split, count: count - split });
+    nodes[idx].first = left;
+    nodes[idx].count = 0;
+    subdivide(nodes, left, indices, bounds, centroids, depth + 1);
+    subdivide(nodes, left + 1, indices, bounds, centroids, depth + 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // xorshift déterministe, valeurs dans [-1, 1[
+    struct Rng(u32);
+
+    impl Rng {
+        fn next(&mut self) -> f32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            (self.0 >> 8) as f32 / (1u32 << 23) as f32 - 1.0
+        }
+
+        fn vec3(&mut self, scale: f32) -> Vec3 {
+            Vec3::new(self.next(), self.next(), self.next()).mul(scale)
+        }
+    }
+
+    // Petits triangles éparpillés dans un cube de côté 4
+    fn random_triangles(rng: &mut Rng, count: usize) -> Vec<[Vec3; 3]> {
+        (0..count)
+            .map(|_| {
+                let c = rng.vec3(2.0);
+                [c.add(rng.vec3(0.3)), c.add(rng.vec3(0.3)), c.add(rng.vec3(0.3))]
+            })
+            .collect()
+    }
+
+    // Rayons partant de l'extérieur vers un point de la scène
+    fn random_rays(rng: &mut Rng, count: usize) -> Vec<(Vec3, Vec3)> {
+        (0..count)
+            .map(|_| {
+                let origin = rng.vec3(6.0);
+                (origin, rng.vec3(1.5).sub(origin).normalize())
+            })
+            .collect()
+    }
+
+    fn brute_force(triangles: &[[Vec3; 3]], origin: Vec3, dir: Vec3, t_min: f32, t_max: f32) -> Vec<(usize, f32)> {
+        triangles
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &[v0, v1, v2])| {
+                let (t, _, _) = intersect_triangle_barycentric(origin, dir, v0, v1, v2)?;
+                (t > t_min && t < t_max).then_some((i, t))
+            })
+            .collect()
+    }
+
+    fn closest(hits: &[(usize, f32)]) -> Option<(usize, f32)> {
+        hits.iter().copied().min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    fn all_hits(bvh: &Bvh, origin: Vec3, dir: Vec3, t_min: f32, t_max: f32) -> Vec<(usize, f32)> {
+        let mut hits = Vec::new();
+        bvh.for_each_hit(origin, dir, t_min, t_max, |index, t| {
+            hits.push((index, t));
+            true
+        });
+        hits.sort_by_key(|h| h.0);
+        hits
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = Rng(0x2545_f491);
+        let triangles = random_triangles(&mut rng, 500);
+        let bvh = Bvh::build(&triangles);
+        assert_eq!(bvh.len(), triangles.len());
+
+        let mut hit_count = 0;
+        for (origin, dir) in random_rays(&mut rng, 2000) {
+            let expected = brute_force(&triangles, origin, dir, 1e-4, f32::MAX);
+            let hit = bvh.intersect(origin, dir, 1e-4, f32::MAX);
+            // Même distance (deux triangles peuvent être à égalité)
+            assert_eq!(hit.map(|h| h.t), closest(&expected).map(|h| h.1));
+            if let Some(h) = hit {
+                assert!(expected.contains(&(h.index, h.t)));
+                hit_count += 1;
+            }
+            assert_eq!(all_hits(&bvh, origin, dir, 1e-4, f32::MAX), expected);
+        }
+        // Les rayons visent la scène : une bonne part doit toucher quelque chose
+        assert!(hit_count > 200, "{}", hit_count);
+    }
+
+    #[test]
+    fn respects_interval_and_stop() {
+        let mut rng = Rng(0x1234_5679);
+        let triangles = random_triangles(&mut rng, 200);
+        let bvh = Bvh::build(&triangles);
+        for (origin, dir) in random_rays(&mut rng, 500) {
+            let expected = brute_force(&triangles, origin, dir, 5.0, 7.0);
+            assert_eq!(bvh.intersect(origin, dir, 5.0, 7.0).map(|h| h.t), closest(&expected).map(|h| h.1));
+            assert_eq!(all_hits(&bvh, origin, dir, 5.0, 7.0), expected);
+
+            // Arrêt au premier impact
+            let mut visited = 0;
+            bvh.for_each_hit(origin, dir, 5.0, 7.0, |_, _| {
+                visited += 1;
+                false
+            });
+            assert_eq!(visited, expected.len().min(1));
+        }
+    }
+
+    #[test]
+    fn refit_matches_rebuild() {
+        let mut rng = Rng(0x0bad_cafe);
+        let triangles = random_triangles(&mut rng, 300);
+        let mut bvh = Bvh::build(&triangles);
+
+        // Déformation : translation différente pour chaque triangle
+        let moved: Vec<[Vec3; 3]> = triangles
+            .iter()
+            .map(|t| {
+                let offset = rng.vec3(1.0);
+                t.map(|p| p.add(offset))
+            })
+            .collect();
+        bvh.refit(&moved);
+        let rebuilt = Bvh::build(&moved);
+
+        for (origin, dir) in random_rays(&mut rng, 2000) {
+            let refit_hit = bvh.intersect(origin, dir, 1e-4, f32::MAX).map(|h| h.t);
+            assert_eq!(refit_hit, rebuilt.intersect(origin, dir, 1e-4, f32::MAX).map(|h| h.t));
+            assert_eq!(refit_hit, closest(&brute_force(&moved, origin, dir, 1e-4, f32::MAX)).map(|h| h.1));
+            assert_eq!(
+                all_hits(&bvh, origin, dir, 1e-4, f32::MAX),
+                all_hits(&rebuilt, origin, dir, 1e-4, f32::MAX)
+            );
+        }
+    }
+
+    #[test]
+    fn empty_and_degenerate() {
+        let empty = Bvh::build(&[]);
+        assert!(empty.intersect(Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 0.0, 1.0), 0.0, f32::MAX).is_none());
+        empty.for_each_hit(Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 0.0, 1.0), 0.0, f32::MAX, |_, _| panic!());
+
+        // Triangles superposés (centres confondus) : découpe au milieu, tous trouvés
+        let t = [Vec3::new(-1.0, -1.0, 0.0), Vec3::new(1.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0)];
+        let bvh = Bvh::build(&[t; 40]);
+        let hits = all_hits(&bvh, Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 0.0, 1.0), 0.0, f32::MAX);
+        assert_eq!(hits.len(), 40);
+    }
+}
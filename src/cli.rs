@@ -0,0 +1,732 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::animation::Axis;
+use crate::environment::EnvironmentSpec;
+use crate::light::{Light, NO_ATTENUATION};
+use crate::output::{Protocol, SixelEncoder};
+use crate::shadow_map;
+use crate::math_3d::{Camera, Color, Material, MaterialRaytrace, Point3d, Projection, Transform, Vec3};
+
+#[derive(Parser)]
+#[command(name = "sixel-3d", version, about = "Rendu de modèles wavefront (.obj) en sixel dans le terminal")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Rasterisation, éclairage calculé aux sommets puis interpolé (Gouraud)
+    Gouraud(RenderArgs),
+    /// Rasterisation, éclairage calculé pour chaque pixel (Phong)
+    Phong(RenderArgs),
+    /// Lancer de rayons (BVH, réflexions, transparence)
+    Raytrace(RenderArgs),
+    /// Path tracing : éclairage global par Monte-Carlo (rebonds diffus, objets émissifs)
+    Pathtrace(RenderArgs),
+    /// Animation : le modèle tourne sur lui-même (platine)
+    Animate(AnimateArgs),
+    /// Rendu d'un fichier de scène TOML (modèles, matériaux, lumière, caméra, moteur)
+    Scene(SceneArgs),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Gouraud,
+    Phong,
+    Raytrace,
+    Pathtrace,
+}
+
+impl Command {
+    // None pour une scène : les réglages sont lus dans le fichier
+    pub fn args(&self) -> Option<&RenderArgs> {
+        match self {
+            Command::Gouraud(a) | Command::Phong(a) | Command::Raytrace(a) | Command::Pathtrace(a) => Some(a),
+            Command::Animate(a) => Some(&a.render),
+            Command::Scene(_) => None,
+        }
+    }
+
+    pub fn args_mut(&mut self) -> Option<&mut RenderArgs> {
+        match self {
+            Command::Gouraud(a) | Command::Phong(a) | Command::Raytrace(a) | Command::Pathtrace(a) => Some(a),
+            Command::Animate(a) => Some(&mut a.render),
+            Command::Scene(_) => None,
+        }
+    }
+
+    pub fn format(&self) -> &OutputArgs {
+        match self {
+            Command::Gouraud(a) | Command::Phong(a) | Command::Raytrace(a) | Command::Pathtrace(a) => &a.format,
+            Command::Animate(a) => &a.render.format,
+            Command::Scene(s) => &s.format,
+        }
+    }
+
+    // Images affichées dans le terminal, pas écrites dans des fichiers
+    pub fn to_terminal(&self) -> bool {
+        match self {
+            Command::Scene(s) => s.output.is_none(),
+            Command::Animate(a) => a.render.output.is_none() && a.sequence.is_none(),
+            command => command.args().is_some_and(|a| a.output.is_none()),
+        }
+    }
+
+    pub fn backend(&self) -> Option<Backend> {
+        match self {
+            Command::Gouraud(_) => Some(Backend::Gouraud),
+            Command::Phong(_) => Some(Backend::Phong),
+            Command::Raytrace(_) => Some(Backend::Raytrace),
+            Command::Pathtrace(_) => Some(Backend::Pathtrace),
+            Command::Animate(a) => Some(a.backend),
+            Command::Scene(s) => s.backend,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct SceneArgs {
+    /// Fichier de scène (.toml)
+    pub file: PathBuf,
+
+    /// Moteur de rendu, remplace celui du fichier (renderer)
+    #[arg(short, long, value_enum)]
+    pub backend: Option<Backend>,
+
+    /// Écrit le sixel dans un fichier au lieu de la sortie standard
+    #[arg(short, long, conflicts_with = "interactive")]
+    pub output: Option<PathBuf>,
+
+    /// Mode interactif : flèches/WASD pour tourner, +/- pour zoomer, r pour revenir, q pour quitter
+    #[arg(short, long)]
+    pub interactive: bool,
+
+    #[command(flatten)]
+    pub format: OutputArgs,
+}
+
+// Format des images, commun aux commandes de rendu et aux scènes
+#[derive(Args, Clone, Copy)]
+pub struct OutputArgs {
+    /// Protocole d'image du terminal : sixel, kitty (kitty, WezTerm, Ghostty) ou iterm (iTerm2),
+    /// ou texte sans image : blocks (demi-blocs, couleurs 24 bits), braille ou ascii (16 couleurs).
+    /// auto : d'après TERM, TERM_PROGRAM, KITTY_WINDOW_ID, LC_TERMINAL, TMUX et COLORTERM,
+    /// sixel à défaut
+    #[arg(long, value_enum, default_value_t = Protocol::Auto)]
+    pub protocol: Protocol,
+
+    /// Modes texte : largeur en caractères (par défaut celle du terminal, sinon 80)
+    #[arg(long)]
+    pub columns: Option<usize>,
+
+    /// Encodeur sixel : libsixel (bibliothèque C) ou native (en Rust, sans dépendance)
+    #[arg(long, value_enum, default_value_t = SixelEncoder::DEFAULT)]
+    pub sixel_encoder: SixelEncoder,
+
+    /// Protocole kitty : compresse l'image en zlib avant de l'envoyer
+    #[arg(long)]
+    pub kitty_zlib: bool,
+}
+
+#[derive(Args)]
+pub struct AnimateArgs {
+    /// Moteur de rendu utilisé pour chaque image
+    #[arg(short, long, value_enum, default_value_t = Backend::Gouraud)]
+    pub backend: Backend,
+
+    /// Nombre d'images pour un tour complet
+    #[arg(short = 'n', long, default_value_t = 36)]
+    pub frames: usize,
+
+    /// Axe de rotation
+    #[arg(long, value_enum, default_value_t = Axis::Y)]
+    pub axis: Axis,
+
+    /// Délai entre deux images en millisecondes (sortie terminal)
+    #[arg(long, default_value_t = 40)]
+    pub delay: u64,
+
+    /// Nombre de tours, 0 pour boucler indéfiniment (sortie terminal)
+    #[arg(long, default_value_t = 1)]
+    pub loops: usize,
+
+    /// Pose clé (même syntaxe que --transform), répétable : l'animation passe par
+    /// chaque pose (slerp pour l'orientation) au lieu de tourner sur l'axe
+    #[arg(short, long = "key", value_parser = parse_transform, allow_hyphen_values = true)]
+    pub keys: Vec<Transform>,
+
+    /// Écrit une image par fichier : PREFIX_0000.six, PREFIX_0001.six, ...
+    /// (.kitty, .iterm ou .txt selon le protocole)
+    #[arg(long, value_name = "PREFIX", conflicts_with = "output")]
+    pub sequence: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub render: RenderArgs,
+}
+
+#[derive(Args)]
+pub struct RenderArgs {
+    /// Modèle wavefront à afficher, ou modèle intégré : builtin:penger, builtin:cube
+    pub model: PathBuf,
+
+    /// Largeur de l'image en pixels
+    #[arg(short = 'W', long, default_value_t = 1280, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub width: usize,
+
+    /// Hauteur de l'image en pixels
+    #[arg(short = 'H', long, default_value_t = 1280, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub height: usize,
+
+    /// Position de l'oeil "x,y,z"
+    #[arg(long, value_parser = parse_point3d, default_value = "0,0,210", allow_hyphen_values = true)]
+    pub eye: Point3d,
+
+    /// Point visé par la caméra "x,y,z"
+    #[arg(long, value_parser = parse_point3d, default_value = "0,0,0", allow_hyphen_values = true)]
+    pub target: Point3d,
+
+    /// Vecteur "haut" de la caméra "x,y,z"
+    #[arg(long, value_parser = parse_vec3, default_value = "0,1,0", allow_hyphen_values = true)]
+    pub up: Vec3,
+
+    /// Focale en pixels (par défaut 2 x largeur)
+    #[arg(long, conflicts_with_all = ["fov", "ortho"], value_parser = parse_focal)]
+    pub focal: Option<f32>,
+
+    /// Champ de vision vertical en degrés, strictement entre 0 et 180
+    #[arg(long, conflicts_with = "ortho", value_parser = parse_fov)]
+    pub fov: Option<f32>,
+
+    /// Projection orthographique : hauteur visible en unités du modèle
+    #[arg(long, value_name = "HEIGHT", value_parser = parse_ortho)]
+    pub ortho: Option<f32>,
+
+    /// Plan de coupe proche (distance à l'oeil, positive)
+    #[arg(long, default_value_t = 0.1, value_parser = parse_near)]
+    pub near: f32,
+
+    /// Plan de coupe lointain (distance à l'oeil, au-delà de --near)
+    #[arg(long, default_value_t = f32::INFINITY)]
+    pub far: f32,
+
+    /// Source de lumière, répétable : "x,y,z" (direction vers une lumière blanche), ou
+    /// "directional:dir=X,Y,Z", "point:pos=X,Y,Z[:atten=C,L,Q]",
+    /// "spot:pos=X,Y,Z:dir=X,Y,Z[:angles=INTERIEUR,EXTERIEUR][:atten=C,L,Q]" (degrés),
+    /// "area:pos=X,Y,Z:u=X,Y,Z:v=X,Y,Z[:atten=C,L,Q]" (panneau de côtés u et v, ombres douces),
+    /// chacune avec ":color=R,G,B" (0.0..1.0) et ":intensity=I" optionnels
+    #[arg(long = "light", value_parser = parse_light, default_value = "-0.5,1,1", allow_hyphen_values = true)]
+    pub lights: Vec<Light>,
+
+    /// Rayons d'ombre par point pour les lumières surfaciques (raytrace) : ombres douces
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u32).range(1..))]
+    pub shadow_samples: u32,
+
+    /// Anticrénelage, N x N échantillons par pixel : les rasteriseurs rendent l'image N fois
+    /// plus grande puis la réduisent, le raytracer lance des rayons stratifiés et jittérés
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=8))]
+    pub aa: u32,
+
+    /// Échantillons par pixel du path tracing (l'image s'affine passe après passe)
+    #[arg(long, default_value_t = 64, value_parser = clap::value_parser!(u32).range(1..))]
+    pub spp: u32,
+
+    /// Graine du path tracing : même graine, même image (tirée au hasard par défaut)
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Taille (texels) des cartes d'ombre des rasteriseurs, par exemple 1024 (au plus 4096) :
+    /// ombres désactivées par défaut (0), elles sont recalculées à chaque image
+    #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u32).range(0..=shadow_map::MAX_SIZE as i64))]
+    pub shadow_map_size: u32,
+
+    /// Rayon du filtrage PCF des cartes d'ombre en texels (1 : 3x3, 0 : ombres dures)
+    #[arg(long, default_value_t = 1)]
+    pub shadow_pcf: u32,
+
+    /// Biais de profondeur des cartes d'ombre en texels (contre l'acné d'ombre)
+    #[arg(long, default_value_t = 1.5)]
+    pub shadow_bias: f32,
+
+    /// Préréglage de matériau (ex: gold, white_plastic, epic_slayer), pour les faces sans usemtl
+    #[arg(short, long, value_parser = parse_material)]
+    pub material: Option<String>,
+
+    /// Ignore les matériaux .mtl du modèle (usemtl) et utilise --material partout
+    #[arg(long)]
+    pub no_mtl: bool,
+
+    /// Angle de pli en degrés pour les normales calculées (modèles sans vn) : au-delà l'arête reste vive
+    #[arg(long, default_value_t = 60.0)]
+    pub crease_angle: f32,
+
+    /// Transformation "scale=S:rotate=X,Y,Z:translate=X,Y,Z" (degrés), répétable et appliquée dans l'ordre
+    #[arg(short, long = "transform", value_parser = parse_transform, allow_hyphen_values = true)]
+    pub transforms: Vec<Transform>,
+
+    /// Couleur de fond des rasteriseurs "r,g,b"
+    #[arg(long, value_parser = parse_color, default_value = "0,128,255")]
+    pub background: Color,
+
+    /// Environnement (fond, ciel des réflexions, éclairage du path tracing) : "sky" (dégradé
+    /// par défaut du raytracer), "solid:R,G,B", "gradient:bottom=R,G,B:top=R,G,B" ou
+    /// "map:FICHIER[:intensity=I][:rotate=DEGRES]" (image équirectangulaire PFM, HDR, PPM...).
+    /// Sans cette option : --background pour les rasteriseurs, "sky" pour les lancers de rayons
+    #[arg(long = "env", value_parser = parse_environment)]
+    pub environment: Option<EnvironmentSpec>,
+
+    /// Écrit le sixel dans un fichier au lieu de la sortie standard
+    #[arg(short, long, conflicts_with = "interactive")]
+    pub output: Option<PathBuf>,
+
+    /// Mode interactif : flèches/WASD pour tourner, +/- pour zoomer, r pour revenir, q pour quitter
+    #[arg(short, long)]
+    pub interactive: bool,
+
+    #[command(flatten)]
+    pub format: OutputArgs,
+
+    // Ni -W ni -H : la taille peut suivre celle du terminal
+    #[arg(skip)]
+    pub default_size: bool,
+}
+
+// Valeurs par défaut de RenderArgs, telles que la ligne de commande les donne
+#[derive(Parser)]
+struct Defaults {
+    #[command(flatten)]
+    render: RenderArgs,
+}
+
+impl RenderArgs {
+    pub fn defaults(model: PathBuf) -> RenderArgs {
+        let mut render = Defaults::parse_from(["sixel-3d", "model"]).render;
+        render.model = model;
+        render
+    }
+
+    pub fn focal(&self) -> f32 {
+        self.focal.unwrap_or(self.width as f32 * 2.0)
+    }
+
+    // Caméra décrite par la ligne de commande, l'oeil pouvant être déplacé (mode interactif)
+    pub fn camera(&self, eye: Point3d) -> Camera {
+        let projection = match (self.ortho, self.fov) {
+            (Some(height), _) => Projection::Orthographic { height },
+            (None, Some(fov)) => Projection::Perspective { fov_y: fov.to_radians() },
+            (None, None) => Camera::with_focal(eye, self.target, self.focal(), self.height as f32).projection,
+        };
+        Camera {
+            eye,
+            target: self.target,
+            up: self.up,
+            projection,
+            near: self.near,
+            far: self.far,
+        }
+    }
+}
+
+fn parse_f32(s: &str) -> Result<f32, String> {
+    s.trim().parse::<f32>().map_err(|e| format!("'{}' : {}", s.trim(), e))
+}
+
+// Réglages de la caméra, vérifiés aussi pour les fichiers de scène
+pub fn check_fov(fov: f32) -> Result<f32, String> {
+    if fov > 0.0 && fov < 180.0 {
+        Ok(fov)
+    } else {
+        Err(format!("fov {} : angle attendu strictement entre 0 et 180 degrés", fov))
+    }
+}
+
+pub fn check_focal(focal: f32) -> Result<f32, String> {
+    if focal > 0.0 && focal.is_finite() {
+        Ok(focal)
+    } else {
+        Err(format!("focal {} : valeur strictement positive attendue", focal))
+    }
+}
+
+pub fn check_ortho(height: f32) -> Result<f32, String> {
+    if height > 0.0 && height.is_finite() {
+        Ok(height)
+    } else {
+        Err(format!("ortho {} : hauteur strictement positive attendue", height))
+    }
+}
+
+pub fn check_near(near: f32) -> Result<f32, String> {
+    if near >= 0.0 && near.is_finite() {
+        Ok(near)
+    } else {
+        Err(format!("near {} : distance positive attendue", near))
+    }
+}
+
+fn parse_fov(s: &str) -> Result<f32, String> {
+    check_fov(parse_f32(s)?)
+}
+
+fn parse_focal(s: &str) -> Result<f32, String> {
+    check_focal(parse_f32(s)?)
+}
+
+fn parse_ortho(s: &str) -> Result<f32, String> {
+    check_ortho(parse_f32(s)?)
+}
+
+fn parse_near(s: &str) -> Result<f32, String> {
+    check_near(parse_f32(s)?)
+}
+
+fn parse_floats<const N: usize>(s: &str) -> Result<[f32; N], String> {
+    let values: Vec<f32> = s
+        .split(',')
+        .map(|v| v.trim().parse::<f32>().map_err(|e| format!("'{}' : {}", v.trim(), e)))
+        .collect::<Result<_, _>>()?;
+    values
+        .try_into()
+        .map_err(|v: Vec<f32>| format!("{} valeurs attendues, {} trouvées", N, v.len()))
+}
+
+pub fn parse_point3d(s: &str) -> Result<Point3d, String> {
+    let [x, y, z] = parse_floats::<3>(s)?;
+    Ok((x, y, z))
+}
+
+pub fn parse_vec3(s: &str) -> Result<Vec3, String> {
+    let [x, y, z] = parse_floats::<3>(s)?;
+    Ok(Vec3::new(x, y, z))
+}
+
+pub fn parse_color(s: &str) -> Result<Color, String> {
+    let c: Vec<u8> = s
+        .split(',')
+        .map(|v| v.trim().parse::<u8>().map_err(|e| format!("'{}' : {}", v.trim(), e)))
+        .collect::<Result<_, _>>()?;
+    match c[..] {
+        [r, g, b] => Ok((r, g, b)),
+        _ => Err(format!("3 composantes attendues, {} trouvées", c.len())),
+    }
+}
+
+pub fn parse_material(s: &str) -> Result<String, String> {
+    if Material::from_name(s).is_some() || MaterialRaytrace::from_name(s).is_some() {
+        Ok(s.to_string())
+    } else {
+        let mut names: Vec<&str> = Material::PRESETS.to_vec();
+        names.extend_from_slice(MaterialRaytrace::PRESETS);
+        Err(format!("matériau inconnu, choix possibles : {}", names.join(", ")))
+    }
+}
+
+// "scale=2:rotate=90,0,0:translate=0,-1,0", chaque clé est optionnelle
+pub fn parse_transform(s: &str) -> Result<Transform, String> {
+    let mut t = Transform::IDENTITY;
+
+    for item in s.split(':').filter(|i| !i.trim().is_empty()) {
+        let (key, value) = item
+            .split_once('=')
+            .ok_or_else(|| format!("'{}' : clé=valeur attendu", item))?;
+        match key.trim() {
+            "scale" | "s" => {
+                t.scale = value.trim().parse().map_err(|e| format!("scale '{}' : {}", value, e))?;
+            }
+            "rotate" | "r" => {
+                let (x, y, z) = parse_point3d(value)?;
+                t.rotation = (x.to_radians(), y.to_radians(), z.to_radians());
+            }
+            "translate" | "t" => t.translation = parse_point3d(value)?,
+            other => return Err(format!("clé inconnue '{}' (scale, rotate, translate)", other)),
+        }
+    }
+    Ok(t)
+}
+
+// "x,y,z" ou "point:pos=0,5,5:color=1,0.9,0.8:intensity=2", les clés autres que pos/dir sont optionnelles
+pub fn parse_light(s: &str) -> Result<Light, String> {
+    let (kind, rest) = match s.split_once(':') {
+        Some(split) => split,
+        // Type seul ("point") : l'erreur porte sur les clés manquantes
+        None if !s.contains(',') && s.trim().starts_with(|c: char| c.is_ascii_alphabetic()) => (s, ""),
+        None => return Ok(Light::directional(parse_vec3(s)?)),
+    };
+
+    let mut position = None;
+    let mut direction = None;
+    let (mut u, mut v) = (None, None);
+    let mut color = (1.0, 1.0, 1.0);
+    let mut intensity = 1.0;
+    let mut attenuation = NO_ATTENUATION;
+    let mut angles = (20.0f32, 30.0f32);
+    for item in rest.split(':').filter(|i| !i.trim().is_empty()) {
+        let (key, value) = item
+            .split_once('=')
+            .ok_or_else(|| format!("'{}' : clé=valeur attendu", item))?;
+        match key.trim() {
+            "pos" | "position" => position = Some(parse_vec3(value)?),
+            "dir" | "direction" => direction = Some(parse_vec3(value)?),
+            "u" => u = Some(parse_vec3(value)?),
+            "v" => v = Some(parse_vec3(value)?),
+            "color" => color = parse_point3d(value)?,
+            "intensity" => {
+                intensity = value.trim().parse().map_err(|e| format!("intensity '{}' : {}", value, e))?;
+            }
+            "atten" | "attenuation" => attenuation = parse_point3d(value)?,
+            "angles" => {
+                let [inner, outer] = parse_floats::<2>(value)?;
+                angles = (inner, outer);
+            }
+            other => return Err(format!("clé inconnue '{}' (pos, dir, u, v, color, intensity, atten, angles)", other)),
+        }
+    }
+
+    let required = |v: Option<Vec3>, key: &str| v.ok_or_else(|| format!("{} : clé {} manquante", kind, key));
+    match kind.trim() {
+        "directional" | "dir" => Ok(Light::Directional { direction: required(direction, "dir")?, color, intensity }),
+        "point" => Ok(Light::Point { position: required(position, "pos")?, color, intensity, attenuation }),
+        "spot" => Ok(Light::Spot {
+            position: required(position, "pos")?,
+            direction: required(direction, "dir")?,
+            color,
+            intensity,
+            attenuation,
+            inner: angles.0.to_radians(),
+            outer: angles.1.to_radians(),
+        }),
+        "area" => Ok(Light::Area {
+            position: required(position, "pos")?,
+            u: required(u, "u")?,
+            v: required(v, "v")?,
+            color,
+            intensity,
+            attenuation,
+        }),
+        other => Err(format!("type de lumière inconnu '{}' (directional, point, spot, area)", other)),
+    }
+}
+
+// "sky", "solid:0.1,0.1,0.2", "gradient:bottom=1,1,1:top=0.2,0.4,1" ou "map:ciel.hdr:intensity=2:rotate=90"
+pub fn parse_environment(s: &str) -> Result<EnvironmentSpec, String> {
+    let (kind, rest) = s.split_once(':').unwrap_or((s, ""));
+    match kind.trim() {
+        "sky" => Ok(EnvironmentSpec::SKY),
+        "solid" if rest.trim().is_empty() => Err("solid : couleur R,G,B manquante".to_string()),
+        "solid" => Ok(EnvironmentSpec::Solid(parse_point3d(rest)?)),
+        "gradient" => {
+            let (mut bottom, mut top) = (None, None);
+            for item in rest.split(':').filter(|i| !i.trim().is_empty()) {
+                let (key, value) = item
+                    .split_once('=')
+                    .ok_or_else(|| format!("'{}' : clé=valeur attendu", item))?;
+                match key.trim() {
+                    "bottom" => bottom = Some(parse_point3d(value)?),
+                    "top" => top = Some(parse_point3d(value)?),
+                    other => return Err(format!("clé inconnue '{}' (bottom, top)", other)),
+                }
+            }
+            let required = |c: Option<Point3d>, key: &str| c.ok_or_else(|| format!("gradient : clé {} manquante", key));
+            Ok(EnvironmentSpec::Gradient { bottom: required(bottom, "bottom")?, top: required(top, "top")? })
+        }
+        "map" => {
+            let mut items = rest.split(':');
+            let path = items.next().filter(|p| !p.trim().is_empty()).ok_or("map : fichier manquant")?;
+            let (mut intensity, mut rotation) = (1.0, 0.0);
+            for item in items.filter(|i| !i.trim().is_empty()) {
+                let (key, value) = item
+                    .split_once('=')
+                    .ok_or_else(|| format!("'{}' : clé=valeur attendu", item))?;
+                let value: f32 = value.trim().parse().map_err(|e| format!("{} '{}' : {}", key.trim(), value, e))?;
+                match key.trim() {
+                    "intensity" => intensity = value,
+                    "rotate" => rotation = value,
+                    other => return Err(format!("clé inconnue '{}' (intensity, rotate)", other)),
+                }
+            }
+            Ok(EnvironmentSpec::Map { path: PathBuf::from(path), intensity, rotation })
+        }
+        other => Err(format!("environnement inconnu '{}' (sky, solid, gradient, map)", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_args(extra: &[&str]) -> Result<RenderArgs, clap::Error> {
+        let argv = ["sixel-3d", "model", "-W", "200", "-H", "100"].into_iter().chain(extra.iter().copied());
+        Defaults::try_parse_from(argv).map(|d| d.render)
+    }
+
+    #[test]
+    fn rejects_invalid_camera() {
+        for extra in [
+            ["--fov", "0"],
+            ["--fov", "180"],
+            ["--fov", "-30"],
+            ["--ortho", "0"],
+            ["--ortho", "-2"],
+            ["--focal", "0"],
+            ["--near", "-1"],
+            ["--near", "NaN"],
+        ] {
+            assert!(render_args(&extra).is_err(), "{:?}", extra);
+        }
+        for extra in [["--fov", "60"], ["--fov", "179.5"], ["--ortho", "2"], ["--focal", "300"], ["--near", "0"]] {
+            assert!(render_args(&extra).is_ok(), "{:?}", extra);
+        }
+    }
+
+    // Le point visé est au centre de l'image, un point décalé de la moitié du champ
+    // est au bord, quelle que soit la projection
+    #[test]
+    fn projections_center_and_edge() {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-3;
+        let eye = (0.0, 0.0, 10.0);
+
+        // Perspective : fov 90° à 10 unités, demi-hauteur visible 10
+        let camera = render_args(&["--fov", "90"]).unwrap().camera(eye);
+        let (x, y, _) = camera.project((0.0, 0.0, 0.0), 200.0, 100.0).unwrap();
+        assert!(close(x, 100.0) && close(y, 50.0), "{} {}", x, y);
+        let (x, y, _) = camera.project((5.0, 10.0, 0.0), 200.0, 100.0).unwrap();
+        assert!(close(x, 125.0) && close(y, 0.0), "{} {}", x, y);
+
+        // Focale en pixels : un point à 1 unité sur 10 de distance se projette à focal / 10
+        let camera = render_args(&["--focal", "300"]).unwrap().camera(eye);
+        let (x, y, _) = camera.project((0.0, 1.0, 0.0), 200.0, 100.0).unwrap();
+        assert!(close(x, 100.0) && close(y, 20.0), "{} {}", x, y);
+
+        // Orthographique : la distance ne change rien
+        let camera = render_args(&["--ortho", "4"]).unwrap().camera(eye);
+        for z in [0.0, -50.0] {
+            let (x, y, _) = camera.project((0.0, 0.0, z), 200.0, 100.0).unwrap();
+            assert!(close(x, 100.0) && close(y, 50.0), "{} {}", x, y);
+            let (x, y, _) = camera.project((-1.0, -2.0, z), 200.0, 100.0).unwrap();
+            assert!(close(x, 75.0) && close(y, 100.0), "{} {}", x, y);
+        }
+    }
+
+    #[test]
+    fn projection_clips_near_and_far() {
+        let camera = render_args(&["--near", "5", "--far", "20"]).unwrap().camera((0.0, 0.0, 10.0));
+        assert!(camera.project((0.0, 0.0, 8.0), 200.0, 100.0).is_none());
+        assert!(camera.project((0.0, 0.0, 0.0), 200.0, 100.0).is_some());
+        assert!(camera.project((0.0, 0.0, -15.0), 200.0, 100.0).is_none());
+    }
+
+    fn vec3(v: Vec3) -> (f32, f32, f32) {
+        (v.x, v.y, v.z)
+    }
+
+    // Message d'erreur de `parse`, qui doit contenir `expected`
+    fn error<T: std::fmt::Debug>(parse: fn(&str) -> Result<T, String>, s: &str, expected: &str) {
+        match parse(s) {
+            Err(msg) => assert!(msg.contains(expected), "{} : '{}' sans '{}'", s, msg, expected),
+            Ok(v) => panic!("{} accepté : {:?}", s, v),
+        }
+    }
+
+    #[test]
+    fn transform_valid() {
+        let t = parse_transform("scale=2:rotate=90,0,-45:translate=0,-1,2.5").unwrap();
+        assert_eq!(t.scale, 2.0);
+        assert_eq!(t.rotation, (90f32.to_radians(), 0.0, (-45f32).to_radians()));
+        assert_eq!(t.translation, (0.0, -1.0, 2.5));
+
+        // Clés courtes, espaces, clés absentes et ":" en trop
+        let t = parse_transform(" s = 0.5 :: t=1, 2, 3:").unwrap();
+        assert_eq!((t.scale, t.rotation, t.translation), (0.5, (0.0, 0.0, 0.0), (1.0, 2.0, 3.0)));
+        let t = parse_transform("").unwrap();
+        assert_eq!((t.scale, t.translation), (1.0, (0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn transform_errors() {
+        error(parse_transform, "scale", "clé=valeur attendu");
+        error(parse_transform, "scale=deux", "scale 'deux'");
+        error(parse_transform, "scale=2=3", "scale '2=3'");
+        error(parse_transform, "rotate=90,0", "3 valeurs attendues, 2 trouvées");
+        error(parse_transform, "translate=1,2,3,4", "3 valeurs attendues, 4 trouvées");
+        error(parse_transform, "translate=1,x,3", "'x'");
+        error(parse_transform, "shear=1", "clé inconnue 'shear'");
+    }
+
+    #[test]
+    fn light_valid() {
+        // Forme courte : direction d'une lumière blanche
+        let Light::Directional { direction, color, intensity } = parse_light("-0.5,1,1").unwrap() else { panic!() };
+        assert_eq!((vec3(direction), color, intensity), ((-0.5, 1.0, 1.0), (1.0, 1.0, 1.0), 1.0));
+
+        let light = parse_light("point:pos=0,5,5:color=1,0.9,0.8:intensity=2:atten=1,0,0.1").unwrap();
+        let Light::Point { position, color, intensity, attenuation } = light else { panic!("{:?}", light) };
+        assert_eq!((vec3(position), color, intensity, attenuation), ((0.0, 5.0, 5.0), (1.0, 0.9, 0.8), 2.0, (1.0, 0.0, 0.1)));
+
+        let light = parse_light("spot:pos=0,4,0:dir=0,-1,0:angles=10,20").unwrap();
+        let Light::Spot { direction, inner, outer, attenuation, .. } = light else { panic!("{:?}", light) };
+        assert_eq!(vec3(direction), (0.0, -1.0, 0.0));
+        assert_eq!((inner, outer), (10f32.to_radians(), 20f32.to_radians()));
+        assert_eq!(attenuation, NO_ATTENUATION);
+
+        let light = parse_light("area:pos=0,3,0:u=1,0,0:v=0,0,2").unwrap();
+        let Light::Area { u, v, .. } = light else { panic!("{:?}", light) };
+        assert_eq!((vec3(u), vec3(v)), ((1.0, 0.0, 0.0), (0.0, 0.0, 2.0)));
+
+        assert!(matches!(parse_light("directional:dir=0,1,0").unwrap(), Light::Directional { .. }));
+        assert!(matches!(parse_light("dir:direction=0,1,0:intensity=0.5").unwrap(), Light::Directional { intensity: 0.5, .. }));
+    }
+
+    #[test]
+    fn light_errors() {
+        error(parse_light, "1,2", "3 valeurs attendues, 2 trouvées");
+        error(parse_light, "1,2,3,4", "3 valeurs attendues, 4 trouvées");
+        error(parse_light, "a,b,c", "'a'");
+        error(parse_light, "point", "point : clé pos manquante");
+        error(parse_light, "laser", "type de lumière inconnu 'laser'");
+        error(parse_light, "point:color=1,1,1", "point : clé pos manquante");
+        error(parse_light, "spot:pos=0,1,0", "spot : clé dir manquante");
+        error(parse_light, "area:pos=0,1,0:u=1,0,0", "area : clé v manquante");
+        error(parse_light, "point:pos=0,1", "3 valeurs attendues, 2 trouvées");
+        error(parse_light, "point:pos=0,1,0:intensity=fort", "intensity 'fort'");
+        error(parse_light, "spot:pos=0,1,0:dir=0,-1,0:angles=10", "2 valeurs attendues, 1 trouvées");
+        error(parse_light, "point:pos", "'pos' : clé=valeur attendu");
+        error(parse_light, "point:pos=0,1,0:radius=2", "clé inconnue 'radius'");
+        error(parse_light, "laser:pos=0,1,0", "type de lumière inconnu 'laser'");
+    }
+
+    #[test]
+    fn environment_valid() {
+        assert!(matches!(parse_environment("sky").unwrap(), EnvironmentSpec::Gradient { .. }));
+        assert!(matches!(parse_environment("solid:0.1,0.2,0.3").unwrap(), EnvironmentSpec::Solid((0.1, 0.2, 0.3))));
+        let env = parse_environment("gradient:top=0.2,0.4,1:bottom=1,1,1").unwrap();
+        let EnvironmentSpec::Gradient { bottom, top } = env else { panic!("{:?}", env) };
+        assert_eq!((bottom, top), ((1.0, 1.0, 1.0), (0.2, 0.4, 1.0)));
+
+        let env = parse_environment("map:ciel.hdr:intensity=2:rotate=90").unwrap();
+        let EnvironmentSpec::Map { path, intensity, rotation } = env else { panic!("{:?}", env) };
+        assert_eq!((path, intensity, rotation), (PathBuf::from("ciel.hdr"), 2.0, 90.0));
+        let env = parse_environment("map:dossier/ciel.pfm").unwrap();
+        let EnvironmentSpec::Map { path, intensity, rotation } = env else { panic!("{:?}", env) };
+        assert_eq!((path, intensity, rotation), (PathBuf::from("dossier/ciel.pfm"), 1.0, 0.0));
+    }
+
+    #[test]
+    fn environment_errors() {
+        error(parse_environment, "solid", "solid : couleur R,G,B manquante");
+        error(parse_environment, "solid:", "solid : couleur R,G,B manquante");
+        error(parse_environment, "solid:1,1", "3 valeurs attendues, 2 trouvées");
+        error(parse_environment, "solid:1,1,1,1", "3 valeurs attendues, 4 trouvées");
+        error(parse_environment, "solid:rouge,0,0", "'rouge'");
+        error(parse_environment, "gradient:top=1,1,1", "gradient : clé bottom manquante");
+        error(parse_environment, "gradient:bottom=1,1,1:middle=0,0,0", "clé inconnue 'middle'");
+        error(parse_environment, "gradient:bottom", "'bottom' : clé=valeur attendu");
+        error(parse_environment, "map", "map : fichier manquant");
+        error(parse_environment, "map::intensity=2", "map : fichier manquant");
+        error(parse_environment, "map:ciel.hdr:intensity=beaucoup", "intensity 'beaucoup'");
+        error(parse_environment, "map:ciel.hdr:blur=2", "clé inconnue 'blur'");
+        error(parse_environment, "nuit", "environnement inconnu 'nuit'");
+    }
+}
@@ -0,0 +1,90 @@
+// Environnement : ce que voit un rayon qui ne touche rien. Le même sert de fond aux
+// rasteriseurs, de ciel au raytracer (rayons perdus, réflexions, réfractions) et
+// d'éclairage indirect au path tracer.
+use std::f32::consts::PI;
+use std::path::PathBuf;
+
+use rayon::prelude::*;
+
+use crate::frame_buffer::FrameBuffer;
+use crate::math_3d::{self, Camera, Vec3};
+use crate::texture::{Texture, TextureError};
+
+// Description (ligne de commande ou fichier de scène) : l'image n'est lue qu'au moment du rendu
+#[derive(Debug, Clone)]
+pub enum EnvironmentSpec {
+    Solid((f32, f32, f32)),
+    // Couleurs en bas (direction -y) et en haut (+y), interpolées selon la hauteur de la direction
+    Gradient { bottom: (f32, f32, f32), top: (f32, f32, f32) },
+    // Image équirectangulaire (PFM, HDR, ou tout format de texture), rotation autour de y en degrés
+    Map { path: PathBuf, intensity: f32, rotation: f32 },
+}
+
+pub enum Environment {
+    Solid((f32, f32, f32)),
+    Gradient { bottom: (f32, f32, f32), top: (f32, f32, f32) },
+    Map { texture: Texture, intensity: f32, rotation: f32 },
+}
+
+impl EnvironmentSpec {
+    // Dégradé historique du raytracer : rouge en bas, turquoise au zénith
+    pub const SKY: EnvironmentSpec = EnvironmentSpec::Gradient { bottom: (1.0, 0.05, 0.05), top: (0.0, 0.8, 0.8) };
+}
+
+impl Environment {
+    pub fn load(spec: &EnvironmentSpec) -> Result<Environment, TextureError> {
+        Ok(match spec {
+            EnvironmentSpec::Solid(color) => Environment::Solid(*color),
+            EnvironmentSpec::Gradient { bottom, top } => Environment::Gradient { bottom: *bottom, top: *top },
+            EnvironmentSpec::Map { path, intensity, rotation } => Environment::Map {
+                texture: Texture::load(path)?,
+                intensity: *intensity,
+                rotation: rotation.to_radians(),
+            },
+        })
+    }
+
+    // Lumière arrivant de la direction (normalisée) d'un rayon perdu
+    pub fn radiance(&self, direction: Vec3) -> (f32, f32, f32) {
+        match self {
+            Environment::Solid(color) => *color,
+            Environment::Gradient { bottom, top } => {
+                let t = 0.5 * (direction.y + 1.0);
+                (
+                    (1.0 - t) * bottom.0 + t * top.0,
+                    (1.0 - t) * bottom.1 + t * top.1,
+                    (1.0 - t) * bottom.2 + t * top.2,
+                )
+            }
+            Environment::Map { texture, intensity, rotation } => {
+                // Longitude : -z au centre de l'image, croissante vers +x ; latitude : +y en haut
+                let longitude = direction.x.atan2(-direction.z) + rotation;
+                let u = (0.5 + longitude / (2.0 * PI)).rem_euclid(1.0);
+                // Pas de répétition verticale : on reste à un demi-texel des pôles
+                let half_texel = 0.5 / texture.height.max(1) as f32;
+                let v = (1.0 - direction.y.clamp(-1.0, 1.0).acos() / PI).clamp(half_texel, 1.0 - half_texel);
+                let c = texture.sample(u, v);
+                (c.0 * intensity, c.1 * intensity, c.2 * intensity)
+            }
+        }
+    }
+
+    // Fond des rasteriseurs : l'environnement vu par le rayon de chaque pixel
+    pub fn fill(&self, camera: &Camera, fb: &mut FrameBuffer) {
+        if let Environment::Solid(color) = self {
+            // Arrondi : une couleur --background (octets) est restituée telle quelle
+            let byte = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+            fb.clean((byte(color.0), byte(color.1), byte(color.2)));
+            return;
+        }
+
+        let (width, height) = (fb.width, fb.height);
+        fb.pixels.par_chunks_mut(width * 3).enumerate().for_each(|(y, row)| {
+            for (x, px) in row.chunks_exact_mut(3).enumerate() {
+                let (_, direction) = camera.primary_ray(x as f32 + 0.5, y as f32 + 0.5, width as f32, height as f32);
+                let (r, g, b) = math_3d::utils::intensity_to_color(self.radiance(direction));
+                px.copy_from_slice(&[r, g, b]);
+            }
+        });
+    }
+}
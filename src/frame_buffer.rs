@@ -1,4 +1,5 @@
 use std::convert::Infallible;
+use std::io::{self, Write};
 
 use embedded_graphics::{
     Pixel,
@@ -6,7 +7,10 @@ use embedded_graphics::{
     prelude::{DrawTarget, OriginDimensions, RgbColor, Size},
 };
 
-use crate::math_3d::Color;
+#[cfg(feature = "native-sixel")]
+use crate::sixel;
+use crate::text::{self, TextMode};
+use crate::{iterm, kitty, math_3d::Color};
 
 pub struct FrameBuffer {
     size: usize,
@@ -42,6 +46,49 @@ impl FrameBuffer {
         }
     }
 
+    // Anticrénelage (SSAA) : src est la même image rendue `factor` fois plus grande
+    // dans chaque dimension, chaque pixel reçoit la moyenne de son bloc factor x factor
+    pub fn downsample_from(&mut self, src: &FrameBuffer, factor: usize) {
+        let count = (factor * factor) as u32;
+        for y in 0..self.height.min(src.height / factor) {
+            for x in 0..self.width.min(src.width / factor) {
+                let mut sum = [0u32; 3];
+                for sy in y * factor..(y + 1) * factor {
+                    for sx in x * factor..(x + 1) * factor {
+                        let idx = (sy * src.width + sx) * 3;
+                        sum[0] += src.pixels[idx] as u32;
+                        sum[1] += src.pixels[idx + 1] as u32;
+                        sum[2] += src.pixels[idx + 2] as u32;
+                    }
+                }
+                // Arrondi au plus proche
+                let avg = sum.map(|c| ((c + count / 2) / count) as u8);
+                self.pixel(x as u32, y as u32, (avg[0], avg[1], avg[2]));
+            }
+        }
+    }
+
+    // Image en sixel par l'encodeur natif, sans libsixel
+    #[cfg(feature = "native-sixel")]
+    pub fn write_sixel(&self, encoder: &sixel::Encoder, out: &mut impl Write) -> io::Result<()> {
+        encoder.encode(&self.pixels, self.width, self.height, out)
+    }
+
+    // Image pour le protocole graphique de kitty, compressée en zlib ou non
+    pub fn write_kitty(&self, zlib: bool, out: &mut impl Write) -> io::Result<()> {
+        kitty::encode(&self.pixels, self.width, self.height, zlib, out)
+    }
+
+    // Image en ligne d'iTerm2 (PNG)
+    pub fn write_iterm(&self, out: &mut impl Write) -> io::Result<()> {
+        iterm::encode(&self.pixels, self.width, self.height, out)
+    }
+
+    // Image en caractères colorés, sur `columns` colonnes de terminal
+    pub fn write_text(&self, mode: TextMode, columns: usize, out: &mut impl Write) -> io::Result<()> {
+        text::encode(&self.pixels, self.width, self.height, mode, columns, out)
+    }
+
     pub fn pixel(&mut self, x: u32, y: u32, c: Color) {
         let idx = ((y * self.width as u32 + x) * 3) as usize;
 
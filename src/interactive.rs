@@ -0,0 +1,282 @@
+// Visualisation interactive : le terminal passe en mode brut, les touches
+// font orbiter l'oeil autour de la cible et chaque mouvement relance un rendu.
+use std::io::{self, Write};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::math_3d::{Point3d, Vec3};
+
+// Réglages du terminal avant le passage en mode brut, partagés avec le panic hook
+static ORIGINAL_TERMIOS: OnceLock<libc::termios> = OnceLock::new();
+
+const ENTER_SCREEN: &str = "\x1b[?1049h\x1b[?25l"; // Écran alternatif + curseur masqué
+const LEAVE_SCREEN: &str = "\x1b[?25h\x1b[?1049l";
+
+// Attente de la suite d'une séquence : un Échap resté seul après ce délai est la touche Échap
+const ESCAPE_TIMEOUT: Duration = Duration::from_millis(50);
+
+fn restore_terminal() {
+    if let Some(original) = ORIGINAL_TERMIOS.get() {
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, original);
+        }
+        let mut out = io::stdout();
+        let _ = out.write_all(LEAVE_SCREEN.as_bytes());
+        let _ = out.flush();
+    }
+}
+
+// Garde RAII : le terminal est restauré au drop, y compris pendant un panic
+pub struct RawMode;
+
+impl RawMode {
+    pub fn enable() -> io::Result<RawMode> {
+        let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        if ORIGINAL_TERMIOS.set(termios).is_ok() {
+            // Le message de panic doit s'afficher sur un terminal remis en état
+            let previous = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |info| {
+                restore_terminal();
+                previous(info);
+            }));
+        }
+
+        let mut raw = termios;
+        unsafe { libc::cfmakeraw(&mut raw) };
+        // Bloque jusqu'à au moins un octet
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut out = io::stdout();
+        out.write_all(ENTER_SCREEN.as_bytes())?;
+        out.flush()?;
+
+        Ok(RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key {
+    Left,
+    Right,
+    Up,
+    Down,
+    ZoomIn,
+    ZoomOut,
+    Reset,
+    Quit,
+}
+
+// Décode les octets lus en mode brut (une lecture peut contenir plusieurs touches).
+// Renvoie aussi le nombre d'octets décodés : une séquence d'échappement incomplète en fin
+// de bloc, ou un Échap seul, attend la lecture suivante.
+pub fn decode_keys(bytes: &[u8]) -> (Vec<Key>, usize) {
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == 0x1b {
+            // Flèches : CSI (ESC [ paramètres final) ou SS3 (ESC O final), selon le mode du terminal
+            let end = match bytes.get(i + 1) {
+                None => break,
+                Some(b'[') => {
+                    let params = bytes[i + 2..].iter().take_while(|b| (0x20..=0x3f).contains(*b)).count();
+                    i + 2 + params
+                }
+                Some(b'O') => i + 2,
+                // Alt + touche : ignorée
+                Some(_) => i + 1,
+            };
+            let Some(&last) = bytes.get(end) else { break };
+            // Flèche sans modificateur (ESC [ A, ESC O A) : les autres séquences sont ignorées
+            if end == i + 2 {
+                match last {
+                    b'A' => keys.push(Key::Up),
+                    b'B' => keys.push(Key::Down),
+                    b'C' => keys.push(Key::Right),
+                    b'D' => keys.push(Key::Left),
+                    _ => {}
+                }
+            }
+            i = end + 1;
+            continue;
+        }
+        match bytes[i] {
+            0x03 | b'q' | b'Q' => keys.push(Key::Quit), // Ctrl-C
+            b'a' | b'A' | b'h' => keys.push(Key::Left),
+            b'd' | b'D' | b'l' => keys.push(Key::Right),
+            b'w' | b'W' | b'k' => keys.push(Key::Up),
+            b's' | b'S' | b'j' => keys.push(Key::Down),
+            b'+' | b'=' => keys.push(Key::ZoomIn),
+            b'-' | b'_' => keys.push(Key::ZoomOut),
+            b'r' | b'R' => keys.push(Key::Reset),
+            _ => {}
+        }
+        i += 1;
+    }
+    (keys, i)
+}
+
+// Lecture directe du descripteur : le tampon de io::Stdin cacherait à poll les octets déjà lus
+fn read_stdin(buf: &mut [u8]) -> io::Result<usize> {
+    loop {
+        let n = unsafe { libc::read(libc::STDIN_FILENO, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+        if n >= 0 {
+            return Ok(n as usize);
+        }
+        let error = io::Error::last_os_error();
+        if error.kind() != io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+}
+
+// Des octets arrivent sur l'entrée standard avant la fin du délai
+fn stdin_ready(timeout: Duration) -> io::Result<bool> {
+    let mut poll = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+    loop {
+        let ready = unsafe { libc::poll(&mut poll, 1, timeout.as_millis() as libc::c_int) };
+        if ready >= 0 {
+            return Ok(ready > 0);
+        }
+        let error = io::Error::last_os_error();
+        if error.kind() != io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+}
+
+// Position de l'oeil en coordonnées sphériques autour de la cible
+#[derive(Debug, Clone, Copy)]
+pub struct Orbit {
+    pub target: Point3d,
+    pub radius: f32,
+    pub yaw: f32,   // Autour de Y
+    pub pitch: f32, // Au-dessus / en dessous de l'horizon
+}
+
+impl Orbit {
+    const STEP: f32 = std::f32::consts::PI / 24.0;
+    const ZOOM: f32 = 0.9;
+    const PITCH_LIMIT: f32 = std::f32::consts::FRAC_PI_2 - 0.01; // Évite le repère dégénéré avec world_up
+
+    pub fn from_eye(eye: Point3d, target: Point3d) -> Orbit {
+        let d = Vec3::new_from_point3d(eye).sub(Vec3::new_from_point3d(target));
+        let radius = d.length().max(1e-3);
+        Orbit {
+            target,
+            radius,
+            yaw: d.x.atan2(d.z),
+            pitch: (d.y / radius).clamp(-1.0, 1.0).asin(),
+        }
+    }
+
+    pub fn eye(&self) -> Point3d {
+        let (t, r) = (self.target, self.radius);
+        (
+            t.0 + r * self.pitch.cos() * self.yaw.sin(),
+            t.1 + r * self.pitch.sin(),
+            t.2 + r * self.pitch.cos() * self.yaw.cos(),
+        )
+    }
+
+    pub fn apply(&mut self, key: Key) {
+        match key {
+            Key::Left => self.yaw -= Self::STEP,
+            Key::Right => self.yaw += Self::STEP,
+            Key::Up => self.pitch = (self.pitch + Self::STEP).min(Self::PITCH_LIMIT),
+            Key::Down => self.pitch = (self.pitch - Self::STEP).max(-Self::PITCH_LIMIT),
+            Key::ZoomIn => self.radius *= Self::ZOOM,
+            Key::ZoomOut => self.radius /= Self::ZOOM,
+            Key::Reset | Key::Quit => {}
+        }
+    }
+}
+
+// Boucle principale : draw(eye) dessine et encode une image, appelée à chaque déplacement
+pub fn run<E, F>(eye: Point3d, target: Point3d, mut draw: F) -> Result<(), E>
+where
+    E: From<io::Error>,
+    F: FnMut(Point3d) -> Result<(), E>,
+{
+    let _raw = RawMode::enable()?;
+    let initial = Orbit::from_eye(eye, target);
+    let mut orbit = initial;
+    let mut buf = [0u8; 64];
+    // Octets pas encore décodés : début d'une séquence d'échappement
+    let mut pending: Vec<u8> = Vec::new();
+
+    draw(orbit.eye())?;
+    loop {
+        if !pending.is_empty() && !stdin_ready(ESCAPE_TIMEOUT)? {
+            // Rien n'a suivi : Échap seul, ou séquence tronquée abandonnée
+            if pending == [0x1b] {
+                return Ok(());
+            }
+            pending.clear();
+            continue;
+        }
+
+        let n = read_stdin(&mut buf)?;
+        if n == 0 {
+            return Ok(());
+        }
+        pending.extend_from_slice(&buf[..n]);
+
+        let (keys, decoded) = decode_keys(&pending);
+        pending.drain(..decoded);
+        if keys.contains(&Key::Quit) {
+            return Ok(());
+        }
+        if keys.is_empty() {
+            continue;
+        }
+        for key in keys {
+            if key == Key::Reset {
+                orbit = initial;
+            }
+            orbit.apply(key);
+        }
+        draw(orbit.eye())?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arrows_in_both_cursor_modes() {
+        let (keys, decoded) = decode_keys(b"\x1b[A\x1bOB\x1b[C\x1bOD");
+        assert_eq!(keys, [Key::Up, Key::Down, Key::Right, Key::Left]);
+        assert_eq!(decoded, 12);
+    }
+
+    #[test]
+    fn other_sequences_are_ignored() {
+        // Ctrl+flèche, Suppr, F1, Alt+q
+        let (keys, decoded) = decode_keys(b"\x1b[1;5A\x1b[3~\x1bOPq\x1bq");
+        assert_eq!(keys, [Key::Quit]);
+        assert_eq!(decoded, 16);
+    }
+
+    #[test]
+    fn escape_waits_for_more_bytes() {
+        assert_eq!(decode_keys(b"w\x1b"), (vec![Key::Up], 1));
+        assert_eq!(decode_keys(b"\x1b["), (vec![], 0));
+        assert_eq!(decode_keys(b"\x1b[1;"), (vec![], 0));
+        assert_eq!(decode_keys(b"\x1bO"), (vec![], 0));
+    }
+}
@@ -0,0 +1,122 @@
+// Images en ligne d'iTerm2 (OSC 1337) : un fichier PNG en base64, affiché à sa taille en pixels
+use std::io::{self, Write};
+
+use flate2::{Compression, Crc, write::ZlibEncoder};
+
+use crate::base64;
+
+// pixels : RGB888, ligne par ligne
+pub fn encode(pixels: &[u8], width: usize, height: usize, out: &mut impl Write) -> io::Result<()> {
+    let size = width * height * 3;
+    if width == 0 || height == 0 || pixels.len() < size {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "image vide ou tronquée"));
+    }
+
+    let png = png(&pixels[..size], width, height)?;
+    let mut iterm = Vec::with_capacity(png.len() * 4 / 3 + 128);
+    write!(
+        iterm,
+        "\x1b]1337;File=inline=1;size={};width={}px;height={}px;preserveAspectRatio=0:",
+        png.len(),
+        width,
+        height
+    )?;
+    iterm.extend_from_slice(base64::encode(&png).as_bytes());
+    iterm.push(0x07);
+    out.write_all(&iterm)
+}
+
+// PNG RGB 8 bits sans entrelacement, chaque ligne sans filtre
+fn png(pixels: &[u8], width: usize, height: usize) -> io::Result<Vec<u8>> {
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(height as u32).to_be_bytes());
+    // Profondeur 8, couleur RGB (2), compression, filtre et entrelacement par défaut
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut idat = ZlibEncoder::new(Vec::new(), Compression::default());
+    for row in pixels.chunks(width * 3) {
+        idat.write_all(&[0])?;
+        idat.write_all(row)?;
+    }
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    chunk(&mut png, b"IHDR", &ihdr);
+    chunk(&mut png, b"IDAT", &idat.finish()?);
+    chunk(&mut png, b"IEND", &[]);
+    Ok(png)
+}
+
+// Longueur, type, données, puis CRC-32 du type et des données
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let mut crc = Crc::new();
+    crc.update(kind);
+    crc.update(data);
+    png.extend_from_slice(&crc.sum().to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    // Morceaux du PNG (type, données), CRC vérifiés
+    fn chunks(png: &[u8]) -> Vec<([u8; 4], Vec<u8>)> {
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        let mut chunks = Vec::new();
+        let mut pos = 8;
+        while pos < png.len() {
+            let len = u32::from_be_bytes(png[pos..pos + 4].try_into().unwrap()) as usize;
+            let kind: [u8; 4] = png[pos + 4..pos + 8].try_into().unwrap();
+            let data = &png[pos + 8..pos + 8 + len];
+            let crc = u32::from_be_bytes(png[pos + 8 + len..pos + 12 + len].try_into().unwrap());
+            let mut expected = Crc::new();
+            expected.update(&kind);
+            expected.update(data);
+            assert_eq!(crc, expected.sum(), "{}", String::from_utf8_lossy(&kind));
+            chunks.push((kind, data.to_vec()));
+            pos += 12 + len;
+        }
+        assert_eq!(pos, png.len());
+        chunks
+    }
+
+    #[test]
+    fn inline_png() {
+        let (width, height) = (5, 3);
+        let pixels: Vec<u8> = (0..width * height * 3).map(|i| (i * 17) as u8).collect();
+        let mut out = Vec::new();
+        encode(&pixels, width, height, &mut out).unwrap();
+
+        let text = std::str::from_utf8(&out).unwrap();
+        let (header, payload) = text.strip_prefix("\x1b]1337;File=").unwrap().split_once(':').unwrap();
+        let payload = payload.strip_suffix('\x07').unwrap();
+        let png = base64::decode(payload).unwrap();
+        assert_eq!(header, format!("inline=1;size={};width=5px;height=3px;preserveAspectRatio=0", png.len()));
+
+        let chunks = chunks(&png);
+        let kinds: Vec<&[u8; 4]> = chunks.iter().map(|c| &c.0).collect();
+        assert_eq!(kinds, [b"IHDR", b"IDAT", b"IEND"]);
+        assert_eq!(chunks[0].1, [0, 0, 0, 5, 0, 0, 0, 3, 8, 2, 0, 0, 0]);
+        assert!(chunks[2].1.is_empty());
+
+        // Lignes précédées du filtre 0
+        let mut raw = Vec::new();
+        ZlibDecoder::new(&chunks[1].1[..]).read_to_end(&mut raw).unwrap();
+        assert_eq!(raw.len(), height * (1 + width * 3));
+        for (row, expected) in raw.chunks(1 + width * 3).zip(pixels.chunks(width * 3)) {
+            assert_eq!(row[0], 0);
+            assert_eq!(&row[1..], expected);
+        }
+    }
+
+    #[test]
+    fn rejects_empty_or_short() {
+        assert!(encode(&[], 0, 1, &mut Vec::new()).is_err());
+        assert!(encode(&[0; 5], 1, 2, &mut Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,129 @@
+// Protocole graphique de kitty (aussi compris par WezTerm et Ghostty) : l'image RGB brute,
+// éventuellement compressée en zlib, est envoyée en base64 par morceaux de 4096 octets
+use std::io::{self, Write};
+
+use flate2::{Compression, write::ZlibEncoder};
+
+use crate::base64;
+
+// Taille maximale de la charge utile d'une séquence APC
+const CHUNK: usize = 4096;
+// Toujours le même identifiant : chaque nouvelle image remplace la précédente (animation,
+// mode interactif) au lieu de s'accumuler dans la mémoire du terminal
+const IMAGE_ID: u32 = 1;
+
+// pixels : RGB888, ligne par ligne
+pub fn encode(pixels: &[u8], width: usize, height: usize, zlib: bool, out: &mut impl Write) -> io::Result<()> {
+    let size = width * height * 3;
+    if width == 0 || height == 0 || pixels.len() < size {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "image vide ou tronquée"));
+    }
+
+    let payload = if zlib {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&pixels[..size])?;
+        base64::encode(&encoder.finish()?)
+    } else {
+        base64::encode(&pixels[..size])
+    };
+
+    // a=T : transmettre et afficher ; f=24 : RGB ; q=2 : pas de réponse du terminal
+    let mut kitty = Vec::with_capacity(payload.len() + payload.len() / CHUNK * 16 + 64);
+    let chunks = payload.as_bytes().chunks(CHUNK);
+    let last = chunks.len() - 1;
+    for (i, chunk) in chunks.enumerate() {
+        kitty.extend_from_slice(b"\x1b_G");
+        if i == 0 {
+            write!(kitty, "a=T,f=24,s={},v={},i={},q=2,", width, height, IMAGE_ID)?;
+            if zlib {
+                kitty.extend_from_slice(b"o=z,");
+            }
+        }
+        write!(kitty, "m={};", (i < last) as u8)?;
+        kitty.extend_from_slice(chunk);
+        kitty.extend_from_slice(b"\x1b\\");
+    }
+    out.write_all(&kitty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    // Séquences APC : (clés de contrôle, charge utile)
+    fn sequences(out: &[u8]) -> Vec<(String, String)> {
+        let text = std::str::from_utf8(out).unwrap();
+        assert!(text.starts_with("\x1b_G") && text.ends_with("\x1b\\"));
+        text.strip_prefix("\x1b_G")
+            .unwrap()
+            .split("\x1b\\\x1b_G")
+            .map(|s| {
+                let (control, payload) = s.trim_end_matches("\x1b\\").split_once(';').unwrap();
+                (control.to_string(), payload.to_string())
+            })
+            .collect()
+    }
+
+    fn pixels(width: usize, height: usize) -> Vec<u8> {
+        (0..width * height * 3).map(|i| (i * 31 % 251) as u8).collect()
+    }
+
+    #[test]
+    fn chunks_of_4096() {
+        // 40 x 40 x 3 = 4800 octets, 6400 en base64 : deux morceaux
+        let data = pixels(40, 40);
+        let mut out = Vec::new();
+        encode(&data, 40, 40, false, &mut out).unwrap();
+        let seqs = sequences(&out);
+        assert_eq!(seqs.len(), 2);
+        assert_eq!(seqs[0].0, "a=T,f=24,s=40,v=40,i=1,q=2,m=1");
+        assert_eq!(seqs[0].1.len(), CHUNK);
+        assert_eq!(seqs[1].0, "m=0");
+        assert_eq!(seqs[1].1.len(), 6400 - CHUNK);
+
+        let payload: String = seqs.iter().map(|s| s.1.as_str()).collect();
+        assert_eq!(base64::decode(&payload).unwrap(), data);
+    }
+
+    #[test]
+    fn single_chunk_and_exact_multiple() {
+        let mut out = Vec::new();
+        encode(&pixels(2, 2), 2, 2, false, &mut out).unwrap();
+        let seqs = sequences(&out);
+        assert_eq!(seqs.len(), 1);
+        assert!(seqs[0].0.ends_with(",m=0"));
+
+        // 3072 octets donnent exactement 4096 caractères, 6144 exactement deux morceaux
+        for (width, count) in [(32, 1), (64, 2)] {
+            let mut out = Vec::new();
+            encode(&pixels(width, 32), width, 32, false, &mut out).unwrap();
+            let seqs = sequences(&out);
+            assert_eq!(seqs.len(), count);
+            assert!(seqs.iter().all(|s| s.1.len() == CHUNK));
+            assert!(seqs.last().unwrap().0.ends_with("m=0"));
+            assert!(seqs[..count - 1].iter().all(|s| s.0.ends_with("m=1")));
+        }
+    }
+
+    #[test]
+    fn zlib_payload() {
+        let data = pixels(64, 48);
+        let mut out = Vec::new();
+        encode(&data, 64, 48, true, &mut out).unwrap();
+        let seqs = sequences(&out);
+        assert_eq!(seqs[0].0, format!("a=T,f=24,s=64,v=48,i=1,q=2,o=z,m={}", (seqs.len() > 1) as u8));
+
+        let payload: String = seqs.iter().map(|s| s.1.as_str()).collect();
+        let mut decoded = Vec::new();
+        ZlibDecoder::new(&base64::decode(&payload).unwrap()[..]).read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn rejects_empty_or_short() {
+        assert!(encode(&[], 0, 0, false, &mut Vec::new()).is_err());
+        assert!(encode(&[0; 11], 2, 2, false, &mut Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+// Sources de lumière communes aux rasteriseurs et au raytracer.
+// Chaque source a une couleur (RGB 0.0..1.0) et une intensité ; les contributions sont additionnées.
+use crate::math_3d::Vec3;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Light {
+    // Soleil : direction VERS la lumière, sans atténuation
+    Directional {
+        direction: Vec3,
+        color: (f32, f32, f32),
+        intensity: f32,
+    },
+    // Ampoule : atténuation 1 / (constante + linéaire * d + quadratique * d²)
+    Point {
+        position: Vec3,
+        color: (f32, f32, f32),
+        intensity: f32,
+        attenuation: (f32, f32, f32),
+    },
+    // Projecteur : direction = axe du cône (de la lampe vers la scène),
+    // angles (radians, demi-ouverture) : pleine intensité jusqu'à inner, nulle au-delà de outer
+    Spot {
+        position: Vec3,
+        direction: Vec3,
+        color: (f32, f32, f32),
+        intensity: f32,
+        attenuation: (f32, f32, f32),
+        inner: f32,
+        outer: f32,
+    },
+    // Panneau rectangulaire centré en position, de côtés u et v. Il éclaire comme une
+    // lampe ponctuelle placée en son centre ; son étendue sert aux ombres douces du raytracer.
+    Area {
+        position: Vec3,
+        u: Vec3,
+        v: Vec3,
+        color: (f32, f32, f32),
+        intensity: f32,
+        attenuation: (f32, f32, f32),
+    },
+}
+
+// Lumière reçue en un point
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+pub struct Incident {
+    // Direction (normalisée) du point vers la source
+    pub direction: Vec3,
+    // couleur * intensité * atténuation
+    pub radiance: (f32, f32, f32),
+    // Distance à la source (infinie pour une lumière directionnelle)
+    pub distance: f32,
+}
+
+// Pas d'atténuation
+pub const NO_ATTENUATION: (f32, f32, f32) = (1.0, 0.0, 0.0);
+
+#[allow(dead_code)]
+impl Light {
+    // Lumière blanche d'intensité 1 (--light x,y,z)
+    pub fn directional(direction: Vec3) -> Light {
+        Light::Directional { direction, color: (1.0, 1.0, 1.0), intensity: 1.0 }
+    }
+
+    pub fn color(&self) -> (f32, f32, f32) {
+        match *self {
+            Light::Directional { color, .. }
+            | Light::Point { color, .. }
+            | Light::Spot { color, .. }
+            | Light::Area { color, .. } => color,
+        }
+    }
+
+    pub fn intensity(&self) -> f32 {
+        match *self {
+            Light::Directional { intensity, .. }
+            | Light::Point { intensity, .. }
+            | Light::Spot { intensity, .. }
+            | Light::Area { intensity, .. } => intensity,
+        }
+    }
+
+    // None si le point n'est pas éclairé (hors du cône d'un projecteur)
+    pub fn incident(&self, p: Vec3) -> Option<Incident> {
+        let (direction, distance, factor) = match *self {
+            Light::Directional { direction, .. } => (direction.normalize(), f32::INFINITY, 1.0),
+            Light::Point { position, attenuation, .. } | Light::Area { position, attenuation, .. } => {
+                let to_light = position.sub(p);
+                let d = to_light.length();
+                (to_light.normalize(), d, attenuate(attenuation, d))
+            }
+            Light::Spot { position, direction, attenuation, inner, outer, .. } => {
+                let to_light = position.sub(p);
+                let d = to_light.length();
+                let l = to_light.normalize();
+                // Angle entre l'axe du projecteur et le rayon lampe -> point
+                let cos = l.neg().dot(direction.normalize());
+                let (cos_inner, cos_outer) = (inner.cos(), outer.max(inner).cos());
+                let cone = if cos >= cos_inner {
+                    1.0
+                } else if cos <= cos_outer {
+                    0.0
+                } else {
+                    // Bord adouci (smoothstep)
+                    let t = (cos - cos_outer) / (cos_inner - cos_outer);
+                    t * t * (3.0 - 2.0 * t)
+                };
+                (l, d, cone * attenuate(attenuation, d))
+            }
+        };
+        if factor <= 0.0 {
+            return None;
+        }
+        let (r, g, b) = self.color();
+        let k = self.intensity() * factor;
+        Some(Incident { direction, radiance: (r * k, g * k, b * k), distance })
+    }
+
+    pub fn is_area(&self) -> bool {
+        matches!(self, Light::Area { .. })
+    }
+
+    // Point de la surface émettrice, (s, t) dans [0, 1]² ; la position pour les autres sources
+    pub fn sample_point(&self, s: f32, t: f32) -> Option<Vec3> {
+        match *self {
+            Light::Directional { .. } => None,
+            Light::Point { position, .. } | Light::Spot { position, .. } => Some(position),
+            Light::Area { position, u, v, .. } => Some(position.add(u.mul(s - 0.5)).add(v.mul(t - 0.5))),
+        }
+    }
+}
+
+fn attenuate((constant, linear, quadratic): (f32, f32, f32), d: f32) -> f32 {
+    1.0 / (constant + linear * d + quadratic * d * d).max(1e-6)
+}
@@ -1,115 +1,412 @@
 pub(crate) use core::f32;
-use math_3d::raytrace::{self, get_triangles};
+use clap::{CommandFactory, FromArgMatches, ValueEnum, parser::ValueSource};
+use math_3d::raytrace::{self, RaytraceScene};
 use math_3d::{Material, MaterialRaytrace, Transform};
-use sixel_rs::encoder::Encoder;
-use sixel_sys::PixelFormat;
+use std::cmp::Ordering;
+use std::fmt;
 use std::io::{Write};
 use std::io::stdout;
+use std::process::ExitCode;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crate::{
+    animation::{Keyframes, Turntable, frame_path},
+    cli::{AnimateArgs, Backend, Cli, Command, RenderArgs},
+    environment::{Environment, EnvironmentSpec},
     frame_buffer::FrameBuffer,
-    math_3d::{Point3d, Vec3},
+    math_3d::Camera,
+    mesh::{LoadError, Mesh},
+    mtl::MtlError,
+    output::{Protocol, Sink},
+    scene::Scene,
+    scene_file::SceneError,
+    shadow_map::ShadowMaps,
+    terminal::{Capabilities, QUERY_TIMEOUT},
+    texture::TextureError,
 };
 
+mod animation;
+mod base64;
+mod cli;
+mod bvh;
 mod cube;
+mod environment;
 mod frame_buffer;
+mod interactive;
+mod iterm;
+mod kitty;
+mod light;
 mod math_3d;
+mod mesh;
+mod mtl;
+mod output;
 mod penger;
+mod scene;
+mod scene_file;
+mod shadow_map;
+#[cfg(feature = "native-sixel")]
+mod sixel;
+mod terminal;
+mod text;
+mod texture;
 
-const WIDTH: usize = 1280;
-const HEIGHT: usize = 1280;
+// Erreurs remontées jusqu'à main, chacune avec son code de sortie
+#[derive(Debug)]
+enum AppError {
+    Mesh(LoadError),
+    Scene(SceneError),
+    Environment(TextureError),
+    #[cfg(feature = "libsixel")]
+    Encoder(sixel_rs::status::Error),
+    Io(std::io::Error),
+    // Ligne de commande incohérente, comme les erreurs de clap
+    Usage(String),
+}
+
+impl AppError {
+    fn exit_code(&self) -> ExitCode {
+        match self {
+            AppError::Mesh(_) | AppError::Scene(_) | AppError::Environment(_) => ExitCode::from(3),
+            #[cfg(feature = "libsixel")]
+            AppError::Encoder(_) => ExitCode::from(4),
+            AppError::Io(_) => ExitCode::from(5),
+            AppError::Usage(_) => ExitCode::from(2),
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AppError::Mesh(e) => write!(f, "{}", e),
+            AppError::Scene(e) => write!(f, "can't load scene {}", e),
+            AppError::Environment(e) => write!(f, "can't load environment {}", e),
+            #[cfg(feature = "libsixel")]
+            AppError::Encoder(e) => write!(f, "sixel encoder failed: {:?}", e),
+            AppError::Io(e) => write!(f, "{}", e),
+            AppError::Usage(msg) => write!(f, "{}", msg),
+        }
+    }
+}
 
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        AppError::Io(e)
+    }
+}
+
+#[cfg(feature = "libsixel")]
+impl From<sixel_rs::status::Error> for AppError {
+    fn from(e: sixel_rs::status::Error) -> Self {
+        AppError::Encoder(e)
+    }
+}
 
-fn clear_stdout() -> Result<(), Box<dyn std::error::Error>> {
+fn clear_stdout() -> Result<(), std::io::Error> {
     print!("\x1b[H");
     stdout().flush()?;
     Ok(())
 }
 
-fn flush_stdout() -> Result<(), Box<dyn std::error::Error>> {
+fn flush_stdout() -> Result<(), std::io::Error> {
     stdout().flush()?;
     Ok(())
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+#[allow(clippy::too_many_arguments)]
+fn render<'s>(
+    scene: &'s Scene,
+    backend: Backend,
+    args: &RenderArgs,
+    camera: &Camera,
+    environment: &Environment,
+    transforms: &[&Transform],
+    fb: &mut FrameBuffer,
+    z_buffer: &mut [f32],
+    raytrace_scene: &mut Option<RaytraceScene<'s>>,
+    on_pass: &mut dyn FnMut(&FrameBuffer, u32),
+) {
+    let (width, height) = (args.width as u32, args.height as u32);
+    let model_matrix = Transform::compose(transforms);
 
-    // Lecture d'un modele wavefront
-    let model = wavefront::Obj::from_file("torso_slayer.obj")?;
-    dbg!(&model.triangles().count());
+    z_buffer.fill(f32::NEG_INFINITY);
 
-    #[allow(unreachable_code)]    
-    let encoder: Encoder = match Encoder::new() {
-        Ok(o) => o,
-        Err(e) => {
-            dbg!(e);
-            let err = std::io::Error::other("Can't create Encoder");
-            // return Err(err.into());
-            return Err(Box::new(err));
+    match backend {
+        Backend::Gouraud | Backend::Phong => {
+            let material = args
+                .material
+                .as_deref()
+                .and_then(Material::from_name)
+                .unwrap_or_else(Material::white_plastic);
+
+            // Carte d'ombre de chaque source, recalculée à chaque image (la scène peut bouger)
+            let shadows = ShadowMaps::build(scene, &model_matrix, &args.lights, args.shadow_map_size as usize, args.shadow_pcf, args.shadow_bias);
+
+            let draw = if backend == Backend::Gouraud {
+                math_3d::utils::draw_scene_gouraud
+            } else {
+                math_3d::utils::draw_scene_phong
+            };
+
+            // SSAA : rendu aa fois plus grand dans chaque dimension, puis réduit dans fb
+            let aa = args.aa as usize;
+            if aa > 1 {
+                let mut large = FrameBuffer::new(args.width * aa, args.height * aa);
+                let mut large_z = vec![f32::NEG_INFINITY; large.width * large.height];
+                environment.fill(camera, &mut large);
+                draw(scene, &model_matrix, &material, camera, &args.lights, &shadows, width * aa as u32, height * aa as u32, &mut large, &mut large_z);
+                fb.downsample_from(&large, aa);
+            } else {
+                environment.fill(camera, fb);
+                draw(scene, &model_matrix, &material, camera, &args.lights, &shadows, width, height, fb, z_buffer);
+            }
         }
-    };
+        Backend::Raytrace | Backend::Pathtrace => {
+            // Triangles, matériaux (usemtl), textures (map_Kd) et BVH de toutes les instances :
+            // construits au premier rendu, puis réajustés quand la transformation change
+            let data = match raytrace_scene {
+                Some(data) => {
+                    data.update(&model_matrix);
+                    data
+                }
+                None => {
+                    let material = args
+                        .material
+                        .as_deref()
+                        .and_then(MaterialRaytrace::from_name)
+                        .unwrap_or_else(MaterialRaytrace::epic_slayer);
+                    raytrace_scene.insert(RaytraceScene::new(scene, &model_matrix, &material))
+                }
+            };
 
-    let mut fb: FrameBuffer = FrameBuffer::new(WIDTH, HEIGHT);
-    fb.clean((255, 255, 255));
-    clear_stdout()?;
+            if backend == Backend::Raytrace {
+                raytrace::render_raytrace(data, camera, environment, &args.lights, args.shadow_samples, args.aa, width, height, fb);
+            } else {
+                let seed = args.seed.unwrap_or_else(random_seed);
+                raytrace::render_pathtrace(data, camera, environment, &args.lights, args.spp, seed, width, height, fb, on_pass);
+            }
+        }
+    }
+}
+
+// Graine du path tracing quand --seed n'est pas donné
+fn random_seed() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64)
+}
 
-    let mut z_buffer: Vec<f32> = vec![f32::NEG_INFINITY; WIDTH * HEIGHT];
-
-    let target: Point3d = (0.0, 0.0, -0.0);
-    let focal: f32 = WIDTH as f32 * 2.0;
-    let eye: Point3d = (0.0, 0.0, 210.0);
-    let light_dir: Vec3 = Vec3 {
-        x: -0.5,
-        y: 1.0,
-        z: 1.0,
-    }.normalize();
-
-    let t1: math_3d::Transform = math_3d::Transform {
-        scale: 1.0, 
-        rotation: (std::f32::consts::PI / 2.0, 0.0, 0.0),
-        translation: (0.0, 0.0, 0.0), 
+// Sans --env : fond uni --background pour les rasteriseurs, dégradé historique pour les lancers de rayons
+fn load_environment(args: &RenderArgs, backend: Backend) -> Result<Environment, AppError> {
+    let spec = match (&args.environment, backend) {
+        (Some(spec), _) => spec.clone(),
+        (None, Backend::Gouraud | Backend::Phong) => {
+            let (r, g, b) = args.background;
+            EnvironmentSpec::Solid((r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0))
+        }
+        (None, Backend::Raytrace | Backend::Pathtrace) => EnvironmentSpec::SKY,
     };
+    Environment::load(&spec).map_err(AppError::Environment)
+}
 
-    let t2: math_3d::Transform = math_3d::Transform {
-        scale: 1.0, 
-        rotation: (0.0, std::f32::consts::PI, 0.0),
-        translation: (0.0, 0.0, 0.0), 
+// Tour complet du modèle (ou passage par les poses clés) : soit un flux sixel (terminal ou fichier), soit un fichier par image
+fn run_animation(scene: &Scene, anim: &AnimateArgs, sink: &mut Sink) -> Result<(), AppError> {
+    let args = &anim.render;
+    let environment = load_environment(args, anim.backend)?;
+    let turntable = Turntable { frames: anim.frames.max(1), axis: anim.axis };
+    let keyframes = (!anim.keys.is_empty()).then(|| Keyframes { keys: anim.keys.clone() });
+    let to_terminal = args.output.is_none() && anim.sequence.is_none();
+    // Sur fichier, un seul tour suffit
+    let total = if !to_terminal {
+        turntable.frames
+    } else if anim.loops == 0 {
+        usize::MAX
+    } else {
+        turntable.frames.saturating_mul(anim.loops)
     };
-    
-    fb.clean((0, 128, 255));
+
+    let mut fb: FrameBuffer = FrameBuffer::new(args.width, args.height);
+    let mut z_buffer: Vec<f32> = vec![f32::NEG_INFINITY; args.width * args.height];
+    let mut raytrace_scene = None;
+
+    for frame in 0..total {
+        let spin = match &keyframes {
+            // Aller-retour entre les poses quand on boucle
+            Some(k) => {
+                let f = frame % (2 * turntable.frames);
+                let f = if f < turntable.frames { f } else { 2 * turntable.frames - 1 - f };
+                k.transform(f, turntable.frames)
+            }
+            None => turntable.transform(frame),
+        };
+        let mut transforms: Vec<&Transform> = args.transforms.iter().collect();
+        transforms.push(&spin);
+
+        render(scene, anim.backend, args, &args.camera(args.eye), &environment, &transforms, &mut fb, &mut z_buffer, &mut raytrace_scene, &mut |_, _| {});
+
+        if let Some(prefix) = &anim.sequence {
+            sink.set_output(&frame_path(prefix, frame, sink.extension()))?;
+        }
+        if to_terminal {
+            clear_stdout()?;
+        }
+        sink.write(&fb)?;
+        flush_stdout()?;
+
+        if to_terminal {
+            std::thread::sleep(Duration::from_millis(anim.delay));
+        }
+    }
+
+    Ok(())
+}
+
+// Image affichée par-dessus la précédente dans le terminal
+fn show_frame(sink: &mut Sink, fb: &FrameBuffer) -> Result<(), AppError> {
     clear_stdout()?;
+    sink.write(fb)?;
+    flush_stdout()?;
+    Ok(())
+}
 
-    
-    z_buffer.fill(f32::NEG_INFINITY);
+fn print_warnings(warnings: Vec<MtlError>) {
+    for w in warnings {
+        eprintln!("sixel-3d: warning: {}", w);
+    }
+}
 
+// Protocole, taille de l'image (sans -W ni -H) et limite des images sixel d'après les
+// réponses du terminal
+fn adapt_to_terminal(args: &mut RenderArgs, capabilities: Option<&Capabilities>) {
+    args.format.protocol = args.format.protocol.resolve(capabilities);
+    let Some(capabilities) = capabilities else {
+        return;
+    };
+    if args.default_size
+        && let Some(mut side) = capabilities.image_side()
+    {
+        if args.format.protocol == Protocol::Sixel
+            && let Some((width, height)) = capabilities.sixel_geometry
+        {
+            side = side.min(width).min(height);
+        }
+        args.width = side;
+        args.height = side;
+    }
+}
 
-    let transforms: Vec<&Transform> = vec![&t1, &t2];
-    // let transforms: Vec<&Transform> = vec![];
+fn run(cli: &Cli, capabilities: Option<&Capabilities>) -> Result<(), AppError> {
+    // Scène décrite par un fichier, ou un seul modèle avec les réglages de la ligne de commande
+    let described;
+    let (scene, backend, args) = match &cli.command {
+        Command::Scene(s) => {
+            let description = scene_file::load_scene(&s.file).map_err(AppError::Scene)?;
+            print_warnings(description.warnings);
+            let mut args = description.args;
+            args.output = s.output.clone();
+            args.interactive = s.interactive;
+            args.format = s.format;
+            adapt_to_terminal(&mut args, capabilities);
+            described = args;
+            (description.scene, s.backend.unwrap_or(description.backend), &described)
+        }
+        command => {
+            let (Some(args), Some(backend)) = (command.args(), command.backend()) else {
+                return Err(AppError::Usage("no model or renderer to render".to_string()));
+            };
+            // Les normales manquantes sont calculées une seule fois, à la construction du maillage
+            let (mesh, warnings) =
+                Mesh::load(&args.model, !args.no_mtl, args.crease_angle.to_radians()).map_err(AppError::Mesh)?;
+            print_warnings(warnings);
+            (Scene::from_mesh(mesh), backend, args)
+        }
+    };
 
-    math_3d::utils::draw_obj_model_gouraud(&model, &transforms, &Material::white_plastic(), eye, target, light_dir, focal, WIDTH as u32, HEIGHT as u32, &mut fb, &mut z_buffer);
+    // fov, focal, ortho et near sont vérifiés un par un à la lecture, il reste leur combinaison
+    if args.near.partial_cmp(&args.far) != Some(Ordering::Less) {
+        return Err(AppError::Usage(format!("--near ({}) must be less than --far ({})", args.near, args.far)));
+    }
 
+    let mut sink = Sink::new(args, capabilities)?;
 
-    // Récupération des triangles au format raytrace
-    let all_triangles: Vec<(Vec3, Vec3, Vec3, Vec3, Vec3, Vec3)> = get_triangles(model.triangles());
+    if let Command::Animate(anim) = &cli.command {
+        return run_animation(&scene, anim, &mut sink);
+    }
 
-    // Transformations
-    let all_transformed_triangles: Vec<(Vec3, Vec3, Vec3, Vec3, Vec3, Vec3)> = raytrace::do_transforms(all_triangles, &transforms);
+    let environment = load_environment(args, backend)?;
+    let transforms: Vec<&Transform> = args.transforms.iter().collect();
+    let mut fb: FrameBuffer = FrameBuffer::new(args.width, args.height);
+    let mut z_buffer: Vec<f32> = vec![f32::NEG_INFINITY; args.width * args.height];
+    let mut raytrace_scene = None;
 
-    // Render
-    raytrace::render_raytrace(&all_transformed_triangles, eye, target, light_dir, &&MaterialRaytrace::epic_slayer(), WIDTH as u32, HEIGHT as u32, &mut fb);
+    if args.interactive {
+        return interactive::run(args.eye, args.target, |eye| {
+            render(&scene, backend, args, &args.camera(eye), &environment, &transforms, &mut fb, &mut z_buffer, &mut raytrace_scene, &mut |_, _| {});
+            clear_stdout()?;
+            sink.write(&fb)?;
+            flush_stdout()?;
+            Ok(())
+        });
+    }
 
-    
-    encoder
-        .encode_bytes_ext(WIDTH, HEIGHT, &fb.pixels, PixelFormat::RGB888)
-        .map_err(|e| {
-            dbg!(e);
-            std::io::Error::other("")
-        })?;
+    // Path tracing vers le terminal : l'image s'affiche et s'affine pendant le calcul
+    let progressive = backend == Backend::Pathtrace && args.output.is_none();
+    let mut preview: Result<(), AppError> = Ok(());
+    render(&scene, backend, args, &args.camera(args.eye), &environment, &transforms, &mut fb, &mut z_buffer, &mut raytrace_scene, &mut |fb, done| {
+        if progressive && done < args.spp && preview.is_ok() {
+            preview = show_frame(&mut sink, fb);
+        }
+    });
+    preview?;
 
+    if args.output.is_none() {
+        clear_stdout()?;
+    }
+    sink.write(&fb)?;
     flush_stdout()?;
 
-    // angle += 0.1;
-    //}
-
-    #[allow(unreachable_code)]
     Ok(())
 }
+
+fn main() -> ExitCode {
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let Some((_, sub)) = matches.subcommand()
+        && let Some(args) = cli.command.args_mut()
+    {
+        args.default_size = ["width", "height"].iter().all(|id| sub.value_source(id) == Some(ValueSource::DefaultValue));
+    }
+    if let Command::Animate(anim) = &cli.command
+        && anim.render.interactive
+    {
+        Cli::command()
+            .error(clap::error::ErrorKind::ArgumentConflict, "--interactive can't be used with animate")
+            .exit();
+    }
+    let encoder = cli.command.format().sixel_encoder;
+    if !encoder.available() {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::InvalidValue,
+                format!(
+                    "--sixel-encoder {}: not compiled into this binary",
+                    encoder.to_possible_value().expect("valeur de l'option").get_name()
+                ),
+            )
+            .exit();
+    }
+
+    // Terminal muet ou absent : réglages de la ligne de commande, détection par l'environnement
+    let capabilities = (cli.command.to_terminal() && terminal::stdout_is_tty())
+        .then(|| terminal::query_tty(QUERY_TIMEOUT).ok())
+        .flatten();
+    if let Some(args) = cli.command.args_mut() {
+        adapt_to_terminal(args, capabilities.as_ref());
+    }
+
+    match run(&cli, capabilities.as_ref()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("sixel-3d: {}", e);
+            e.exit_code()
+        }
+    }
+}
@@ -4,16 +4,6 @@ pub type Point2d = (i32, i32);
 #[allow(dead_code)]
 pub type Point3d = (f32, f32, f32);
 
-pub trait ToPoint3d {
-    fn to_p3d(self) -> Point3d;
-}
-
-impl ToPoint3d for (f64, f64, f64) {
-    fn to_p3d(self) -> Point3d {
-        (self.0 as f32, self.1 as f32, self.2 as f32)
-    }
-}
-
 #[allow(dead_code)]
 pub type Color = (u8, u8, u8);
 
@@ -51,7 +41,7 @@ impl Vec3 {
         }
     }
 
-    fn from_point3d(p: Point3d) -> Self {
+    pub fn from_point3d(p: Point3d) -> Self {
         Vec3 {
             x: p.0,
             y: p.1,
@@ -60,7 +50,7 @@ impl Vec3 {
     }
 
     
-    fn to_point3d(&self) -> Point3d {
+    pub fn to_point3d(self) -> Point3d {
         (self.x, self.y, self.z)
     }
 
@@ -132,6 +122,19 @@ impl Vec3 {
         // Ou plus simplement si tu as sub(self, other):
         // self.sub(normal.mul(2.0 * self.dot(normal)))
     }
+
+    // Loi de Snell-Descartes : 'self' (normalisé) traverse une surface de normale `normal`
+    // (du côté du rayon incident), eta = n_incident / n_transmis.
+    // None : réflexion totale interne
+    pub fn refract(&self, normal: Vec3, eta: f32) -> Option<Vec3> {
+        let cos_i = -self.dot(normal);
+        let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+        if sin2_t > 1.0 {
+            return None;
+        }
+        let cos_t = (1.0 - sin2_t).sqrt();
+        Some(self.mul(eta).add(normal.mul(eta * cos_i - cos_t)).normalize())
+    }
 }
 
 
@@ -158,7 +161,7 @@ pub struct Vec3WithColor {
 }
 
 #[allow(dead_code)]
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy)]
 pub struct Material {
     pub ka: (f32, f32, f32), // Ambiant RGB
     pub kd: (f32, f32, f32), // Diffus RGB
@@ -168,12 +171,13 @@ pub struct Material {
 
 
 #[allow(dead_code)]
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy)]
 pub struct MaterialRaytrace {
     pub material: Material,
     pub reflectivity: f32,    // 0.0 (mat) à 1.0 (miroir parfait)
     pub transparency: f32,    // 0.0 (opaque) à 1.0 (verre)
     pub refractive_index: f32, // ex: 1.0 pour air, 1.5 pour verre    
+    pub emission: (f32, f32, f32), // Lumière émise RGB (0.0 : n'émet pas), peut dépasser 1.0
 }
 
 #[allow(dead_code)]
@@ -257,6 +261,54 @@ impl Material {
     pub fn yellow_rubber() -> Self {
         Self { ka: (0.05, 0.05, 0.0), kd: (0.5, 0.5, 0.4), ks: (0.7, 0.7, 0.04), ns: 10.0 }
     }
+
+    // Noms des préréglages, tels qu'acceptés par from_name (ligne de commande)
+    pub const PRESETS: &'static [&'static str] = &[
+        "emerald", "jade", "obsidian", "pearl", "ruby", "turquoise",
+        "brass", "bronze", "chrome", "copper", "gold", "silver",
+        "black_plastic", "cyan_plastic", "green_plastic", "red_plastic", "white_plastic", "yellow_plastic",
+        "black_rubber", "cyan_rubber", "green_rubber", "red_rubber", "white_rubber", "yellow_rubber",
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let m = match name {
+            "emerald" => Self::emerald(),
+            "jade" => Self::jade(),
+            "obsidian" => Self::obsidian(),
+            "pearl" => Self::pearl(),
+            "ruby" => Self::ruby(),
+            "turquoise" => Self::turquoise(),
+            "brass" => Self::brass(),
+            "bronze" => Self::bronze(),
+            "chrome" => Self::chrome(),
+            "copper" => Self::copper(),
+            "gold" => Self::gold(),
+            "silver" => Self::silver(),
+            "black_plastic" => Self::black_plastic(),
+            "cyan_plastic" => Self::cyan_plastic(),
+            "green_plastic" => Self::green_plastic(),
+            "red_plastic" => Self::red_plastic(),
+            "white_plastic" => Self::white_plastic(),
+            "yellow_plastic" => Self::yellow_plastic(),
+            "black_rubber" => Self::black_rubber(),
+            "cyan_rubber" => Self::cyan_rubber(),
+            "green_rubber" => Self::green_rubber(),
+            "red_rubber" => Self::red_rubber(),
+            "white_rubber" => Self::white_rubber(),
+            "yellow_rubber" => Self::yellow_rubber(),
+            _ => return None,
+        };
+        Some(m)
+    }
+
+    // map_Kd : la couleur de la texture module l'ambiant et le diffus
+    pub fn with_texel(self, texel: (f32, f32, f32)) -> Self {
+        Self {
+            ka: (self.ka.0 * texel.0, self.ka.1 * texel.1, self.ka.2 * texel.2),
+            kd: (self.kd.0 * texel.0, self.kd.1 * texel.1, self.kd.2 * texel.2),
+            ..self
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -273,6 +325,7 @@ impl MaterialRaytrace {
             reflectivity: 0.6,       // 60% de miroir
             transparency: 0.0,
             refractive_index: 1.0,
+            emission: (0.0, 0.0, 0.0),
         }
     }
 
@@ -282,6 +335,7 @@ impl MaterialRaytrace {
             reflectivity: 0.9,            // 90% de réflexion (très brillant)
             transparency: 0.0,            // Opaque
             refractive_index: 1.0,        // Pas de réfraction
+            emission: (0.0, 0.0, 0.0),
         }
     }
 
@@ -293,14 +347,67 @@ impl MaterialRaytrace {
                 ks: (1.0, 1.0, 1.0),    // Spéculaire blanc pur (reflets de surface brillants)
                 ns: 150.0,              // Très poli
             },
-            reflectivity: 0.2,          // 20% de reflet miroir (important pour la brillance)
-            transparency: 0.8,          // 80% de transparence
+            reflectivity: 0.2,          // 20% de reflet miroir sur la partie opaque
+            transparency: 0.9,          // 90% de transparence (reflet de surface : Fresnel)
             refractive_index: 1.33,     // Indice de l'eau/glace
+            emission: (0.0, 0.0, 0.0),
+        }
+    }
+
+    pub fn glass() -> MaterialRaytrace {
+        MaterialRaytrace {
+            material: Material {
+                ka: (0.0, 0.0, 0.0),
+                kd: (0.0, 0.0, 0.0),    // Tout passe par la réflexion et la réfraction
+                ks: (1.0, 1.0, 1.0),
+                ns: 300.0,
+            },
+            reflectivity: 0.0,          // Réflexion donnée par Fresnel
+            transparency: 1.0,
+            refractive_index: 1.5,      // Verre sodocalcique
+            emission: (0.0, 0.0, 0.0),
+        }
+    }
+
+    // Panneau lumineux blanc : éclaire la scène en path tracing, simplement lumineux ailleurs
+    pub fn emitter() -> MaterialRaytrace {
+        MaterialRaytrace {
+            material: Material {
+                ka: (0.0, 0.0, 0.0),
+                kd: (0.8, 0.8, 0.8),
+                ks: (0.0, 0.0, 0.0),
+                ns: 1.0,
+            },
+            reflectivity: 0.0,
+            transparency: 0.0,
+            refractive_index: 1.0,
+            emission: (5.0, 5.0, 5.0),
+        }
+    }
+
+    pub const PRESETS: &'static [&'static str] = &["epic_slayer", "chrome_raytrace", "ice_crystal", "glass", "emitter"];
+
+    // Préréglages raytrace, sinon un matériau simple opaque et sans reflet
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "epic_slayer" => Some(Self::epic_slayer()),
+            "chrome_raytrace" => Some(Self::chrome_raytrace()),
+            "ice_crystal" => Some(Self::ice_crystal()),
+            "glass" => Some(Self::glass()),
+            "emitter" => Some(Self::emitter()),
+            _ => Material::from_name(name).map(|material| MaterialRaytrace {
+                material,
+                reflectivity: 0.0,
+                transparency: 0.0,
+                refractive_index: 1.0,
+                emission: (0.0, 0.0, 0.0),
+            }),
         }
     }
 }
 
 
+#[derive(Debug, Clone, Copy)]
 pub struct Transform {
     pub scale: f32,
     pub rotation: (f32, f32, f32), // (angle_x, angle_y, angle_z)
@@ -386,6 +493,559 @@ impl Transform {
     }
 }
 
+#[allow(dead_code)]
+impl Transform {
+    // Matrice équivalente à transform_point : scale, puis rotations Y -> X -> Z, puis translation
+    pub fn to_mat4(self) -> Mat4 {
+        Mat4::translation(self.translation.0, self.translation.1, self.translation.2)
+            .mul(&Mat4::from_euler(self.rotation.0, self.rotation.1, self.rotation.2))
+            .mul(&Mat4::scale(self.scale, self.scale, self.scale))
+    }
+
+    // Réduit une chaîne de transformations (la première est appliquée en premier) en une seule matrice
+    pub fn compose(transforms: &[&Transform]) -> Mat4 {
+        transforms
+            .iter()
+            .fold(Mat4::IDENTITY, |acc, t| t.to_mat4().mul(&acc))
+    }
+}
+
+#[allow(dead_code)]
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        scale: 1.0,
+        rotation: (0.0, 0.0, 0.0),
+        translation: (0.0, 0.0, 0.0),
+    };
+
+    pub fn orientation(&self) -> Quat {
+        Quat::from_euler(self.rotation.0, self.rotation.1, self.rotation.2)
+    }
+
+    pub fn with_orientation(&self, q: Quat) -> Transform {
+        Transform { rotation: q.to_euler(), ..*self }
+    }
+
+    // Interpolation entre deux poses : linéaire pour l'échelle et la translation,
+    // slerp pour l'orientation (pas de blocage de cardan ni de chemin "tordu")
+    pub fn interpolate(&self, other: &Transform, t: f32) -> Transform {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        let q = self.orientation().slerp(other.orientation(), t);
+        Transform {
+            scale: lerp(self.scale, other.scale),
+            rotation: q.to_euler(),
+            translation: (
+                lerp(self.translation.0, other.translation.0),
+                lerp(self.translation.1, other.translation.1),
+                lerp(self.translation.2, other.translation.2),
+            ),
+        }
+    }
+}
+
+impl From<&Transform> for Mat4 {
+    fn from(t: &Transform) -> Self {
+        t.to_mat4()
+    }
+}
+
+// Quaternion unitaire (x, y, z, w) pour les rotations
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+#[allow(dead_code)]
+impl Quat {
+    pub const IDENTITY: Quat = Quat { x: 0.0, y: 0.0, z: 0.0, w: 1.0 };
+
+    pub fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self { x, y, z, w }
+    }
+
+    // Rotation d'un angle (radians) autour d'un axe quelconque
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Quat {
+        let a = axis.normalize();
+        let (s, c) = (angle * 0.5).sin_cos();
+        Quat::new(a.x * s, a.y * s, a.z * s, c)
+    }
+
+    // Mêmes angles et même ordre que Transform::rotation : Y, puis X, puis Z
+    pub fn from_euler(ax: f32, ay: f32, az: f32) -> Quat {
+        let qx = Quat::from_axis_angle(Vec3::new(1.0, 0.0, 0.0), ax);
+        let qy = Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), ay);
+        let qz = Quat::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), az);
+        qz.mul(qx).mul(qy)
+    }
+
+    // Retour aux angles (angle_x, angle_y, angle_z) de Transform::rotation
+    pub fn to_euler(self) -> (f32, f32, f32) {
+        let m = self.to_mat3().m;
+        // R = Rz * Rx * Ry  =>  m[2][1] = sin(ax)
+        let ax = m[2][1].clamp(-1.0, 1.0).asin();
+        if m[2][1].abs() < 0.99999 {
+            let ay = (-m[2][0]).atan2(m[2][2]);
+            let az = (-m[0][1]).atan2(m[1][1]);
+            (ax, ay, az)
+        } else {
+            // Blocage de cardan : Y et Z tournent autour du même axe, on met tout sur Y
+            (std::f32::consts::FRAC_PI_2.copysign(m[2][1]), m[0][2].atan2(m[0][0]), 0.0)
+        }
+    }
+
+    // Composition : a.mul(b) applique d'abord b puis a
+    pub fn mul(self, o: Quat) -> Quat {
+        Quat::new(
+            self.w * o.x + self.x * o.w + self.y * o.z - self.z * o.y,
+            self.w * o.y - self.x * o.z + self.y * o.w + self.z * o.x,
+            self.w * o.z + self.x * o.y - self.y * o.x + self.z * o.w,
+            self.w * o.w - self.x * o.x - self.y * o.y - self.z * o.z,
+        )
+    }
+
+    pub fn dot(self, o: Quat) -> f32 {
+        self.x * o.x + self.y * o.y + self.z * o.z + self.w * o.w
+    }
+
+    pub fn conjugate(self) -> Quat {
+        Quat::new(-self.x, -self.y, -self.z, self.w)
+    }
+
+    pub fn normalize(self) -> Quat {
+        let len = self.dot(self).sqrt();
+        if len > 0.0 {
+            Quat::new(self.x / len, self.y / len, self.z / len, self.w / len)
+        } else {
+            Quat::IDENTITY
+        }
+    }
+
+    // Interpolation sphérique à vitesse angulaire constante, par le chemin le plus court
+    pub fn slerp(self, other: Quat, t: f32) -> Quat {
+        let mut cos_theta = self.dot(other);
+        let mut end = other;
+        if cos_theta < 0.0 {
+            cos_theta = -cos_theta;
+            end = Quat::new(-other.x, -other.y, -other.z, -other.w);
+        }
+
+        let (k0, k1) = if cos_theta > 0.9995 {
+            // Angles très proches : l'interpolation linéaire suffit et évite la division par ~0
+            (1.0 - t, t)
+        } else {
+            let theta = cos_theta.acos();
+            let sin_theta = theta.sin();
+            (((1.0 - t) * theta).sin() / sin_theta, (t * theta).sin() / sin_theta)
+        };
+
+        Quat::new(
+            self.x * k0 + end.x * k1,
+            self.y * k0 + end.y * k1,
+            self.z * k0 + end.z * k1,
+            self.w * k0 + end.w * k1,
+        )
+        .normalize()
+    }
+
+    pub fn rotate_vec3(self, v: Vec3) -> Vec3 {
+        let p = Quat::new(v.x, v.y, v.z, 0.0);
+        let r = self.mul(p).mul(self.conjugate());
+        Vec3::new(r.x, r.y, r.z)
+    }
+
+    pub fn to_mat4(self) -> Mat4 {
+        Mat4::from_quaternion(self.x, self.y, self.z, self.w)
+    }
+
+    pub fn to_mat3(self) -> Mat3 {
+        self.to_mat4().to_mat3()
+    }
+}
+
+// Matrice 3x3 (rotation / échelle), utilisée pour transformer les normales
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat3 {
+    pub m: [[f32; 3]; 3], // m[ligne][colonne]
+}
+
+#[allow(dead_code)]
+impl Mat3 {
+    pub const IDENTITY: Mat3 = Mat3 {
+        m: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+    };
+
+    pub fn mul(&self, other: &Mat3) -> Mat3 {
+        let mut r = [[0.0; 3]; 3];
+        for (i, row) in r.iter_mut().enumerate() {
+            for (j, v) in row.iter_mut().enumerate() {
+                *v = (0..3).map(|k| self.m[i][k] * other.m[k][j]).sum();
+            }
+        }
+        Mat3 { m: r }
+    }
+
+    pub fn mul_vec3(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+
+    pub fn transpose(&self) -> Mat3 {
+        let m = &self.m;
+        Mat3 {
+            m: [
+                [m[0][0], m[1][0], m[2][0]],
+                [m[0][1], m[1][1], m[2][1]],
+                [m[0][2], m[1][2], m[2][2]],
+            ],
+        }
+    }
+
+    pub fn determinant(&self) -> f32 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    // Inverse par la comatrice, None si la matrice est singulière
+    pub fn inverse(&self) -> Option<Mat3> {
+        let det = self.determinant();
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let m = &self.m;
+        let inv_det = 1.0 / det;
+        Some(Mat3 {
+            m: [
+                [
+                    (m[1][1] * m[2][2] - m[1][2] * m[2][1]) * inv_det,
+                    (m[0][2] * m[2][1] - m[0][1] * m[2][2]) * inv_det,
+                    (m[0][1] * m[1][2] - m[0][2] * m[1][1]) * inv_det,
+                ],
+                [
+                    (m[1][2] * m[2][0] - m[1][0] * m[2][2]) * inv_det,
+                    (m[0][0] * m[2][2] - m[0][2] * m[2][0]) * inv_det,
+                    (m[0][2] * m[1][0] - m[0][0] * m[1][2]) * inv_det,
+                ],
+                [
+                    (m[1][0] * m[2][1] - m[1][1] * m[2][0]) * inv_det,
+                    (m[0][1] * m[2][0] - m[0][0] * m[2][1]) * inv_det,
+                    (m[0][0] * m[1][1] - m[0][1] * m[1][0]) * inv_det,
+                ],
+            ],
+        })
+    }
+}
+
+// Matrice 4x4 homogène, convention vecteurs colonnes : p' = M * p
+// a.mul(&b) applique d'abord b puis a
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    pub m: [[f32; 4]; 4], // m[ligne][colonne]
+}
+
+#[allow(dead_code)]
+impl Mat4 {
+    pub const IDENTITY: Mat4 = Mat4 {
+        m: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    };
+
+    pub fn translation(x: f32, y: f32, z: f32) -> Mat4 {
+        let mut r = Mat4::IDENTITY;
+        r.m[0][3] = x;
+        r.m[1][3] = y;
+        r.m[2][3] = z;
+        r
+    }
+
+    // Échelle non uniforme
+    pub fn scale(sx: f32, sy: f32, sz: f32) -> Mat4 {
+        let mut r = Mat4::IDENTITY;
+        r.m[0][0] = sx;
+        r.m[1][1] = sy;
+        r.m[2][2] = sz;
+        r
+    }
+
+    pub fn rotation_x(a: f32) -> Mat4 {
+        let (s, c) = a.sin_cos();
+        let mut r = Mat4::IDENTITY;
+        r.m[1][1] = c;
+        r.m[1][2] = -s;
+        r.m[2][1] = s;
+        r.m[2][2] = c;
+        r
+    }
+
+    pub fn rotation_y(a: f32) -> Mat4 {
+        let (s, c) = a.sin_cos();
+        let mut r = Mat4::IDENTITY;
+        r.m[0][0] = c;
+        r.m[0][2] = s;
+        r.m[2][0] = -s;
+        r.m[2][2] = c;
+        r
+    }
+
+    pub fn rotation_z(a: f32) -> Mat4 {
+        let (s, c) = a.sin_cos();
+        let mut r = Mat4::IDENTITY;
+        r.m[0][0] = c;
+        r.m[0][1] = -s;
+        r.m[1][0] = s;
+        r.m[1][1] = c;
+        r
+    }
+
+    // Même ordre que Transform : Y, puis X, puis Z
+    pub fn from_euler(ax: f32, ay: f32, az: f32) -> Mat4 {
+        Mat4::rotation_z(az).mul(&Mat4::rotation_x(ax)).mul(&Mat4::rotation_y(ay))
+    }
+
+    // Rotation à partir d'un quaternion unitaire (x, y, z, w)
+    pub fn from_quaternion(x: f32, y: f32, z: f32, w: f32) -> Mat4 {
+        let (xx, yy, zz) = (x * x, y * y, z * z);
+        let (xy, xz, yz) = (x * y, x * z, y * z);
+        let (wx, wy, wz) = (w * x, w * y, w * z);
+        Mat4 {
+            m: [
+                [1.0 - 2.0 * (yy + zz), 2.0 * (xy - wz), 2.0 * (xz + wy), 0.0],
+                [2.0 * (xy + wz), 1.0 - 2.0 * (xx + zz), 2.0 * (yz - wx), 0.0],
+                [2.0 * (xz - wy), 2.0 * (yz + wx), 1.0 - 2.0 * (xx + yy), 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    pub fn mul(&self, other: &Mat4) -> Mat4 {
+        let mut r = [[0.0; 4]; 4];
+        for (i, row) in r.iter_mut().enumerate() {
+            for (j, v) in row.iter_mut().enumerate() {
+                *v = (0..4).map(|k| self.m[i][k] * other.m[k][j]).sum();
+            }
+        }
+        Mat4 { m: r }
+    }
+
+    pub fn transform_point(&self, p: Point3d) -> Point3d {
+        let m = &self.m;
+        let x = m[0][0] * p.0 + m[0][1] * p.1 + m[0][2] * p.2 + m[0][3];
+        let y = m[1][0] * p.0 + m[1][1] * p.1 + m[1][2] * p.2 + m[1][3];
+        let z = m[2][0] * p.0 + m[2][1] * p.1 + m[2][2] * p.2 + m[2][3];
+        let w = m[3][0] * p.0 + m[3][1] * p.1 + m[3][2] * p.2 + m[3][3];
+        if w != 1.0 && w.abs() > 1e-12 {
+            (x / w, y / w, z / w)
+        } else {
+            (x, y, z)
+        }
+    }
+
+    // Direction : pas de translation ni de normalisation
+    pub fn transform_vec3(&self, v: Vec3) -> Vec3 {
+        self.to_mat3().mul_vec3(v)
+    }
+
+    pub fn to_mat3(self) -> Mat3 {
+        let m = &self.m;
+        Mat3 {
+            m: [
+                [m[0][0], m[0][1], m[0][2]],
+                [m[1][0], m[1][1], m[1][2]],
+                [m[2][0], m[2][1], m[2][2]],
+            ],
+        }
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        let mut r = [[0.0; 4]; 4];
+        for (i, row) in r.iter_mut().enumerate() {
+            for (j, v) in row.iter_mut().enumerate() {
+                *v = self.m[j][i];
+            }
+        }
+        Mat4 { m: r }
+    }
+
+    // Inverse générale (élimination de Gauss-Jordan avec pivot partiel)
+    pub fn inverse(&self) -> Option<Mat4> {
+        let mut a = self.m;
+        let mut inv = Mat4::IDENTITY.m;
+
+        for col in 0..4 {
+            let pivot = (col..4)
+                .max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))
+                .unwrap_or(col);
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let d = 1.0 / a[col][col];
+            for k in 0..4 {
+                a[col][k] *= d;
+                inv[col][k] *= d;
+            }
+            for row in 0..4 {
+                if row != col {
+                    let f = a[row][col];
+                    for k in 0..4 {
+                        a[row][k] -= f * a[col][k];
+                        inv[row][k] -= f * inv[col][k];
+                    }
+                }
+            }
+        }
+        Some(Mat4 { m: inv })
+    }
+
+    // Matrice des normales : inverse transposée de la partie 3x3.
+    // Indispensable dès que l'échelle n'est pas uniforme.
+    pub fn normal_matrix(&self) -> Mat3 {
+        let m3 = self.to_mat3();
+        m3.inverse().map(|i| i.transpose()).unwrap_or(m3)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    // Champ de vision vertical en radians
+    Perspective { fov_y: f32 },
+    // Hauteur (unités monde) visible à l'écran
+    Orthographic { height: f32 },
+}
+
+// Caméra partagée par les rasteriseurs et le raytracer : même repère, même
+// projection, donc un pixel (x, y) correspond au même point de la scène.
+#[derive(Debug, Clone, Copy)]
+pub struct Camera {
+    pub eye: Point3d,
+    pub target: Point3d,
+    pub up: Vec3,
+    pub projection: Projection,
+    pub near: f32,
+    pub far: f32,
+}
+
+#[allow(dead_code)]
+impl Camera {
+    pub fn perspective(eye: Point3d, target: Point3d, fov_y: f32) -> Camera {
+        Camera {
+            eye,
+            target,
+            up: Vec3::new(0.0, 1.0, 0.0),
+            projection: Projection::Perspective { fov_y },
+            near: 0.1,
+            far: f32::INFINITY,
+        }
+    }
+
+    // Focale exprimée en pixels pour une image de `height` pixels de haut
+    pub fn with_focal(eye: Point3d, target: Point3d, focal: f32, height: f32) -> Camera {
+        Camera::perspective(eye, target, 2.0 * (height * 0.5 / focal).atan())
+    }
+
+    pub fn orthographic(eye: Point3d, target: Point3d, height: f32) -> Camera {
+        Camera {
+            projection: Projection::Orthographic { height },
+            ..Camera::perspective(eye, target, 0.0)
+        }
+    }
+
+    // Repère caméra (right, up, forward)
+    pub fn basis(&self) -> (Vec3, Vec3, Vec3) {
+        let f = Vec3::from(self.target).sub(Vec3::from(self.eye)).normalize();
+        let mut r = f.cross(self.up).normalize();
+        if r.length() == 0.0 {
+            // Vue alignée sur le vecteur up : on prend un autre axe pour ne pas dégénérer
+            r = f.cross(Vec3::new(0.0, 0.0, 1.0)).normalize();
+        }
+        let u = r.cross(f);
+        (r, u, f)
+    }
+
+    // Nombre de pixels par unité monde (ortho), ou focale en pixels (perspective)
+    pub fn pixel_scale(&self, height: f32) -> f32 {
+        match self.projection {
+            Projection::Perspective { fov_y } => height * 0.5 / (fov_y * 0.5).tan(),
+            Projection::Orthographic { height: h } => height / h,
+        }
+    }
+
+    // Point monde -> (x, y) écran en sous-pixels et profondeur (plus grand = plus proche).
+    // None hors des plans near/far.
+    pub fn project(&self, p: Point3d, width: f32, height: f32) -> Option<(f32, f32, f32)> {
+        let v = self.view_space(p);
+        if v.z < self.near || v.z > self.far {
+            return None;
+        }
+
+        let (x, y, depth) = self.project_view(v, width, height);
+
+        // Rejet des points délirants avant toute conversion entière
+        let limit = 10000.0;
+        if (x - width / 2.0).abs() > limit || (y - height / 2.0).abs() > limit {
+            return None;
+        }
+
+        Some((x, y, depth))
+    }
+
+    // Point monde -> repère de la caméra : (droite, haut, distance le long de l'axe)
+    pub fn view_space(&self, p: Point3d) -> Vec3 {
+        let (r, u, f) = self.basis();
+        let v = Vec3::from(p).sub(Vec3::from(self.eye));
+        Vec3::new(v.dot(r), v.dot(u), v.dot(f))
+    }
+
+    // Point du repère de la caméra -> écran et profondeur, sans test : v doit être devant le plan near
+    pub fn project_view(&self, v: Vec3, width: f32, height: f32) -> (f32, f32, f32) {
+        let scale = self.pixel_scale(height);
+        let (px, py, depth) = match self.projection {
+            // 1/z s'interpole linéairement à l'écran
+            Projection::Perspective { .. } => (v.x * scale / v.z, v.y * scale / v.z, 1.0 / v.z),
+            Projection::Orthographic { .. } => (v.x * scale, v.y * scale, -v.z),
+        };
+        (px + width / 2.0, height / 2.0 - py, depth)
+    }
+
+    // Rayon primaire passant par le point écran (x, y) (x + 0.5 pour le centre d'un pixel)
+    pub fn primary_ray(&self, x: f32, y: f32, width: f32, height: f32) -> (Vec3, Vec3) {
+        let (r, u, f) = self.basis();
+        let scale = self.pixel_scale(height);
+        let sx = (x - width / 2.0) / scale;
+        let sy = (height / 2.0 - y) / scale;
+        match self.projection {
+            Projection::Perspective { .. } => {
+                (Vec3::from(self.eye), f.add(r.mul(sx)).add(u.mul(sy)).normalize())
+            }
+            Projection::Orthographic { .. } => {
+                (Vec3::from(self.eye).add(r.mul(sx)).add(u.mul(sy)), f)
+            }
+        }
+    }
+
+    // Direction d'un point vers la caméra (non normalisée)
+    pub fn view_dir(&self, p: Vec3) -> Vec3 {
+        match self.projection {
+            Projection::Perspective { .. } => Vec3::from(self.eye).sub(p),
+            Projection::Orthographic { .. } => self.basis().2.neg(),
+        }
+    }
+}
+
 #[allow(dead_code)]
 // Dans ce repère (souvent appelé View Space ou Camera Space) :
 //     L'œil (l'origine) : Il est toujours à (0,0,0).
@@ -397,14 +1057,49 @@ impl Transform {
 //     Puis une projection
 pub mod utils {
 
-    use std::usize;
-
-    use wavefront::Obj;
-
     use crate::frame_buffer::{self, FrameBuffer};
+    use crate::light::{Incident, Light};
     use crate::math_3d::{self, Point3d, Vec3, Vec3WithColor};
 
-    use super::{Color, Material};
+    use super::{Camera, Color, Mat4, Material, Projection};
+    use crate::mesh::Mesh;
+    use crate::scene::Scene;
+    use crate::shadow_map::ShadowMaps;
+    use crate::texture::Texture;
+
+    // Sommet projeté d'un triangle texturé (Gouraud) : l'éclairage est séparé en
+    // une partie modulée par la texture (ambiant + diffus) et le spéculaire
+    #[derive(Copy, Clone)]
+    pub struct TexturedVertex {
+        pub x: f32, // Coordonnée écran
+        pub y: f32, // Coordonnée écran
+        pub z: f32, // Profondeur pour le Z-Buffer
+        pub uv: (f32, f32),
+        pub diffuse: (f32, f32, f32),
+        pub specular: (f32, f32, f32),
+    }
+
+    // Poids barycentriques corrigés de la perspective pour les attributs (uv) :
+    // on interpole u/z, v/z et 1/z à l'écran. En perspective la profondeur vaut déjà 1/z_cam,
+    // en orthographique l'interpolation affine est exacte.
+    fn perspective_weights(camera: &Camera, w: [f32; 3], depth: [f32; 3]) -> [f32; 3] {
+        if !matches!(camera.projection, Projection::Perspective { .. }) {
+            return w;
+        }
+        let (a, b, c) = (w[0] * depth[0], w[1] * depth[1], w[2] * depth[2]);
+        let sum = a + b + c;
+        if sum.abs() < f32::EPSILON {
+            return w;
+        }
+        [a / sum, b / sum, c / sum]
+    }
+
+    fn interpolate_uv(uv: [(f32, f32); 3], w: [f32; 3]) -> (f32, f32) {
+        (
+            uv[0].0 * w[0] + uv[1].0 * w[1] + uv[2].0 * w[2],
+            uv[0].1 * w[0] + uv[1].1 * w[1] + uv[2].1 * w[2],
+        )
+    }
 
     pub fn project_look_at(
         p: Point3d,
@@ -414,56 +1109,12 @@ pub mod utils {
         width: f32,
         height: f32,
     ) -> Option<(i32, i32, f32)> {
-        // Ajout du f32 pour le Z-buffer
-        // 1. Calcul des axes du repère
-        let world_up = Vec3 {
-            x: 0.0,
-            y: 1.0,
-            z: 0.0,
-        };
-
-        let eye = Vec3::from(eye);
-        let target = Vec3::from(target);
-        let f = (target.sub(eye)).normalize();
-        let r = f.cross(world_up).normalize();
-        let u = r.cross(f);
-
-        // 2. Passage au repère caméra
-        let p = Vec3::from(p);
-        let v = p.sub(eye);
-        let x_cam = v.dot(r);
-        let y_cam = v.dot(u);
-        let z_cam = v.dot(f);
-
-        // 3. Clipping Z (Near Plane)
-        // Sécurité absolue contre la division par zéro et les points derrière la caméra
-        if z_cam <= 0.1 {
-            return None;
-        }
-
-        // 4. Projection
-        let px = (x_cam * focal) / z_cam;
-        let py = (y_cam * focal) / z_cam;
-
-        // --- SÉCURITÉ ANTI-CRASH (Clipping 2D) ---
-        // Si px ou py sont délirants (ex: 35000), on rejette le point avant le cast en i32.
-        // Cela évite l'overflow dans les calculs de lignes/surfaces de la bibliothèque.
-        let limit = 10000.0;
-        if px.abs() > limit || py.abs() > limit {
-            return None;
-        }
-
-        // Centrage sur le FrameBuffer
-        let screen_x = (px + width / 2.0) as i32;
-        let screen_y = (height / 2.0 - py) as i32;
-
-        // --- LE Z POUR LE BUFFER ---
-        // On renvoie 1.0 / z_cam.
-        // Valeur la plus grande est plus proche
-        // Ainsi : z_cam = 0.1 (proche) -> 10.0 (grand Z)
-        //         z_cam = 100.0 (loin) -> 0.01 (petit Z)
-        // Cela correspond parfaitement à ton test "if new_z > z_buffer".
-        Some((screen_x, screen_y, 1.0 / z_cam)) // profondeur w
+        // Le Z renvoyé vaut 1.0 / z_cam : la valeur la plus grande est la plus proche,
+        // ce qui correspond au test "if new_z > z_buffer".
+        let camera = Camera::with_focal(eye, target, focal, height);
+        camera
+            .project(p, width, height)
+            .map(|(x, y, z)| (x as i32, y as i32, z))
     }
 
     pub fn is_point_in_polygon(poly: &[(f32, f32)], t: (f32, f32)) -> bool {
@@ -501,36 +1152,63 @@ pub mod utils {
         v1.cross(v2).normalize()
     }
 
-    pub fn compute_vertex_normals(points: &[(f64, f64, f64)], faces: &[&[usize]]) -> Vec<Vec3> {
-        let mut normals = vec![
-            Vec3 {
-                x: 0.0,
-                y: 0.0,
-                z: 0.0
-            };
-            points.len()
-        ];
-
-        for f in faces {
-            if f.len() < 3 {
-                continue;
+    // Normales lissées, une par coin de face (faces = indices des positions).
+    // Chaque coin reçoit la moyenne des normales des faces qui partagent le sommet,
+    // pondérées par aire x angle au sommet, en ne gardant que celles dont l'écart
+    // avec la face courante est inférieur à crease_angle (radians).
+    pub fn compute_smooth_normals(positions: &[Vec3], faces: &[Vec<usize>], crease_angle: f32) -> Vec<Vec<Vec3>> {
+        let cos_crease = crease_angle.cos();
+
+        // Normale (méthode de Newell, valable pour les polygones) et aire de chaque face
+        let face_normals: Vec<(Vec3, f32)> = faces.iter().map(|f| {
+            let mut n = Vec3::new(0.0, 0.0, 0.0);
+            for (i, &a) in f.iter().enumerate() {
+                let (a, b) = (positions[a], positions[f[(i + 1) % f.len()]]);
+                n = n.add(Vec3::new(
+                    (a.y - b.y) * (a.z + b.z),
+                    (a.z - b.z) * (a.x + b.x),
+                    (a.x - b.x) * (a.y + b.y),
+                ));
             }
+            (n.normalize(), n.length() * 0.5)
+        }).collect();
 
-            // Calcul de la normal de la face à partir des 3 premiers points
-            let p0 = Vec3::new_from_point3d(math_3d::ToPoint3d::to_p3d(points[f[0]]));
-            let p1 = Vec3::new_from_point3d(math_3d::ToPoint3d::to_p3d(points[f[1]]));
-            let p2 = Vec3::new_from_point3d(math_3d::ToPoint3d::to_p3d(points[f[2]]));
-            // let face_normal = p1.sub(p0).cross(p2.sub(p0));
-            let face_normal = p2.sub(p0).cross(p1.sub(p0));
-
-            // Distribue la normal à tous les points sommets de la face
-            for vertex_idx in *f {
-                let i: usize = *vertex_idx;
-                normals[i] = face_normal;
+        // Angle de chaque coin
+        let corner_angles: Vec<Vec<f32>> = faces.iter().map(|f| {
+            (0..f.len()).map(|i| {
+                let p = positions[f[i]];
+                let prev = positions[f[(i + f.len() - 1) % f.len()]].sub(p).normalize();
+                let next = positions[f[(i + 1) % f.len()]].sub(p).normalize();
+                prev.dot(next).clamp(-1.0, 1.0).acos()
+            }).collect()
+        }).collect();
+
+        // Coins (face, coin) incidents à chaque position
+        let mut incident: Vec<Vec<(usize, usize)>> = vec![Vec::new(); positions.len()];
+        for (fi, f) in faces.iter().enumerate() {
+            for (ci, &p) in f.iter().enumerate() {
+                incident[p].push((fi, ci));
             }
         }
 
-        normals
+        faces.iter().enumerate().map(|(fi, f)| {
+            let (n_face, _) = face_normals[fi];
+            f.iter().map(|&p| {
+                let mut sum = Vec3::new(0.0, 0.0, 0.0);
+                for &(gi, ci) in &incident[p] {
+                    let (n, area) = face_normals[gi];
+                    if n.dot(n_face) >= cos_crease {
+                        sum = sum.add(n.mul(area * corner_angles[gi][ci]));
+                    }
+                }
+                // Face dégénérée : pas de direction exploitable
+                match (sum.length() > 0.0, n_face.length() > 0.0) {
+                    (true, _) => sum.normalize(),
+                    (false, true) => n_face,
+                    (false, false) => Vec3::new(0.0, 1.0, 0.0),
+                }
+            }).collect()
+        }).collect()
     }
 
 
@@ -585,16 +1263,88 @@ pub mod utils {
         }
     }
 
+    // Comme draw_triangle_shaded, mais la couleur vient de la texture à chaque pixel :
+    // couleur = texel * diffus + spéculaire, avec des uv corrigés de la perspective
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_triangle_textured(
+        p: [TexturedVertex; 3],
+        texture: &Texture,
+        camera: &Camera,
+        fb: &mut frame_buffer::FrameBuffer,
+        z_buffer: &mut [f32],
+        width: i32,
+        height: i32,
+    ) {
+        let min_x = p.iter().map(|v| v.x).fold(f32::INFINITY, f32::min).round() as i32;
+        let max_x = p.iter().map(|v| v.x).fold(f32::NEG_INFINITY, f32::max).round() as i32;
+        let min_y = p.iter().map(|v| v.y).fold(f32::INFINITY, f32::min).round() as i32;
+        let max_y = p.iter().map(|v| v.y).fold(f32::NEG_INFINITY, f32::max).round() as i32;
 
+        let start_x = min_x.clamp(0, width - 1);
+        let end_x = max_x.clamp(0, width - 1);
+        let start_y = min_y.clamp(0, height - 1);
+        let end_y = max_y.clamp(0, height - 1);
+
+        let den = (p[1].y - p[2].y) * (p[0].x - p[2].x) + (p[2].x - p[1].x) * (p[0].y - p[2].y);
+        if den.abs() < 1e-6 { return; }
+
+        let depth = [p[0].z, p[1].z, p[2].z];
+        let uv = [p[0].uv, p[1].uv, p[2].uv];
+
+        let epsilon = -0.0001; // Marge pour boucher les trous entre faces
+        for y in start_y..=end_y {
+            for x in start_x..=end_x {
+                let fx = x as f32 + 0.5;
+                let fy = y as f32 + 0.5;
+
+                let w0 = ((p[1].y - p[2].y) * (fx - p[2].x) + (p[2].x - p[1].x) * (fy - p[2].y)) / den;
+                let w1 = ((p[2].y - p[0].y) * (fx - p[2].x) + (p[0].x - p[2].x) * (fy - p[2].y)) / den;
+                let w2 = 1.0 - w0 - w1;
+
+                if w0 >= epsilon && w1 >= epsilon && w2 >= epsilon {
+                    let z = p[0].z * w0 + p[1].z * w1 + p[2].z * w2;
+                    let offset = (y * width + x) as usize;
+
+                    if z >= z_buffer[offset] {
+                        // Les couleurs restent interpolées à l'écran (Gouraud), seules les uv sont corrigées
+                        let (u, v) = interpolate_uv(uv, perspective_weights(camera, [w0, w1, w2], depth));
+                        let texel = texture.sample(u, v);
+                        let lerp = |a: f32, b: f32, c: f32| a * w0 + b * w1 + c * w2;
+                        let diffuse = (
+                            lerp(p[0].diffuse.0, p[1].diffuse.0, p[2].diffuse.0),
+                            lerp(p[0].diffuse.1, p[1].diffuse.1, p[2].diffuse.1),
+                            lerp(p[0].diffuse.2, p[1].diffuse.2, p[2].diffuse.2),
+                        );
+                        let specular = (
+                            lerp(p[0].specular.0, p[1].specular.0, p[2].specular.0),
+                            lerp(p[0].specular.1, p[1].specular.1, p[2].specular.1),
+                            lerp(p[0].specular.2, p[1].specular.2, p[2].specular.2),
+                        );
+
+                        z_buffer[offset] = z;
+                        fb.pixel(x as u32, y as u32, intensity_to_color((
+                            texel.0 * diffuse.0 + specular.0,
+                            texel.1 * diffuse.1 + specular.1,
+                            texel.2 * diffuse.2 + specular.2,
+                        )));
+                    }
+                }
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn draw_phong_triangle(
         fb: &mut FrameBuffer,
-        z_buffer: &mut Vec<f32>,
+        z_buffer: &mut [f32],
         vertices: [Point3d; 3],       // Points projetés (Ecran)
         world_pos: [Point3d; 3],      // Points réels (Monde)
         normals: [Vec3; 3],           // Normales aux sommets
         material: &Material,
-        light_dir: Vec3,
-        eye: Point3d,
+        texture: Option<(&Texture, [(f32, f32); 3])>, // map_Kd et uv des sommets
+        lights: &[Light],
+        shadows: &ShadowMaps,
+        camera: &Camera,
         width: i32,
         height: i32
     ) {
@@ -639,6 +1389,10 @@ pub mod utils {
 
                         z_buffer[offset] = z; // N'oublie pas de mettre à jour le buffer !
 
+                        // Poids corrigés de la perspective : les attributs monde ne sont pas linéaires à l'écran
+                        let depth = [vertices[0].2, vertices[1].2, vertices[2].2];
+                        let [w0, w1, w2] = perspective_weights(camera, [w0, w1, w2], depth);
+
                         // d. Interpolation de la NORMALE (N)
                         let nx = normals[0].x  * w0 + normals[1].x * w1 + normals[2].x * w2;
                         let ny = normals[0].y * w0 + normals[1].y * w1 + normals[2].y * w2;
@@ -653,11 +1407,22 @@ pub mod utils {
 
                         // f. SHADING par pixel
                         // V = direction vers la caméra
-                        let eye_vec: Vec3  = Vec3::from(eye);
                         let interpolated_p_vec = Vec3::from(interpolated_p);
-                        let v = eye_vec.sub(interpolated_p_vec).normalize();
+                        let v = camera.view_dir(interpolated_p_vec).normalize();
                         
-                        let intensity = calculate_intensity(interpolated_n, light_dir, v, material);
+                        // g. Texture : uv corrigées de la perspective
+                        let material = match texture {
+                            Some((texture, uv)) => {
+                                let (u, v) = interpolate_uv(uv, [w0, w1, w2]);
+                                material.with_texel(texture.sample(u, v))
+                            }
+                            None => *material,
+                        };
+
+                        // h. Ombres : carte de profondeur de chaque source (PCF)
+                        let visibility = |i, _: &Light, incident: &Incident| shadows.visibility(i, interpolated_p_vec, interpolated_n, incident);
+                        let (diffuse, specular) = calculate_intensity_split_with(interpolated_n, interpolated_p_vec, v, &material, lights, visibility);
+                        let intensity = (diffuse.0 + specular.0, diffuse.1 + specular.1, diffuse.2 + specular.2);
                         let rgb = intensity_to_color(intensity);
                         fb.pixel(x as u32, y as u32, rgb);
                     }
@@ -695,23 +1460,60 @@ pub mod utils {
     // }
 
 
-    pub fn calculate_intensity(n: Vec3, light_dir: Vec3, v: Vec3, m: &Material) -> (f32, f32, f32) {
+    pub fn calculate_intensity(n: Vec3, p: Vec3, v: Vec3, m: &Material, lights: &[Light]) -> (f32, f32, f32) {
+        let (diffuse, specular) = calculate_intensity_split(n, p, v, m, lights);
+        (diffuse.0 + specular.0, diffuse.1 + specular.1, diffuse.2 + specular.2)
+    }
+
+    // (ambiant + diffus, spéculaire) : seule la première partie est modulée par une texture.
+    // p : point éclairé (monde), chaque source ajoute sa contribution
+    pub fn calculate_intensity_split(n: Vec3, p: Vec3, v: Vec3, m: &Material, lights: &[Light]) -> ((f32, f32, f32), (f32, f32, f32)) {
+        calculate_intensity_split_with(n, p, v, m, lights, |_, _, _| 1.0)
+    }
+
+    // Idem, la contribution de chaque source étant multipliée par visibility(index, source, incident) (0.0 : dans l'ombre)
+    pub fn calculate_intensity_split_with<F>(n: Vec3, p: Vec3, v: Vec3, m: &Material, lights: &[Light], visibility: F) -> ((f32, f32, f32), (f32, f32, f32))
+    where
+        F: Fn(usize, &Light, &Incident) -> f32,
+    {
+        let (mut diffuse, mut specular) = ((0.0, 0.0, 0.0), (0.0, 0.0, 0.0));
+        for (i, light) in lights.iter().enumerate() {
+            if let Some(incident) = light.incident(p) {
+                let visible = visibility(i, light, &incident);
+                if visible <= 0.0 {
+                    continue;
+                }
+                let (d, s) = light_contribution(n, incident.direction, v, m);
+                let c = incident.radiance;
+                let c = (c.0 * visible, c.1 * visible, c.2 * visible);
+                diffuse = (diffuse.0 + d.0 * c.0, diffuse.1 + d.1 * c.1, diffuse.2 + d.2 * c.2);
+                specular = (specular.0 + s.0 * c.0, specular.1 + s.1 * c.1, specular.2 + s.2 * c.2);
+            }
+        }
+
+        // AMBIANT : La couleur dans l'ombre, une seule fois quel que soit le nombre de sources
+        let (amb_r, amb_g, amb_b) = m.ka;
+        (
+            (amb_r + diffuse.0, amb_g + diffuse.1, amb_b + diffuse.2),
+            specular
+        )
+    }
+
+    // (diffus, spéculaire) d'une source blanche d'intensité 1 dans la direction light_dir
+    pub fn light_contribution(n: Vec3, light_dir: Vec3, v: Vec3, m: &Material) -> ((f32, f32, f32), (f32, f32, f32)) {
         // 1. S'assurer que les vecteurs d'entrée sont normalisés
         let n = n.normalize();
         let l = light_dir.normalize(); // Vers la source lumineuse
         let v = v.normalize();         // Vers la caméra
 
-        // 2. AMBIANT : La couleur dans l'ombre
-        let (amb_r, amb_g, amb_b) = m.ka;
-
-        // 3. DIFFUS (Lambert) : La couleur de base sous la lumière
+        // 2. DIFFUS (Lambert) : La couleur de base sous la lumière
         // On utilise max(0.0) pour ne pas éclairer l'arrière de l'objet
         let dot_diffuse = n.dot(l).max(0.0);
         let diff_r = m.kd.0 * dot_diffuse;
         let diff_g = m.kd.1 * dot_diffuse;
         let diff_b = m.kd.2 * dot_diffuse;
 
-        // 4. SPÉCULAIRE (Blinn-Phong) : Le reflet brillant
+        // 3. SPÉCULAIRE (Blinn-Phong) : Le reflet brillant
         // Le vecteur H est la bissectrice entre la lumière et la vue
         let h = l.add(v).normalize();
         let dot_spec = n.dot(h).max(0.0);
@@ -723,13 +1525,8 @@ pub mod utils {
         let spec_g = m.ks.1 * spec_intensity;
         let spec_b = m.ks.2 * spec_intensity;
 
-        // 5. TOTAL : On additionne tout
         // Note : On ne multiplie PAS le spéculaire par le diffuse, on l'AJOUTE par-dessus
-        (
-            amb_r + diff_r + spec_r,
-            amb_g + diff_g + spec_g,
-            amb_b + diff_b + spec_b
-        )
+        ((diff_r, diff_g, diff_b), (spec_r, spec_g, spec_b))
     }
     
 
@@ -742,61 +1539,35 @@ pub mod utils {
     }
 
     
-    pub fn draw_object_model_gouraud(model: &wavefront::Object,
-                             transforms: &Vec<&math_3d::Transform>,
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_mesh_gouraud(mesh: &Mesh,
+                             model_matrix: &Mat4,
                              material: &Material,
-                             eye: Point3d,
-                             target: Point3d,
-                             light_dir: Vec3,
-                             focal: f32,
+                             camera: &Camera,
+                             lights: &[Light],
+                             shadows: &ShadowMaps,
                              width: u32, height: u32,
                              fb: &mut FrameBuffer,
-                             z_buffer: &mut Vec<f32>) {
+                             z_buffer: &mut [f32]) {
 
-        let eye_vec = Vec3::new_from_point3d(eye);
-        
-        for f in model.triangles() {
-            let fsz: usize = f.len();
+        let normal_matrix = model_matrix.normal_matrix();
 
-            if fsz < 3 {
-                return;
-            }
+        for tri in mesh.triangles() {
+            // Matériau de la face (usemtl), sinon celui passé en paramètre
+            let material = mesh.material(tri.material, material);
+            let fsz: usize = tri.positions.len();
 
             let mut world_points: Vec<Point3d> = Vec::with_capacity(fsz);
             let mut world_normals: Vec<Vec3> = Vec::with_capacity(fsz);
+            // Texture seulement si tous les sommets ont des coordonnées de texture
+            let texture = mesh.texture(tri.material).zip(tri.uvs);
 
-            for v in f {
-                let p: Point3d = (v.position()[0], v.position()[1], v.position()[2]);
-
+            for (p, vn) in tri.positions.iter().zip(tri.normals.iter()) {
                 // == Gestion des transformations points ==
-                if transforms.len() > 0 { 
-                    let mut pt = math_3d::Transform::transform_point(p, &transforms[0]);
-                    for t in transforms.iter().skip(1) {
-                        pt = math_3d::Transform::transform_point(pt, t);
-                    }
-                    world_points.push(pt);
-                } else {
-                    world_points.push(p);
-                }
+                world_points.push(model_matrix.transform_point(p.to_point3d()));
 
                 // == Gestion des transformations vecteurs normales ==
-                let n = if let Some(n_exists) = v.normal() {
-                    n_exists
-                } else {
-                    // Todo calculer la normale
-                    [0.0, 1.0, 0.0]
-                };
-
-                if transforms.len() > 0 {
-                    let mut vn = Vec3 { x: n[0], y: n[1], z: n[2] };
-                    vn = math_3d::Transform::transform_vec3(vn, transforms[0]);
-                    for t in transforms.iter().skip(1) {
-                        vn = math_3d::Transform::transform_vec3(vn, t);
-                    }
-                    world_normals.push(vn);
-                } else {
-                    world_normals.push(Vec3 { x: n[0], y: n[1], z: n[2] });
-                }
+                world_normals.push(normal_matrix.mul_vec3(*vn).normalize());
             }
 
             // == Back-face culling ==
@@ -807,7 +1578,7 @@ pub mod utils {
             let vec_p0 = Vec3::new_from_point3d(p0);
             // Vecteur allant du triangle vers la caméra
             // 2 points A et B dans l'espace, le vecteur AB s'obtient par AB = B - A
-            let view_dir = eye_vec.sub(vec_p0);
+            let view_dir = camera.view_dir(vec_p0);
             // Si le produit scalaire est négatif, la face regarde ailleurs
             // Angle θ < 90° (positif)  ou Angle θ > 90° (négatif) entre normale et vue 
             if normal.dot(view_dir) <= 0.0 {
@@ -830,36 +1601,52 @@ pub mod utils {
             // Color=(Ka×Ambiant)+(Kd×Diffus)+(Ks×Speculaire)
             // 1. Définir les propriétés du matériau (à extraire de ton .mtl)
 
+            // Face texturée : la couleur est calculée par pixel à partir de la texture
+            if let Some((texture, uvs)) = &texture {
+                let mut projected: Vec<TexturedVertex> = Vec::with_capacity(fsz);
+                for ((p, n), &uv) in world_points.iter().zip(world_normals.iter()).zip(uvs.iter()) {
+                    let v = camera.view_dir(Vec3::new_from_point3d(*p)).normalize();
+                    let p_vec = Vec3::new_from_point3d(*p);
+                    let visibility = |i, _: &Light, incident: &Incident| shadows.visibility(i, p_vec, *n, incident);
+                    let (diffuse, specular) = calculate_intensity_split_with(*n, p_vec, v, &material, lights, visibility);
+                    let Some((x, y, z)) = camera.project(*p, width as f32, height as f32) else { break };
+                    projected.push(TexturedVertex { x, y, z, uv, diffuse, specular });
+                }
+                if projected.len() == fsz {
+                    for i in 1..fsz - 1 {
+                        let triangle = [projected[0], projected[i], projected[i + 1]];
+                        draw_triangle_textured(triangle, texture, camera, fb, z_buffer, width as i32, height as i32);
+                    }
+                }
+                continue;
+            }
+
             // let m = math_3d::Material::gold();
             let mut vertex_colors: Vec<Color> = Vec::with_capacity(world_normals.len());
             // On utilise .zip() pour avoir le point ET la normale correspondante
             for (p_world, n_world) in world_points.iter().zip(world_normals.iter()) {
                 
                 // 1. Vecteurs de base
-                let l = light_dir; 
-                let v = eye_vec.sub(Vec3::new_from_point3d(*p_world)).normalize();
+                let v = camera.view_dir(Vec3::new_from_point3d(*p_world)).normalize();
                 let n = n_world; // Garde n_world.neg() si l'objet est noir
                 // let n = n_world.neg();  // parfois la normale a besoin d'être inversée
 
-                let rgb = calculate_intensity(*n, l, v, material);
+                // Ombres évaluées aux sommets, comme l'éclairage
+                let p = Vec3::new_from_point3d(*p_world);
+                let visibility = |i, _: &Light, incident: &Incident| shadows.visibility(i, p, *n, incident);
+                let (diffuse, specular) = calculate_intensity_split_with(*n, p, v, &material, lights, visibility);
+                let rgb = (diffuse.0 + specular.0, diffuse.1 + specular.1, diffuse.2 + specular.2);
                 let color = intensity_to_color(rgb);
 
                 vertex_colors.push(color);
             }
 
             // Projection des sommets et des couleurs
-            let mut projected_vertices: Vec<Vec3WithColor> = Vec::with_capacity(f.len());
+            let mut projected_vertices: Vec<Vec3WithColor> = Vec::with_capacity(fsz);
             let mut all_visible = true;
             for (p, &color) in world_points.iter().zip(vertex_colors.iter()) {
-                if let Some((sx, sy, sz)) = math_3d::utils::project_look_at(
-                    *p,
-                    eye,
-                    target,
-                    focal,
-                    width as f32,
-                    height as f32,
-                ) {
-                    projected_vertices.push(Vec3WithColor { x: sx as f32, y: sy as f32, z: sz, c: color });
+                if let Some((sx, sy, sz)) = camera.project(*p, width as f32, height as f32) {
+                    projected_vertices.push(Vec3WithColor { x: sx, y: sy, z: sz, c: color });
                 } else {
                     all_visible = false;
                     break;
@@ -888,89 +1675,80 @@ pub mod utils {
     
 
 
-    pub fn draw_obj_model_gouraud(model: &Obj,
-                          transforms: &Vec<&math_3d::Transform>,
-                          material: &Material,
-                          eye: Point3d,
-                          target: Point3d,
-                          light_dir: Vec3,
-                          focal: f32,
-                          width: u32, height: u32,
-                          fb: &mut FrameBuffer,
-                          z_buffer: &mut Vec<f32>) {
-        for o in model.objects() {
-            let object = o.1;
-            draw_object_model_gouraud(&object, transforms, material, eye, target, light_dir, focal, width, height, fb, z_buffer);
-        }
-    }
 
 
 
 
-    pub fn draw_object_model_phong(model: &wavefront::Object,
-                                   transforms: &Vec<&math_3d::Transform>,
+    // Toutes les instances de la scène partagent le z-buffer.
+    // root : transformation appliquée à toute la scène, material : matériau des noeuds qui n'en ont pas
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_scene_gouraud(scene: &Scene,
+                              root: &Mat4,
+                              material: &Material,
+                              camera: &Camera,
+                              lights: &[Light],
+                              shadows: &ShadowMaps,
+                              width: u32, height: u32,
+                              fb: &mut FrameBuffer,
+                              z_buffer: &mut [f32]) {
+        for instance in scene.instances(root) {
+            let material = instance.material.map_or(*material, |m| m.material);
+            draw_mesh_gouraud(instance.mesh, &instance.model_matrix, &material, camera, lights, shadows, width, height, fb, z_buffer);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_scene_phong(scene: &Scene,
+                            root: &Mat4,
+                            material: &Material,
+                            camera: &Camera,
+                            lights: &[Light],
+                            shadows: &ShadowMaps,
+                            width: u32, height: u32,
+                            fb: &mut FrameBuffer,
+                            z_buffer: &mut [f32]) {
+        for instance in scene.instances(root) {
+            let material = instance.material.map_or(*material, |m| m.material);
+            draw_mesh_phong(instance.mesh, &instance.model_matrix, &material, camera, lights, shadows, width, height, fb, z_buffer);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_mesh_phong(mesh: &Mesh,
+                                   model_matrix: &Mat4,
                                    material: &Material,
-                                   eye: Point3d,
-                                   target: Point3d,
-                                   light_dir: Vec3,
-                                   focal: f32,
+                                   camera: &Camera,
+                                   lights: &[Light],
+                                   shadows: &ShadowMaps,
                                    width: u32, height: u32,
                                    fb: &mut FrameBuffer,
-                                   z_buffer: &mut Vec<f32>) {
+                                   z_buffer: &mut [f32]) {
 
-        let eye_vec = Vec3::new_from_point3d(eye);
-        
-        for f in model.triangles() {
-            let fsz: usize = f.len();
+        let normal_matrix = model_matrix.normal_matrix();
 
-            if fsz < 3 {
-                return;
-            }
+        for tri in mesh.triangles() {
+            // Matériau de la face (usemtl), sinon celui passé en paramètre
+            let material = mesh.material(tri.material, material);
+            let fsz: usize = tri.positions.len();
 
             let mut world_points: Vec<Point3d> = Vec::with_capacity(fsz);
             let mut world_normals: Vec<Vec3> = Vec::with_capacity(fsz);
             let mut screen_points: Vec<Point3d> = Vec::with_capacity(fsz);
+            // Texture seulement si tous les sommets ont des coordonnées de texture
+            let texture = mesh.texture(tri.material).zip(tri.uvs);
             let mut all_visible = true;
-            for v in f {
-                let p: Point3d = (v.position()[0], v.position()[1], v.position()[2]);
-
+            for (p, vn) in tri.positions.iter().zip(tri.normals.iter()) {
                 // == Gestion des transformations points ==
-                if transforms.len() > 0 { 
-                    let mut pt = math_3d::Transform::transform_point(p, &transforms[0]);
-                    for t in transforms.iter().skip(1) {
-                        pt = math_3d::Transform::transform_point(pt, t);
-                    }
-                    world_points.push(pt);
-                } else {
-                    world_points.push(p);
-                }
+                world_points.push(model_matrix.transform_point(p.to_point3d()));
 
                 // == Gestion des transformations vecteurs normales ==
-                let n = if let Some(n_exists) = v.normal() {
-                    n_exists
-                } else {
-                    // Todo calculer la normale
-                    [0.0, 1.0, 0.0]
-                };
-
-                if transforms.len() > 0 {
-                    let mut vn = Vec3 { x: n[0], y: n[1], z: n[2] };
-                    vn = math_3d::Transform::transform_vec3(vn, transforms[0]);
-                    for t in transforms.iter().skip(1) {
-                        vn = math_3d::Transform::transform_vec3(vn, t);
-                    }
-                    world_normals.push(vn);
-                } else {
-                    world_normals.push(Vec3 { x: n[0], y: n[1], z: n[2] });
-                }
+                world_normals.push(normal_matrix.mul_vec3(*vn).normalize());
 
 
                 // == Projections ecran ==
                 let p_proj = world_points.last().unwrap();
-                if let Some((sx, sy, sz)) = math_3d::utils::project_look_at(
-                    *p_proj, eye, target, focal, width as f32, height as f32
-                ) {
-                    screen_points.push((sx as f32, sy as f32, sz));
+                if let Some((sx, sy, sz)) = camera.project(*p_proj, width as f32, height as f32) {
+                    screen_points.push((sx, sy, sz));
                 } else {
                     all_visible = false;
                     break;
@@ -986,7 +1764,7 @@ pub mod utils {
             let vec_p0 = Vec3::new_from_point3d(p0);
             // Vecteur allant du triangle vers la caméra
             // 2 points A et B dans l'espace, le vecteur AB s'obtient par AB = B - A
-            let view_dir = eye_vec.sub(vec_p0);
+            let view_dir = camera.view_dir(vec_p0);
             // Si le produit scalaire est négatif, la face regarde ailleurs
             // Angle θ < 90° (positif)  ou Angle θ > 90° (négatif) entre normale et vue 
             if normal.dot(view_dir) <= 0.0 {
@@ -1000,6 +1778,7 @@ pub mod utils {
                 let tri_screen = [screen_points[0], screen_points[i], screen_points[i+1]];
                 let tri_world  = [world_points[0], world_points[i], world_points[i+1]];
                 let tri_normal = [world_normals[0], world_normals[i], world_normals[i+1]];
+                let tri_texture = texture.as_ref().map(|(t, uv)| (*t, [uv[0], uv[i], uv[i+1]]));
 
                 draw_phong_triangle(
                     fb,
@@ -1007,9 +1786,11 @@ pub mod utils {
                     tri_screen,
                     tri_world,
                     tri_normal,
-                    material,
-                    light_dir,
-                    eye,
+                    &material,
+                    tri_texture,
+                    lights,
+                    shadows,
+                    camera,
                     width as i32,
                     height as i32
                 );
@@ -1017,19 +1798,90 @@ pub mod utils {
         }
     }
 
-    pub fn draw_obj_model_phong(model: &Obj,
-                          transforms: &Vec<&math_3d::Transform>,
-                          material: &Material,
-                          eye: Point3d,
-                          target: Point3d,
-                          light_dir: Vec3,
-                          focal: f32,
-                          width: u32, height: u32,
-                          fb: &mut FrameBuffer,
-                          z_buffer: &mut Vec<f32>) {
-        for o in model.objects() {
-            let object = o.1;
-            draw_object_model_phong(&object, transforms, material, eye, target, light_dir, focal, width, height, fb, z_buffer);
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn close(a: Vec3, b: Vec3) -> bool {
+            a.sub(b).length() < 1e-4
+        }
+
+        // Deux triangles partagent le sommet 0 : l'un (+z) d'aire 2, l'autre (+x) d'aire 0.5,
+        // avec des angles au sommet de 90° et 45°
+        #[test]
+        fn smooth_normals_weighted_by_area_and_angle() {
+            let positions = [
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(0.0, 2.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                Vec3::new(0.0, 1.0, 1.0),
+            ];
+            let faces = vec![vec![0, 1, 2], vec![0, 3, 4]];
+            let normals = compute_smooth_normals(&positions, &faces, std::f32::consts::PI);
+
+            let (z, x) = (2.0 * std::f32::consts::FRAC_PI_2, 0.5 * std::f32::consts::FRAC_PI_4);
+            let expected = Vec3::new(x, 0.0, z).normalize();
+            assert!(close(normals[0][0], expected), "{:?}", normals[0][0]);
+            assert!(close(normals[1][0], expected), "{:?}", normals[1][0]);
+            // Les autres coins n'appartiennent qu'à une face
+            assert!(close(normals[0][1], Vec3::new(0.0, 0.0, 1.0)));
+            assert!(close(normals[1][2], Vec3::new(1.0, 0.0, 0.0)));
+
+            // Écart de 90° au-delà du pli : chaque face garde sa normale
+            let normals = compute_smooth_normals(&positions, &faces, 60f32.to_radians());
+            assert!(close(normals[0][0], Vec3::new(0.0, 0.0, 1.0)));
+            assert!(close(normals[1][0], Vec3::new(1.0, 0.0, 0.0)));
+        }
+
+        // Normale géométrique de chaque face du maillage, comparée à celle de ses coins
+        fn corners_match_faces(mesh: &Mesh) -> bool {
+            mesh.faces.iter().all(|face| {
+                let p: Vec<Vec3> = face.vertices.iter().map(|v| mesh.positions[v.position]).collect();
+                let n = p[1].sub(p[0]).cross(p[2].sub(p[0])).normalize();
+                face.vertices.iter().all(|v| close(mesh.normals[v.normal], n))
+            })
+        }
+
+        #[test]
+        fn crease_keeps_cube_faces_flat() {
+            assert!(corners_match_faces(&Mesh::cube(30f32.to_radians())));
+            // Pli plus large que 90° : les coins du cube sont lissés
+            assert!(!corners_match_faces(&Mesh::cube(100f32.to_radians())));
+        }
+
+        #[test]
+        fn sphere_normals_are_radial() {
+            let (rings, segments) = (48, 96);
+            let mut points = vec![(0.0, 1.0, 0.0)];
+            for i in 1..rings {
+                let theta = std::f64::consts::PI * i as f64 / rings as f64;
+                for j in 0..segments {
+                    let phi = 2.0 * std::f64::consts::PI * j as f64 / segments as f64;
+                    points.push((theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin()));
+                }
+            }
+            points.push((0.0, -1.0, 0.0));
+            let (top, bottom) = (0, points.len() - 1);
+            let ring = |i: usize, j: usize| 1 + (i - 1) * segments + j % segments;
+
+            let mut faces: Vec<Vec<usize>> = Vec::new();
+            for j in 0..segments {
+                faces.push(vec![top, ring(1, j + 1), ring(1, j)]);
+                for i in 1..rings - 1 {
+                    faces.push(vec![ring(i, j), ring(i, j + 1), ring(i + 1, j + 1), ring(i + 1, j)]);
+                }
+                faces.push(vec![bottom, ring(rings - 1, j), ring(rings - 1, j + 1)]);
+            }
+            let mesh = Mesh::from_points(&points, faces.iter().map(|f| &f[..]), 60f32.to_radians());
+
+            for face in &mesh.faces {
+                for v in &face.vertices {
+                    let radial = mesh.positions[v.position].normalize();
+                    let n = mesh.normals[v.normal];
+                    assert!(n.dot(radial) > 0.999, "{:?} {:?}", radial, n);
+                }
+            }
         }
     }
 }
@@ -1038,17 +1890,16 @@ pub mod utils {
 
 
 pub mod raytrace {
-    use crate::{frame_buffer::FrameBuffer, math_3d::{self, Point3d, Vec3, Transform}};
+    use crate::{bvh::Bvh, environment::Environment, frame_buffer::FrameBuffer, light::{Incident, Light}, math_3d::{self, Camera, Mat4, Vec3}, mesh::{Mesh, Triangle}, scene::Scene, texture::Texture};
     use rayon::prelude::*;
-    use wavefront::Vertex;
 
     use super::MaterialRaytrace;
 
-    // Structure pour stocker les données de triangles optimisées
+    // Attributs d'un triangle (les positions sont dans le BVH), même index que dans le BVH
     struct TriData {
-        v0: Vec3, v1: Vec3, v2: Vec3,
         n0: Vec3, n1: Vec3, n2: Vec3,
-        center: Vec3,
+        material: usize, // Index dans la liste des matériaux passée au rendu
+        uv: Option<[(f32, f32); 3]>,
     }
 
     // Structure retournée lors d'un impact
@@ -1057,243 +1908,165 @@ pub mod raytrace {
         pub t: f32,
         pub normal: Vec3,
         pub hit_p: Vec3,
-    }
+        pub material: usize,
+        pub uv: Option<(f32, f32)>,
+    }
+
+    // Scène préparée pour le lancer de rayons : triangles dans le repère monde et leur BVH.
+    // Construite au premier rendu ; quand seule la transformation change (animation),
+    // `update` retransforme les sommets et réajuste le BVH au lieu de le reconstruire.
+    pub struct RaytraceScene<'a> {
+        scene: &'a Scene,
+        root: Mat4,
+        materials: Vec<MaterialRaytrace>,
+        textures: Vec<Option<&'a Texture>>,
+        triangles: Vec<TriData>,
+        bvh: Bvh,
+    }
+
+    impl<'a> RaytraceScene<'a> {
+        pub fn new(scene: &'a Scene, root: &Mat4, default_material: &MaterialRaytrace) -> RaytraceScene<'a> {
+            // Chaque instance ajoute à la liste globale son matériau par défaut puis ceux
+            // de son maillage : le matériau d'un triangle est décalé de `base`.
+            let mut materials: Vec<MaterialRaytrace> = Vec::new();
+            let mut textures: Vec<Option<&Texture>> = Vec::new();
+            for instance in scene.instances(root) {
+                materials.extend(instance.mesh.raytrace_materials(&instance.material.unwrap_or(*default_material)));
+                textures.extend(instance.mesh.raytrace_textures());
+            }
 
-    #[derive(Clone)]
-    struct BVHNode {
-        bbox_min: Vec3,
-        bbox_max: Vec3,
-        left_child: usize,
-        right_child: usize,
-        first_tri: usize,
-        tri_count: usize,
-    }
-
-    impl Default for BVHNode {
-        fn default() -> Self {
-            Self {
-                bbox_min: Vec3::new(0.0, 0.0, 0.0),
-                bbox_max: Vec3::new(0.0, 0.0, 0.0),
-                left_child: 0,
-                right_child: 0,
-                first_tri: 0,
-                tri_count: 0,
+            let (positions, triangles) = world_triangles(scene, root);
+            // Un seul BVH pour toutes les instances
+            let bvh = Bvh::build(&positions);
+
+            RaytraceScene { scene, root: *root, materials, textures, triangles, bvh }
+        }
+
+        // Nouvelle transformation de la scène : refit du BVH (reconstruit si le nombre de triangles a changé)
+        pub fn update(&mut self, root: &Mat4) {
+            if *root == self.root {
+                return;
             }
+            let (positions, triangles) = world_triangles(self.scene, root);
+            if positions.len() == self.bvh.len() {
+                self.bvh.refit(&positions);
+            } else {
+                self.bvh = Bvh::build(&positions);
+            }
+            self.triangles = triangles;
+            self.root = *root;
         }
-    }
 
-    // --- FONCTIONS DE CALCUL ---
+        // Impact le plus proche (normale et uv interpolées)
+        fn trace(&self, origin: Vec3, dir: Vec3, t_min: f32, t_max: f32) -> Option<HitInfo> {
+            let hit = self.bvh.intersect(origin, dir, t_min, t_max)?;
+            let tri = &self.triangles[hit.index];
+            let (u, v, w) = (hit.u, hit.v, 1.0 - hit.u - hit.v);
+            let normal = tri.n0.mul(w).add(tri.n1.mul(u)).add(tri.n2.mul(v)).normalize();
+            let uv = tri.uv.map(|c| (c[0].0 * w + c[1].0 * u + c[2].0 * v, c[0].1 * w + c[1].1 * u + c[2].1 * v));
+            Some(HitInfo { t: hit.t, normal, hit_p: origin.add(dir.mul(hit.t)), material: tri.material, uv })
+        }
 
-    // pub fn _do_transforms(all_triangles: Vec<(Vec3, Vec3, Vec3, Vec3, Vec3, Vec3)>, transforms: &Vec<&Transform>) -> Vec<(Vec3, Vec3, Vec3, Vec3, Vec3, Vec3)> {
-    //     let mut all_transformed_triangles: Vec<(Vec3, Vec3, Vec3, Vec3, Vec3, Vec3)> = Vec::new();
-    //     for t in all_triangles {
-    //         let p1: Point3d = (t.0.x, t.0.y, t.0.z);
-    //         let mut pt1: Point3d;
-    //         let p2: Point3d = (t.1.x, t.1.y, t.1.z);
-    //         let mut pt2: Point3d;
-    //         let p3: Point3d = (t.2.x, t.2.y, t.2.z);
-    //         let mut pt3: Point3d;
-    //         let mut nt1: Vec3;
-    //         let mut nt2: Vec3;
-    //         let mut nt3: Vec3;
-    //         if transforms.len() > 0 { 
-    //             pt1 = math_3d::Transform::transform_point(p1, &transforms[0]);
-    //             pt2 = math_3d::Transform::transform_point(p2, &transforms[0]);
-    //             pt3 = math_3d::Transform::transform_point(p3, &transforms[0]);
-    //             nt1 = math_3d::Transform::transform_vec3(t.3, &transforms[0]);
-    //             nt2 = math_3d::Transform::transform_vec3(t.4, &transforms[0]);
-    //             nt3 = math_3d::Transform::transform_vec3(t.5, &transforms[0]);
-    //             for t in transforms.iter().skip(1) {
-    //                 pt1 = math_3d::Transform::transform_point(pt1, t);
-    //                 pt2 = math_3d::Transform::transform_point(pt2, t);
-    //                 pt3 = math_3d::Transform::transform_point(pt3, t);
-    //                 nt1 = math_3d::Transform::transform_vec3(nt1, &t);
-    //                 nt2 = math_3d::Transform::transform_vec3(nt2, &t);
-    //                 nt3 = math_3d::Transform::transform_vec3(nt3, &t);
-    //             }
-    //         } else {
-    //             pt1 = p1;
-    //             pt2 = p2;
-    //             pt3 = p3;
-    //             nt1 = t.3;
-    //             nt2 = t.4;
-    //             nt3 = t.5;
-    //         }
-    //         all_transformed_triangles.push(
-    //             (Vec3 { x:pt1.0, y:pt1.1, z:pt1.2 },
-    //              Vec3 { x:pt2.0, y:pt2.1, z:pt2.2 },
-    //              Vec3 { x:pt3.0, y:pt3.1, z:pt3.2 },
-    //              nt1,
-    //              nt2,
-    //              nt3));
-    //     }
-    //     all_transformed_triangles
-    // }
+        // Rayon d'ombre : part de lumière transmise entre t_min et t_max (produit des transparences
+        // des triangles traversés). Sortie dès qu'un objet opaque est touché : pas besoin du plus proche.
+        fn transmittance(&self, origin: Vec3, dir: Vec3, t_min: f32, t_max: f32) -> f32 {
+            let mut transmitted = 1.0f32;
+            self.bvh.for_each_hit(origin, dir, t_min, t_max, |index, _| {
+                transmitted *= self.materials[self.triangles[index].material].transparency.clamp(0.0, 1.0);
+                transmitted > 0.0
+            });
+            transmitted.max(0.0)
+        }
+    }
 
-    pub fn get_triangles<'a, I>(triangles: I) -> Vec<(Vec3, Vec3, Vec3, Vec3, Vec3, Vec3)>
-    where 
-          I: Iterator<Item = [Vertex<'a>; 3]>
-       
-    {
-        triangles.filter_map(|f| {
-            // if f.len() < 3 { return None; }
-            let p0 = Vec3::new_from_point3d((f[0].position()[0], f[0].position()[1], f[0].position()[2]));
-            let p1 = Vec3::new_from_point3d((f[1].position()[0], f[1].position()[1], f[1].position()[2]));
-            let p2 = Vec3::new_from_point3d((f[2].position()[0], f[2].position()[1], f[2].position()[2]));
-
-            // let n = if let Some(n_exists) = f[0].normal() {
-            //     n_exists
-            // } else {
-            //     [0.0, 1.0, 0.0]
-            // };
-            // let n0 = Vec3 { x: n[0], y: n[1], z: n[2] };
-            // let n = if let Some(n_exists) = f[1].normal() {
-            //     n_exists
-            // } else {
-            //     [0.0, 1.0, 0.0]
-            // };
-            // let n1 = Vec3 { x: n[0], y: n[1], z: n[2] };
-            // let n = if let Some(n_exists) = f[2].normal() {
-            //     n_exists
-            // } else {
-            //     [0.0, 1.0, 0.0]
-            // };
-            // let n2 = Vec3 { x: n[0], y: n[1], z: n[2] };
-
-            // Fonction utilitaire locale pour extraire la normale ou une valeur par défaut
-            let get_n = |v: &Vertex| {
-                let n = v.normal().unwrap_or([0.0, 1.0, 0.0]);
-                Vec3 { x: n[0], y: n[1], z: n[2] }
-            };
+    // Triangles de toutes les instances dans le repère monde : positions (pour le BVH) et attributs
+    fn world_triangles(scene: &Scene, root: &Mat4) -> (Vec<[Vec3; 3]>, Vec<TriData>) {
+        let mut positions = Vec::new();
+        let mut triangles_data = Vec::new();
+        let mut base = 0;
+        for instance in scene.instances(root) {
+            let triangles = do_transforms(instance.mesh.triangles().collect(), &instance.model_matrix);
+            positions.extend(triangles.iter().map(|tri| tri.positions));
+            triangles_data.extend(triangles.iter().map(|tri| {
+                let [n0, n1, n2] = tri.normals;
+                let material = base + Mesh::material_index(tri.material);
+                TriData { n0, n1, n2, material, uv: tri.uvs }
+            }));
+            base += instance.mesh.raytrace_material_count();
+        }
+        (positions, triangles_data)
+    }
 
-            let n0 = get_n(&f[0]);
-            let n1 = get_n(&f[1]);
-            let n2 = get_n(&f[2]);
+    // Intervalle de recherche des rayons secondaires (epsilon contre l'auto-intersection)
+    const SECONDARY_RANGE: (f32, f32) = (0.001, f32::MAX);
 
-            
-            Some((p0, p1, p2, n0, n1, n2))
-        }).collect()
-    }
-        
-    
+    // --- FONCTIONS DE CALCUL ---
 
-    pub fn do_transforms(all_triangles: Vec<(Vec3, Vec3, Vec3, Vec3, Vec3, Vec3)>, transforms: &[&Transform]) -> Vec<(Vec3, Vec3, Vec3, Vec3, Vec3, Vec3)> {
+    pub fn do_transforms(all_triangles: Vec<Triangle>, model_matrix: &Mat4) -> Vec<Triangle> {
         // L'itérateur parcourt le vecteur d'origine.
         // Le map crée une version transformée de chaque triangle.
         // Le collect alloue un nouvel espace mémoire et y place les nouveaux triangles.
         // L'ancien vecteur est ensuite détruit (into_par_iter()) 
-        
-        all_triangles.into_par_iter().map(|(v0, v1, v2, n0, n1, n2)| {
-            // On réduit toutes les transformations en une seule application successive
-            transforms.iter().fold((v0, v1, v2, n0, n1, n2), |(p1, p2, p3, nt1, nt2, nt3), trans| {
-                (
-                    Vec3::from_point3d(Transform::transform_point(p1.to_point3d(), trans)),
-                    Vec3::from_point3d(Transform::transform_point(p2.to_point3d(), trans)),
-                    Vec3::from_point3d(Transform::transform_point(p3.to_point3d(), trans)),
-                    Transform::transform_vec3(nt1, trans),
-                    Transform::transform_vec3(nt2, trans),
-                    Transform::transform_vec3(nt3, trans),
-                )
-            })
-        }).collect()
+
+        // Une seule matrice (et sa matrice des normales) pour tout l'objet
+        let normal_matrix = model_matrix.normal_matrix();
+
+        all_triangles.into_par_iter().map(|tri| tri.transform(model_matrix, &normal_matrix)).collect()
     }
 
     
     
-    fn intersect_aabb(origin: Vec3, dir: Vec3, min: Vec3, max: Vec3) -> bool {
-        let inv_d = Vec3::new(1.0 / dir.x, 1.0 / dir.y, 1.0 / dir.z);
-        let t0 = (min.x - origin.x) * inv_d.x;
-        let t1 = (max.x - origin.x) * inv_d.x;
-        let mut tmin = t0.min(t1);
-        let mut tmax = t0.max(t1);
-
-        let t2 = (min.y - origin.y) * inv_d.y;
-        let t3 = (max.y - origin.y) * inv_d.y;
-        tmin = tmin.max(t2.min(t3));
-        tmax = tmax.min(t2.max(t3));
-
-        let t4 = (min.z - origin.z) * inv_d.z;
-        let t5 = (max.z - origin.z) * inv_d.z;
-        tmin = tmin.max(t4.min(t5));
-        tmax = tmax.min(t4.max(t5));
-
-        tmax >= tmin.max(0.0)
-    }
-
-    fn build_bvh(triangles: &mut [TriData], nodes: &mut Vec<BVHNode>, offset: usize) -> usize {
-        let node_idx = nodes.len();
-        nodes.push(BVHNode::default());
-
-        let mut b_min = Vec3::new(f32::MAX, f32::MAX, f32::MAX);
-        let mut b_max = Vec3::new(f32::MIN, f32::MIN, f32::MIN);
-        for t in triangles.iter() {
-            b_min.x = b_min.x.min(t.v0.x).min(t.v1.x).min(t.v2.x);
-            b_min.y = b_min.y.min(t.v0.y).min(t.v1.y).min(t.v2.y);
-            b_min.z = b_min.z.min(t.v0.z).min(t.v1.z).min(t.v2.z);
-            b_max.x = b_max.x.max(t.v0.x).max(t.v1.x).max(t.v2.x);
-            b_max.y = b_max.y.max(t.v0.y).max(t.v1.y).max(t.v2.y);
-            b_max.z = b_max.z.max(t.v0.z).max(t.v1.z).max(t.v2.z);
-        }
-
-        let count = triangles.len();
-        if count <= 4 {
-            nodes[node_idx] = BVHNode {
-                bbox_min: b_min, bbox_max: b_max,
-                left_child: 0, right_child: 0,
-                first_tri: offset,
-                tri_count: count,
-            };
-            return node_idx;
+    // Générateur pseudo-aléatoire déterministe (xorshift), graine tirée du point éclairé
+    struct Rng(u32);
+
+    impl Rng {
+        fn from_point(p: Vec3) -> Rng {
+            let seed = p.x.to_bits() ^ p.y.to_bits().rotate_left(11) ^ p.z.to_bits().rotate_left(22);
+            Rng(seed | 1)
         }
 
-        let extent = b_max.sub(b_min);
-        let axis = if extent.x > extent.y && extent.x > extent.z { 0 } 
-        else if extent.y > extent.z { 1 } else { 2 };
+        // Suite propre à un échantillon (pixel, numéro) pour une graine donnée : le résultat
+        // ne dépend pas de l'ordre dans lequel les threads traitent les lignes
+        fn from_sample(seed: u64, pixel: u64, sample: u32) -> Rng {
+            // splitmix64
+            let mut z = seed ^ pixel.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ (sample as u64).wrapping_mul(0xD1B5_4A32_D192_ED03);
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^= z >> 31;
+            Rng((z as u32 ^ (z >> 32) as u32) | 1)
+        }
 
-        triangles.sort_by(|a, b| {
-            let va = match axis { 0 => a.center.x, 1 => a.center.y, _ => a.center.z };
-            let vb = match axis { 0 => b.center.x, 1 => b.center.y, _ => b.center.z };
-            va.partial_cmp(&vb).unwrap_or(std::cmp::Ordering::Equal)
-        });
+        fn next_f32(&mut self) -> f32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            (self.0 >> 8) as f32 / (1u32 << 24) as f32
+        }
+    }
 
-        let mid = count / 2;
-        let (left_tris, right_tris) = triangles.split_at_mut(mid);
-        let left_child = build_bvh(left_tris, nodes, offset);
-        let right_child = build_bvh(right_tris, nodes, offset + mid);
+    // Part de la source visible depuis p : lumière transmise vers une source ponctuelle,
+    // moyenne sur `samples` points (stratifiés) d'une lumière surfacique sinon
+    fn light_visibility(data: &RaytraceScene, p: Vec3, normal: Vec3, light: &Light, incident: &Incident, samples: u32) -> f32 {
+        // Départ décalé du côté de la lumière, contre l'auto-intersection
+        let side = if normal.dot(incident.direction) >= 0.0 { normal } else { normal.neg() };
+        let origin = p.add(side.mul(0.001));
 
-        nodes[node_idx] = BVHNode {
-            bbox_min: b_min, bbox_max: b_max,
-            left_child, right_child,
-            tri_count: 0, first_tri: 0,
-        };
-        node_idx
-    }
-
-    fn trace_bvh(nodes: &[BVHNode], triangles: &[TriData], node_idx: usize, 
-                 origin: Vec3, dir: Vec3, t_min: f32, t_max: &mut f32) -> Option<HitInfo> {
-        let node = &nodes[node_idx];
-        if !intersect_aabb(origin, dir, node.bbox_min, node.bbox_max) { return None; }
-
-        if node.tri_count > 0 {
-            let mut best_hit = None;
-            for i in 0..node.tri_count {
-                let tri = &triangles[node.first_tri + i];
-                // On réutilise ton intersection barycentrique
-                if let Some((t, u, v)) = intersect_triangle_barycentric(origin, dir, tri.v0, tri.v1, tri.v2) {
-                    if t < *t_max && t > t_min {
-                        *t_max = t;
-                        let w = 1.0 - u - v;
-                        let normal = tri.n0.mul(w).add(tri.n1.mul(u)).add(tri.n2.mul(v)).normalize();
-                        best_hit = Some(HitInfo { t, normal, hit_p: origin.add(dir.mul(t)) });
-                    }
-                }
-            }
-            return best_hit;
+        if !light.is_area() || samples <= 1 {
+            return data.transmittance(origin, incident.direction, 0.0, incident.distance);
         }
 
-        let hit_l = trace_bvh(nodes, triangles, node.left_child, origin, dir, t_min, t_max);
-        let hit_r = trace_bvh(nodes, triangles, node.right_child, origin, dir, t_min, t_max);
-        hit_r.or(hit_l)
+        let grid = (samples as f32).sqrt().ceil() as u32;
+        let mut rng = Rng::from_point(p);
+        let mut visible = 0.0;
+        for i in 0..samples {
+            let s = ((i % grid) as f32 + rng.next_f32()) / grid as f32;
+            let t = (((i / grid) % grid) as f32 + rng.next_f32()) / grid as f32;
+            let Some(target) = light.sample_point(s, t) else { continue };
+            let to_light = target.sub(origin);
+            let distance = to_light.length();
+            visible += data.transmittance(origin, to_light.normalize(), 0.0, distance);
+        }
+        visible / samples as f32
     }
 
     pub fn intersect_triangle_barycentric(orig: Vec3, dir: Vec3, v0: Vec3, v1: Vec3, v2: Vec3) -> Option<(f32, f32, f32)> {
@@ -1305,7 +2078,7 @@ pub mod raytrace {
         let f = 1.0 / a;
         let s = orig.sub(v0);
         let u = f * s.dot(h);
-        if u < 0.0 || u > 1.0 { return None; }
+        if !(0.0..=1.0).contains(&u) { return None; }
         let q = s.cross(edge1);
         let v = f * dir.dot(q);
         if v < 0.0 || u + v > 1.0 { return None; }
@@ -1313,224 +2086,209 @@ pub mod raytrace {
         if t > 0.000001 { Some((t, u, v)) } else { None }
     }
 
-    pub fn calculate_sky_color(direction: Vec3) -> (f32, f32, f32) {
-        // Normalisation de direction.y entre 0.0 et 1.0
-        let t = 0.5 * (direction.y + 1.0);
+    // Milieux traversés par un rayon (objets transparents imbriqués), l'air en dehors de tout objet
+    #[derive(Clone, Copy)]
+    struct Media {
+        indices: [f32; 8],
+        len: usize,
+    }
 
-        // Couleur A (Bas / Horizon) : Rouge
-        // RGB: (1.0, 0.05, 0.05)
-        let color_a = (1.0, 0.05, 0.05);
+    impl Media {
+        const AIR: Media = Media { indices: [1.0; 8], len: 0 };
 
-        // Couleur B (Haut / Zénith) : Turquoise
-        // RGB: (0.0, 0.8, 0.8)
-        let color_b = (0.0, 0.8, 0.8);
+        fn current(&self) -> f32 {
+            if self.len == 0 { 1.0 } else { self.indices[self.len - 1] }
+        }
 
-        // Interpolation linéaire (Lerp)
-        let r = (1.0 - t) * color_a.0 + t * color_b.0;
-        let g = (1.0 - t) * color_a.1 + t * color_b.1;
-        let b = (1.0 - t) * color_a.2 + t * color_b.2;
+        fn enter(mut self, refractive_index: f32) -> Media {
+            if self.len < self.indices.len() {
+                self.indices[self.len] = refractive_index;
+                self.len += 1;
+            }
+            self
+        }
 
-        (r, g, b)
+        fn exit(mut self) -> Media {
+            self.len = self.len.saturating_sub(1);
+            self
+        }
     }
 
-    
-    // --- RENDERER ---
-    // fn trace_scene(
-    //     origin: Vec3,
-    //     direction: Vec3,
-    //     bvh_nodes: &[BVHNode],
-    //     triangles: &[TriData],
-    //     light_dir: Vec3,
-    //     material: &MaterialRaytrace,
-    //     depth: u32,
-    // ) -> (f32, f32, f32) {
-    //     // Limite de récursion (pour éviter les boucles infinies entre miroirs)
-    //     if depth > 4 {
-    //         return (0.1, 0.1, 0.1); // Couleur ambiante de fond
-    //     }
-
-    //     let mut t_max = f32::MAX;
-    //     if let Some(hit) = trace_bvh(bvh_nodes, triangles, 0, origin, direction, 0.001, &mut t_max) {
-    //         let v = origin.sub(hit.hit_p).normalize();
-    //         let l = light_dir.normalize();
-            
-    //         // 1. Calcul de la lumière locale (Phong)
-    //         let local_intensity = math_3d::utils::calculate_intensity(hit.normal, l, v, &material.material);
-
-    //         let mut final_color = local_intensity;
-
-    //         // 2. Gestion de la Réflexion (Miroir)
-    //         if material.reflectivity > 0.0 {
-    //             let reflect_dir = direction.reflect(hit.normal).normalize();
-    //             // On décale le point d'origine (epsilon) pour éviter l'auto-intersection
-    //             let reflect_origin = hit.hit_p.add(hit.normal.mul(0.001));
-                
-    //             let reflected_color = trace_scene(reflect_origin, reflect_dir, bvh_nodes, triangles, light_dir, material, depth + 1);
-                
-    //             final_color.0 = final_color.0 * (1.0 - material.reflectivity) + reflected_color.0 * material.reflectivity;
-    //             final_color.1 = final_color.1 * (1.0 - material.reflectivity) + reflected_color.1 * material.reflectivity;
-    //             final_color.2 = final_color.2 * (1.0 - material.reflectivity) + reflected_color.2 * material.reflectivity;
-    //         }
-
-    //         // 3. Gestion de la Transparence (Réfraction simple)
-    //         // if material.transparency > 0.0 {
-    //         //     // Pour simplifier, on tire un rayon tout droit (ou utilise refract() si tu l'as implémenté)
-    //         //     let refract_origin = hit.hit_p.sub(hit.normal.mul(0.001));
-    //         //     let refracted_color = trace_scene(refract_origin, direction, bvh_nodes, triangles, light_dir, material, depth + 1);
-
-    //         //     final_color.0 = final_color.0 * (1.0 - material.transparency) + refracted_color.0 * material.transparency;
-    //         //     final_color.1 = final_color.1 * (1.0 - material.transparency) + refracted_color.1 * material.transparency;
-    //         //     final_color.2 = final_color.2 * (1.0 - material.transparency) + refracted_color.2 * material.transparency;
-    //         // }
-
-    //         if material.transparency > 0.0 {
-    //             let refract_origin = hit.hit_p.sub(hit.normal.mul(0.001));
-    //             let refracted_color = trace_scene(refract_origin, direction, bvh_nodes, triangles, light_dir, material, depth + 1);
-
-    //             // 1. On garde le spéculaire (le petit reflet blanc brillant en surface)
-    //             // 2. On remplace le diffus par la couleur réfractée
-    //             final_color.0 = (final_color.0 * material.reflectivity) + (refracted_color.0 * material.transparency);
-    //             final_color.1 = (final_color.1 * material.reflectivity) + (refracted_color.1 * material.transparency);
-    //             final_color.2 = (final_color.2 * material.reflectivity) + (refracted_color.2 * material.transparency);
-                
-    //             // Si tu veux que l'objet colore la lumière qui le traverse (ex: verre rouge) :
-    //             // final_color.0 = refracted_color.0 * material.kd.0;
-    //         }
+    // Fresnel exact pour un dioptre (lumière non polarisée) : part réfléchie, 1.0 en réflexion totale interne
+    pub fn fresnel_dielectric(cos_i: f32, eta_i: f32, eta_t: f32) -> f32 {
+        let cos_i = cos_i.clamp(0.0, 1.0);
+        let sin_t = eta_i / eta_t * (1.0 - cos_i * cos_i).sqrt();
+        if sin_t >= 1.0 {
+            return 1.0;
+        }
+        let cos_t = (1.0 - sin_t * sin_t).sqrt();
+        let rs = (eta_i * cos_i - eta_t * cos_t) / (eta_i * cos_i + eta_t * cos_t);
+        let rp = (eta_t * cos_i - eta_i * cos_t) / (eta_t * cos_i + eta_i * cos_t);
+        (rs * rs + rp * rp) * 0.5
+    }
 
-    //         return final_color;
-    //     }
+    // Approximation de Schlick, f0 : réflectance à incidence normale
+    pub fn schlick(cos_i: f32, f0: f32) -> f32 {
+        f0 + (1.0 - f0) * (1.0 - cos_i.clamp(0.0, 1.0)).powi(5)
+    }
 
-    //     //(0.06, 0.06, 0.06) // Couleur du "vide" (noir grisâtre)
-    //     calculate_sky_color(direction)
-    // }
+    // Réflectance à incidence normale d'un dioptre air / indice n
+    pub fn dielectric_f0(refractive_index: f32) -> f32 {
+        let r = (refractive_index - 1.0) / (refractive_index + 1.0);
+        r * r
+    }
 
+    
+    // --- RENDERER ---
+    #[allow(clippy::too_many_arguments)]
     fn trace_scene(
         origin: Vec3,
         direction: Vec3,
-        bvh_nodes: &[BVHNode],
-        triangles: &[TriData],
-        light_dir: Vec3,
-        material: &MaterialRaytrace,
+        t_range: (f32, f32),
+        data: &RaytraceScene,
+        lights: &[Light],
+        shadow_samples: u32,
+        environment: &Environment,
+        media: Media,
         depth: u32,
     ) -> (f32, f32, f32) {
-        // 1. Limite de récursion et ciel
-        if depth > 4 {
-            return calculate_sky_color(direction);
+        // 1. Limite de récursion et ciel (un objet transparent coûte deux niveaux : entrée et sortie)
+        if depth > 6 {
+            return environment.radiance(direction);
         }
 
-        let mut t_max = f32::MAX;
-        if let Some(hit) = trace_bvh(bvh_nodes, triangles, 0, origin, direction, 0.001, &mut t_max) {
+        // t_range : plans near/far de la caméra pour les rayons primaires
+        if let Some(hit) = data.trace(origin, direction, t_range.0, t_range.1) {
+            let mut material = data.materials[hit.material];
+            // map_Kd : texture échantillonnée au point d'impact (barycentriques)
+            if let (Some(Some(texture)), Some((u, v))) = (data.textures.get(hit.material), hit.uv) {
+                material.material = material.material.with_texel(texture.sample(u, v));
+            }
             let v = origin.sub(hit.hit_p).normalize();
-            let l = light_dir.normalize();
-            
-            // --- CALCUL DU FRESNEL ---
-            // Détermine si on regarde la face de biais ou de face
-            let view_dot_norm = direction.dot(hit.normal).abs();
-            let fresnel = (1.0 - view_dot_norm).powi(3); // Coefficient de réflexion sur les bords
+
+            // Face avant : le rayon entre dans l'objet, face arrière : il en sort.
+            // n : normale du côté du rayon incident
+            let front_face = direction.dot(hit.normal) < 0.0;
+            let n = if front_face { hit.normal } else { hit.normal.neg() };
+            let cos_i = -direction.dot(n);
+            let transparency = material.transparency.clamp(0.0, 1.0);
+
+            // --- FRESNEL ---
+            // Partie opaque : Schlick, réflectivité à incidence normale donnée par le matériau (ou son indice)
+            let opaque_refl = schlick(cos_i, material.reflectivity.max(dielectric_f0(material.refractive_index)));
+            // Partie transparente : Fresnel exact entre le milieu courant et celui de l'objet
+            let (eta_i, eta_t, inner_media) = if front_face {
+                (media.current(), material.refractive_index, media.enter(material.refractive_index))
+            } else {
+                (material.refractive_index, media.exit().current(), media.exit())
+            };
+            let fresnel = if transparency > 0.0 { fresnel_dielectric(cos_i, eta_i, eta_t) } else { 0.0 };
 
             // --- GESTION DES REBONDS (REFLEXION) ---
             let mut reflected_color = (0.0, 0.0, 0.0);
-            // On augmente la réflectivité naturelle par le fresnel
-            let effective_refl = material.reflectivity.max(fresnel * 0.5); 
-            
+            let effective_refl = (1.0 - transparency) * opaque_refl + transparency * fresnel;
+
             if effective_refl > 0.0 {
-                let reflect_dir = direction.reflect(hit.normal).normalize();
-                let reflect_origin = hit.hit_p.add(hit.normal.mul(0.001));
-                reflected_color = trace_scene(reflect_origin, reflect_dir, bvh_nodes, triangles, light_dir, material, depth + 1);
+                let reflect_dir = direction.reflect(n).normalize();
+                let reflect_origin = hit.hit_p.add(n.mul(0.001));
+                reflected_color = trace_scene(reflect_origin, reflect_dir, SECONDARY_RANGE, data, lights, shadow_samples, environment, media, depth + 1);
             }
 
             // --- GESTION DE LA TRANSPARENCE (REFRACTION) ---
+            // Snell-Descartes ; en réflexion totale interne fresnel vaut 1 et il n'y a rien à transmettre
             let mut refracted_color = (0.0, 0.0, 0.0);
-            let effective_trans = material.transparency * (1.0 - effective_refl);
+            let effective_trans = transparency * (1.0 - fresnel);
 
-            if effective_trans > 0.0 {
-                let refract_origin = hit.hit_p.sub(hit.normal.mul(0.001));
-                // On tire tout droit pour l'instant (direction)
-                refracted_color = trace_scene(refract_origin, direction, bvh_nodes, triangles, light_dir, material, depth + 1);
+            if effective_trans > 0.0
+                && let Some(refract_dir) = direction.refract(n, eta_i / eta_t)
+            {
+                let refract_origin = hit.hit_p.sub(n.mul(0.001));
+                refracted_color = trace_scene(refract_origin, refract_dir, SECONDARY_RANGE, data, lights, shadow_samples, environment, inner_media, depth + 1);
             }
 
             // --- CALCUL DE L'INTENSITÉ LOCALE (PHONG) ---
-            let (r_local, g_local, b_local) = math_3d::utils::calculate_intensity(hit.normal, l, v, &material.material);
+            // Rayons d'ombre vers chaque source
+            let visibility = |_, light: &Light, incident: &Incident| {
+                light_visibility(data, hit.hit_p, hit.normal, light, incident, shadow_samples)
+            };
+            let (diffuse, specular) = math_3d::utils::calculate_intensity_split_with(hit.normal, hit.hit_p, v, &material.material, lights, visibility);
 
             // --- MÉLANGE FINAL ---
-            // On sépare le spéculaire (éclat lumineux) du reste
-            let mut final_r = (reflected_color.0 * effective_refl) + (refracted_color.0 * effective_trans);
-            let mut final_g = (reflected_color.1 * effective_refl) + (refracted_color.1 * effective_trans);
-            let mut final_b = (reflected_color.2 * effective_refl) + (refracted_color.2 * effective_trans);
-
-            // On ajoute le spéculaire par-dessus pour la brillance (ks)
-            // Et un peu de diffus/ambiant si l'objet n'est pas totalement transparent
-            let opacity = 1.0 - effective_trans;
-            final_r += r_local * opacity;
-            final_g += g_local * opacity;
-            final_b += b_local * opacity;
+            // Le diffus/ambiant ne concerne que la partie opaque, le spéculaire (éclat des sources) toute la surface.
+            // Un objet émissif ajoute sa propre lumière.
+            let opacity = 1.0 - transparency;
+            let final_r = reflected_color.0 * effective_refl + refracted_color.0 * effective_trans + diffuse.0 * opacity + specular.0 + material.emission.0;
+            let final_g = reflected_color.1 * effective_refl + refracted_color.1 * effective_trans + diffuse.1 * opacity + specular.1 + material.emission.1;
+            let final_b = reflected_color.2 * effective_refl + refracted_color.2 * effective_trans + diffuse.2 * opacity + specular.2 + material.emission.2;
 
             return (final_r, final_g, final_b);
         }
 
-        // Retourne le dégradé turquoise/rouge si rien n'est touché
-        calculate_sky_color(direction)
+        // Rien n'est touché : l'environnement (ciel, fond ou image)
+        environment.radiance(direction)
     }
     
     
+    #[allow(clippy::too_many_arguments)]
     pub fn render_raytrace(
-        triangles: &[(Vec3, Vec3, Vec3, Vec3, Vec3, Vec3)], 
-        eye: (f32, f32, f32), target: Point3d,
-        light_dir: Vec3,
-        material: &MaterialRaytrace,
+        data: &RaytraceScene,
+        camera: &Camera,
+        environment: &Environment,
+        lights: &[Light],
+        shadow_samples: u32,
+        aa: u32,
         width: u32, height: u32,
         fb: &mut FrameBuffer
     ) {
-        let eye_vec = Vec3::new_from_point3d(eye);
-        let target_vec = Vec3 { x: target.0, y: target.1, z: target.2 };
-        let forward = target_vec.sub(eye_vec).normalize();
-        let right = forward.cross(Vec3 { x: 0.0, y: 1.0, z: 0.0 }).normalize();
-        let up = right.cross(forward).normalize();
-        let aspect_ratio = width as f32 / height as f32;
-
-        // 1. Préparation des données (TriData)
-        let mut triangles_data: Vec<TriData> = triangles.iter().map(|tri| {
-            let center = Vec3::new(
-                (tri.0.x + tri.1.x + tri.2.x) / 3.0,
-                (tri.0.y + tri.1.y + tri.2.y) / 3.0,
-                (tri.0.z + tri.1.z + tri.2.z) / 3.0,
-            );
-            TriData { v0: tri.0, v1: tri.1, v2: tri.2, n0: tri.3, n1: tri.4, n2: tri.5, center }
-        }).collect();
-
-        // 2. Construction du BVH
-        let mut bvh_nodes = Vec::with_capacity(triangles_data.len() * 2);
-        build_bvh(&mut triangles_data, &mut bvh_nodes, 0);
+        let (_, _, forward) = camera.basis();
 
+        // 1 et 2. Triangles, matériaux et BVH de toutes les instances : préparés par l'appelant (RaytraceScene)
         let mut image_data = vec![(16u8, 16u8, 16u8); (width * height) as usize];
 
         // 3. Rendu Parallèle
         image_data.par_chunks_mut(width as usize).enumerate().for_each(|(y, row)| {
             for x in 0..width {
-                let px = (2.0 * ((x as f32 + 0.5) / width as f32) - 1.0) * aspect_ratio;
-                let py = 1.0 - 2.0 * ((y as f32 + 0.5) / height as f32);
-                let zoom = 3.5;
-                let ray_dir = forward.add(right.mul(px / zoom)).add(up.mul(py / zoom)).normalize();
-
-                // Appel de la fonction récursive au lieu du simple trace_bvh
-                let color = trace_scene(
-                    eye_vec, 
-                    ray_dir, 
-                    &bvh_nodes, 
-                    &triangles_data, 
-                    light_dir, 
-                    material, 
-                    0
-                );
-                row[x as usize] = math_3d::utils::intensity_to_color(color);
-                
-                // let mut t_max = f32::MAX;
-                // if let Some(hit) = trace_bvh(&bvh_nodes, &triangles_data, 0, eye_vec, ray_dir, 0.001, &mut t_max) {
-                //     let v = eye_vec.sub(hit.hit_p).normalize();
-                //     let l = light_dir.normalize();
-                //     let intensity = math_3d::utils::calculate_intensity(hit.normal, l, v, &material.material);
-                //     row[x as usize] = math_3d::utils::intensity_to_color(intensity);
-                // }
+                let trace = |px: f32, py: f32| {
+                    let (ray_origin, ray_dir) = camera.primary_ray(px, py, width as f32, height as f32);
+                    // near/far sont des distances le long de l'axe de visée
+                    let cos = ray_dir.dot(forward).max(1e-6);
+                    let t_range = (camera.near / cos, camera.far / cos);
+
+                    // Appel de la fonction récursive au lieu du simple trace_bvh
+                    trace_scene(
+                        ray_origin, 
+                        ray_dir, 
+                        t_range,
+                        data,
+                        lights,
+                        shadow_samples,
+                        environment,
+                        Media::AIR,
+                        0
+                    )
+                };
+
+                if aa <= 1 {
+                    // Même projection que les rasteriseurs : rayon par le centre du pixel
+                    let color = trace(x as f32 + 0.5, y as f32 + 0.5);
+                    row[x as usize] = math_3d::utils::intensity_to_color(color);
+                    continue;
+                }
+
+                // Anticrénelage : un rayon jittéré dans chaque case d'une grille aa x aa du pixel.
+                // Chaque échantillon est borné à 1.0 avant la moyenne, comme il serait affiché.
+                let mut rng = Rng::from_sample(0, y as u64 * width as u64 + x as u64, 0);
+                let mut sum = (0.0, 0.0, 0.0);
+                for j in 0..aa {
+                    for i in 0..aa {
+                        let px = x as f32 + (i as f32 + rng.next_f32()) / aa as f32;
+                        let py = y as f32 + (j as f32 + rng.next_f32()) / aa as f32;
+                        let c = trace(px, py);
+                        sum = (sum.0 + c.0.min(1.0), sum.1 + c.1.min(1.0), sum.2 + c.2.min(1.0));
+                    }
+                }
+                let k = 1.0 / (aa * aa) as f32;
+                row[x as usize] = math_3d::utils::intensity_to_color((sum.0 * k, sum.1 * k, sum.2 * k));
             }
         });
 
@@ -1541,4 +2299,404 @@ pub mod raytrace {
             }
         }
     }
+
+    // --- PATH TRACING ---
+
+    // Garde-fou : la roulette russe arrête les chemins bien avant
+    const MAX_BOUNCES: u32 = 64;
+    // Rebonds garantis avant la roulette russe
+    const ROULETTE_AFTER: u32 = 3;
+
+    // Direction tirée dans l'hémisphère autour de n avec une densité proportionnelle au cosinus
+    fn cosine_sample(n: Vec3, rng: &mut Rng) -> Vec3 {
+        let (r1, r2) = (rng.next_f32(), rng.next_f32());
+        let phi = 2.0 * std::f32::consts::PI * r1;
+        let r = r2.sqrt();
+        // Repère orthonormé (t, b, n)
+        let helper = if n.x.abs() > 0.9 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+        let t = helper.cross(n).normalize();
+        let b = n.cross(t);
+        t.mul(r * phi.cos()).add(b.mul(r * phi.sin())).add(n.mul((1.0 - r2).max(0.0).sqrt())).normalize()
+    }
+
+    // Rayon d'ombre du path tracing : un seul point tiré sur une lumière surfacique,
+    // les échantillons par pixel font la moyenne
+    fn shadow_ray(data: &RaytraceScene, p: Vec3, n: Vec3, light: &Light, incident: &Incident, rng: &mut Rng) -> f32 {
+        let side = if n.dot(incident.direction) >= 0.0 { n } else { n.neg() };
+        let origin = p.add(side.mul(0.001));
+        let (direction, distance) = match light.sample_point(rng.next_f32(), rng.next_f32()) {
+            Some(target) if light.is_area() => {
+                let to_light = target.sub(origin);
+                (to_light.normalize(), to_light.length())
+            }
+            _ => (incident.direction, incident.distance),
+        };
+        data.transmittance(origin, direction, 0.0, distance)
+    }
+
+    // Un chemin depuis la caméra : à chaque impact, éclairage direct des sources (rayons d'ombre)
+    // puis un seul rebond choisi au hasard selon le matériau (réflexion, réfraction ou diffus).
+    // Les probabilités de choix valent les poids de chaque lobe : seul le rebond diffus teinte le chemin.
+    #[allow(clippy::too_many_arguments)]
+    fn trace_path(origin: Vec3, direction: Vec3, t_range: (f32, f32), data: &RaytraceScene, environment: &Environment, lights: &[Light], rng: &mut Rng) -> (f32, f32, f32) {
+        let (mut origin, mut direction, mut t_range) = (origin, direction, t_range);
+        let mut media = Media::AIR;
+        let mut radiance = (0.0, 0.0, 0.0);
+        let mut throughput = (1.0, 1.0, 1.0);
+
+        for bounce in 0..MAX_BOUNCES {
+            let Some(hit) = data.trace(origin, direction, t_range.0, t_range.1) else {
+                // L'environnement éclaire toute la scène
+                let sky = environment.radiance(direction);
+                radiance.0 += throughput.0 * sky.0;
+                radiance.1 += throughput.1 * sky.1;
+                radiance.2 += throughput.2 * sky.2;
+                break;
+            };
+
+            let mut material = data.materials[hit.material];
+            if let (Some(Some(texture)), Some((u, v))) = (data.textures.get(hit.material), hit.uv) {
+                material.material = material.material.with_texel(texture.sample(u, v));
+            }
+            let m = &material.material;
+
+            // Surfaces émissives : touchées par hasard, aucune ne sert de source échantillonnée
+            radiance.0 += throughput.0 * material.emission.0;
+            radiance.1 += throughput.1 * material.emission.1;
+            radiance.2 += throughput.2 * material.emission.2;
+
+            // Mêmes conventions que trace_scene
+            let front_face = direction.dot(hit.normal) < 0.0;
+            let n = if front_face { hit.normal } else { hit.normal.neg() };
+            let cos_i = -direction.dot(n);
+            let transparency = material.transparency.clamp(0.0, 1.0);
+            let opaque_refl = schlick(cos_i, material.reflectivity.max(dielectric_f0(material.refractive_index)));
+            let (eta_i, eta_t, inner_media) = if front_face {
+                (media.current(), material.refractive_index, media.enter(material.refractive_index))
+            } else {
+                (material.refractive_index, media.exit().current(), media.exit())
+            };
+            let fresnel = if transparency > 0.0 { fresnel_dielectric(cos_i, eta_i, eta_t) } else { 0.0 };
+
+            // Éclairage direct : diffus de la partie opaque non réfléchie, reflets spéculaires partout.
+            // Pas d'ambiant : il est remplacé par l'éclairage indirect. Le diffus suit la BRDF
+            // lambertienne kd/pi, comme les rebonds : les sources et les surfaces émissives ou le
+            // ciel de même valeur éclairent autant.
+            let diffuse_weight = (1.0 - transparency) * (1.0 - opaque_refl) * std::f32::consts::FRAC_1_PI;
+            let v = direction.neg();
+            for light in lights {
+                let Some(incident) = light.incident(hit.hit_p) else { continue };
+                let visible = shadow_ray(data, hit.hit_p, n, light, &incident, rng);
+                if visible <= 0.0 {
+                    continue;
+                }
+                let (diffuse, specular) = math_3d::utils::light_contribution(n, incident.direction, v, m);
+                radiance.0 += throughput.0 * incident.radiance.0 * visible * (diffuse.0 * diffuse_weight + specular.0);
+                radiance.1 += throughput.1 * incident.radiance.1 * visible * (diffuse.1 * diffuse_weight + specular.1);
+                radiance.2 += throughput.2 * incident.radiance.2 * visible * (diffuse.2 * diffuse_weight + specular.2);
+            }
+
+            // Rebond suivant
+            let choice = rng.next_f32();
+            if choice < transparency * fresnel || (choice >= transparency && choice < transparency + (1.0 - transparency) * opaque_refl) {
+                // Réflexion miroir (Fresnel du diélectrique ou reflet de la partie opaque)
+                direction = direction.reflect(n).normalize();
+                origin = hit.hit_p.add(n.mul(0.001));
+            } else if choice < transparency {
+                // Réfraction, fresnel < 1 : pas de réflexion totale interne
+                let Some(refract_dir) = direction.refract(n, eta_i / eta_t) else { break };
+                direction = refract_dir;
+                origin = hit.hit_p.sub(n.mul(0.001));
+                media = inner_media;
+            } else {
+                // Diffus lambertien : tirage selon le cosinus, la densité compense le cosinus et 1/pi
+                direction = cosine_sample(n, rng);
+                origin = hit.hit_p.add(n.mul(0.001));
+                throughput = (throughput.0 * m.kd.0, throughput.1 * m.kd.1, throughput.2 * m.kd.2);
+            }
+            t_range = SECONDARY_RANGE;
+
+            // Roulette russe : un chemin qui ne transporte presque plus rien s'arrête souvent,
+            // les survivants sont renforcés d'autant (estimateur non biaisé)
+            if bounce >= ROULETTE_AFTER {
+                let survive = throughput.0.max(throughput.1).max(throughput.2).min(0.95);
+                if rng.next_f32() >= survive {
+                    break;
+                }
+                throughput = (throughput.0 / survive, throughput.1 / survive, throughput.2 / survive);
+            }
+        }
+
+        radiance
+    }
+
+    // Path tracing progressif : une passe ajoute un échantillon (rayon jitteré dans le pixel) par pixel,
+    // lignes en parallèle. `on_pass` reçoit l'image moyennée après les passes 1, 2, 4, 8... et la dernière.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_pathtrace(
+        data: &RaytraceScene,
+        camera: &Camera,
+        environment: &Environment,
+        lights: &[Light],
+        spp: u32,
+        seed: u64,
+        width: u32, height: u32,
+        fb: &mut FrameBuffer,
+        on_pass: &mut dyn FnMut(&FrameBuffer, u32),
+    ) {
+        let (_, _, forward) = camera.basis();
+
+        let mut accum = vec![(0.0f32, 0.0f32, 0.0f32); (width * height) as usize];
+
+        for pass in 0..spp {
+            accum.par_chunks_mut(width as usize).enumerate().for_each(|(y, row)| {
+                for x in 0..width {
+                    let pixel = y as u64 * width as u64 + x as u64;
+                    let mut rng = Rng::from_sample(seed, pixel, pass);
+                    let (ray_origin, ray_dir) = camera.primary_ray(
+                        x as f32 + rng.next_f32(),
+                        y as f32 + rng.next_f32(),
+                        width as f32,
+                        height as f32,
+                    );
+                    let cos = ray_dir.dot(forward).max(1e-6);
+                    let t_range = (camera.near / cos, camera.far / cos);
+
+                    let sample = trace_path(ray_origin, ray_dir, t_range, data, environment, lights, &mut rng);
+                    // Un échantillon NaN ou infini gâcherait le pixel pour toutes les passes suivantes
+                    if sample.0.is_finite() && sample.1.is_finite() && sample.2.is_finite() {
+                        let acc = &mut row[x as usize];
+                        *acc = (acc.0 + sample.0, acc.1 + sample.1, acc.2 + sample.2);
+                    }
+                }
+            });
+
+            let done = pass + 1;
+            if done.is_power_of_two() || done == spp {
+                let k = 1.0 / done as f32;
+                for y in 0..height {
+                    for x in 0..width {
+                        let c = accum[(y * width + x) as usize];
+                        fb.pixel(x, y, math_3d::utils::intensity_to_color((c.0 * k, c.1 * k, c.2 * k)));
+                    }
+                }
+                on_pass(fb, done);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        // Cube sous un ciel dégradé, 16 x 12 pixels, quelques échantillons
+        fn render(seed: u64) -> Vec<u8> {
+            let scene = Scene::from_mesh(Mesh::cube(0.5));
+            let data = RaytraceScene::new(&scene, &Mat4::IDENTITY, &MaterialRaytrace::epic_slayer());
+            let camera = Camera::perspective((2.0, 1.5, 3.0), (0.0, 0.0, 0.0), std::f32::consts::FRAC_PI_4);
+            let environment = Environment::Gradient { bottom: (0.2, 0.1, 0.1), top: (0.5, 0.7, 0.9) };
+            let lights = [Light::directional(Vec3::new(-0.5, 1.0, 1.0))];
+            let mut fb = FrameBuffer::new(16, 12);
+            render_pathtrace(&data, &camera, &environment, &lights, 4, seed, 16, 12, &mut fb, &mut |_, _| {});
+            fb.pixels
+        }
+
+        #[test]
+        fn same_seed_same_image() {
+            assert_eq!(render(42), render(42));
+        }
+
+        #[test]
+        fn seed_changes_image() {
+            assert_ne!(render(42), render(43));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close_mat4(a: &Mat4, b: &Mat4) -> bool {
+        a.m.iter().flatten().zip(b.m.iter().flatten()).all(|(x, y)| (x - y).abs() < 1e-4)
+    }
+
+    fn close_point(a: Point3d, b: Point3d) -> bool {
+        (a.0 - b.0).abs() < 1e-4 && (a.1 - b.1).abs() < 1e-4 && (a.2 - b.2).abs() < 1e-4
+    }
+
+    fn transforms() -> [Transform; 3] {
+        [
+            Transform { scale: 2.0, rotation: (0.3, -1.2, 0.7), translation: (1.0, -2.0, 3.0) },
+            Transform { scale: 0.5, rotation: (2.5, 0.4, -3.0), translation: (-0.5, 0.25, 4.0) },
+            Transform { scale: 1.0, rotation: (0.0, std::f32::consts::FRAC_PI_2, 0.0), translation: (0.0, 0.0, 0.0) },
+        ]
+    }
+
+    #[test]
+    fn mat4_inverse() {
+        let m = Mat4::translation(1.0, -2.0, 3.0)
+            .mul(&Mat4::from_euler(0.3, -1.2, 0.7))
+            .mul(&Mat4::scale(2.0, 0.5, 3.0));
+        let inv = m.inverse().unwrap();
+        assert!(close_mat4(&m.mul(&inv), &Mat4::IDENTITY));
+        assert!(close_mat4(&inv.mul(&m), &Mat4::IDENTITY));
+
+        // Matrice quelconque (pas seulement affine) : le pivot doit permuter les lignes
+        let m = Mat4 { m: [[0.0, 2.0, 1.0, 0.0], [1.0, 0.0, 0.0, 3.0], [0.0, 1.0, 0.0, 1.0], [2.0, 0.0, 1.0, 1.0]] };
+        assert!(close_mat4(&m.mul(&m.inverse().unwrap()), &Mat4::IDENTITY));
+
+        // Singulières : échelle nulle, lignes liées
+        assert!(Mat4::scale(1.0, 0.0, 1.0).inverse().is_none());
+        let m = Mat4 { m: [[1.0, 2.0, 3.0, 4.0], [2.0, 4.0, 6.0, 8.0], [0.0, 1.0, 0.0, 1.0], [0.0, 0.0, 0.0, 1.0]] };
+        assert!(m.inverse().is_none());
+    }
+
+    #[test]
+    fn mat3_inverse() {
+        let m = Mat3 { m: [[2.0, 0.0, 1.0], [1.0, 3.0, 0.0], [0.0, 1.0, 4.0]] };
+        let inv = m.inverse().unwrap();
+        let id = m.mul(&inv);
+        for (i, row) in id.m.iter().enumerate() {
+            for (j, v) in row.iter().enumerate() {
+                assert!((v - if i == j { 1.0 } else { 0.0 }).abs() < 1e-5, "{:?}", id);
+            }
+        }
+        assert!(Mat3 { m: [[1.0, 2.0, 3.0], [2.0, 4.0, 6.0], [0.0, 1.0, 1.0]] }.inverse().is_none());
+    }
+
+    // Échelle non uniforme : la normale transformée reste perpendiculaire à la surface transformée
+    #[test]
+    fn normal_matrix_non_uniform_scale() {
+        let m = Mat4::from_euler(0.4, 0.9, -0.2).mul(&Mat4::scale(4.0, 1.0, 0.5));
+        let normal_matrix = m.normal_matrix();
+
+        // Plan x + y + z = 0, normale (1, 1, 1), deux tangentes
+        let n = Vec3::new(1.0, 1.0, 1.0).normalize();
+        for tangent in [Vec3::new(1.0, -1.0, 0.0), Vec3::new(0.0, 1.0, -1.0)] {
+            let t = m.transform_vec3(tangent);
+            assert!(normal_matrix.mul_vec3(n).dot(t).abs() < 1e-5);
+            // La matrice du modèle elle-même ne convient pas
+            assert!(m.transform_vec3(n).dot(t).abs() > 0.1);
+        }
+
+        // Rotation pure : la matrice des normales est la rotation
+        let r = Mat4::from_euler(0.4, 0.9, -0.2);
+        let v = Vec3::new(0.3, -0.5, 0.8);
+        assert!(r.normal_matrix().mul_vec3(v).sub(r.transform_vec3(v)).length() < 1e-5);
+    }
+
+    // Les matrices donnent les mêmes points que l'ancien chemin (angles d'Euler appliqués un à un)
+    #[test]
+    fn matrices_match_euler_path() {
+        let points = [(0.0, 0.0, 0.0), (1.0, 2.0, 3.0), (-4.0, 0.5, 2.5)];
+        let transforms = transforms();
+        for t in &transforms {
+            for &p in &points {
+                let expected = Transform::transform_point(p, t);
+                assert!(close_point(t.to_mat4().transform_point(p), expected));
+                let n = Vec3::new(p.0, p.1, p.2 + 1.0);
+                let expected = Transform::transform_vec3(n, t);
+                assert!(t.to_mat4().normal_matrix().mul_vec3(n).normalize().sub(expected).length() < 1e-4);
+            }
+        }
+
+        let chain: Vec<&Transform> = transforms.iter().collect();
+        let m = Transform::compose(&chain);
+        for &p in &points {
+            let expected = transforms.iter().fold(p, Transform::transform_point);
+            assert!(close_point(m.transform_point(p), expected), "{:?} {:?}", m.transform_point(p), expected);
+        }
+        assert_eq!(Transform::compose(&[]), Mat4::IDENTITY);
+    }
+
+    fn close_quat(a: Quat, b: Quat) -> bool {
+        // q et -q sont la même rotation
+        (a.dot(b).abs() - 1.0).abs() < 1e-5
+    }
+
+    fn close_vec(a: Vec3, b: Vec3) -> bool {
+        a.sub(b).length() < 1e-4
+    }
+
+    #[test]
+    fn slerp_endpoints_and_midpoint() {
+        let y = Vec3::new(0.0, 1.0, 0.0);
+        let a = Quat::from_axis_angle(y, 0.2);
+        let b = Quat::from_axis_angle(y, 1.4);
+        assert!(close_quat(a.slerp(b, 0.0), a));
+        assert!(close_quat(a.slerp(b, 1.0), b));
+        assert!(close_quat(a.slerp(b, 0.5), Quat::from_axis_angle(y, 0.8)));
+        // Vitesse angulaire constante
+        assert!(close_quat(a.slerp(b, 0.25), Quat::from_axis_angle(y, 0.5)));
+
+        // Axes différents : le milieu est à égale distance angulaire des deux bouts
+        let c = Quat::from_axis_angle(Vec3::new(1.0, 0.0, 0.0), 1.0);
+        let mid = a.slerp(c, 0.5);
+        assert!((mid.dot(a) - mid.dot(c)).abs() < 1e-5);
+        assert!((mid.dot(mid) - 1.0).abs() < 1e-5);
+    }
+
+    // Produit scalaire négatif : -b est la même rotation, on passe par le chemin le plus court
+    #[test]
+    fn slerp_shortest_path() {
+        let y = Vec3::new(0.0, 1.0, 0.0);
+        let a = Quat::from_axis_angle(y, 0.0);
+        let b = Quat::from_axis_angle(y, 0.5);
+        let neg = Quat::new(-b.x, -b.y, -b.z, -b.w);
+        assert!(a.dot(neg) < 0.0);
+        let mid = a.slerp(neg, 0.5);
+        assert!(close_quat(mid, Quat::from_axis_angle(y, 0.25)));
+
+        // Sans le retournement, le milieu serait une rotation de 180° - 0.25 rad
+        let v = Vec3::new(1.0, 0.0, 0.0);
+        assert!(close_vec(mid.rotate_vec3(v), Quat::from_axis_angle(y, 0.25).rotate_vec3(v)));
+    }
+
+    // Quaternions presque égaux : interpolation linéaire normalisée, sans NaN
+    #[test]
+    fn slerp_near_parallel() {
+        let axis = Vec3::new(0.0, 0.0, 1.0);
+        let a = Quat::from_axis_angle(axis, 0.3);
+        let b = Quat::from_axis_angle(axis, 0.3 + 1e-4);
+        for t in [0.0, 0.3, 1.0] {
+            let q = a.slerp(b, t);
+            assert!(q.x.is_finite() && q.y.is_finite() && q.z.is_finite() && q.w.is_finite());
+            assert!(close_quat(q, Quat::from_axis_angle(axis, 0.3 + 1e-4 * t)));
+        }
+        let q = a.slerp(a, 0.5);
+        assert!(close_quat(q, a));
+    }
+
+    #[test]
+    fn axis_angle_rotation() {
+        let q = Quat::from_axis_angle(Vec3::new(0.0, 0.0, 2.0), std::f32::consts::FRAC_PI_2);
+        assert!(close_vec(q.rotate_vec3(Vec3::new(1.0, 0.0, 0.0)), Vec3::new(0.0, 1.0, 0.0)));
+        // Même rotation en matrice
+        let p = q.to_mat4().transform_point((1.0, 2.0, 3.0));
+        assert!(close_point(p, (-2.0, 1.0, 3.0)));
+    }
+
+    // Euler -> quaternion -> Euler : même rotation (mêmes angles hors blocage de cardan),
+    // et même rotation que Transform
+    #[test]
+    fn euler_round_trip() {
+        let v = Vec3::new(0.3, -0.7, 1.1);
+        for (ax, ay, az) in [(0.3, -1.2, 0.7), (-1.0, 2.5, -3.0), (0.0, 0.0, 0.0), (1.2, 0.1, -0.4)] {
+            let q = Quat::from_euler(ax, ay, az);
+            let (rx, ry, rz) = q.to_euler();
+            assert!(close_quat(Quat::from_euler(rx, ry, rz), q), "{:?}", (rx, ry, rz));
+            assert!((rx - ax).abs() < 1e-4 && (ry - ay).abs() < 1e-4 && (rz - az).abs() < 1e-4, "{:?}", (rx, ry, rz));
+
+            let t = Transform { scale: 1.0, rotation: (ax, ay, az), translation: (0.0, 0.0, 0.0) };
+            assert!(close_vec(q.rotate_vec3(v), Transform::transform_vec3(v, &t).mul(v.length())));
+        }
+
+        // Blocage de cardan (ax = ±90°) : les angles changent, la rotation non
+        for ax in [std::f32::consts::FRAC_PI_2, -std::f32::consts::FRAC_PI_2] {
+            let q = Quat::from_euler(ax, 0.4, 0.3);
+            let (rx, ry, rz) = q.to_euler();
+            assert!(close_vec(Quat::from_euler(rx, ry, rz).rotate_vec3(v), q.rotate_vec3(v)), "{:?}", (rx, ry, rz));
+        }
+    }
 }
@@ -0,0 +1,289 @@
+// Maillage indexé commun à tous les moteurs de rendu : modèles wavefront,
+// et modèles intégrés (penger, cube). Les normales sont toujours présentes :
+// celles qui manquent sont calculées (lissées) à la construction.
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::math_3d::utils::compute_smooth_normals;
+use crate::math_3d::{Mat3, Mat4, Material, MaterialRaytrace, Vec3};
+use crate::mtl::{FaceMaterials, MtlError, MtlMaterial};
+use crate::texture::Texture;
+use crate::{cube, penger};
+
+#[derive(Debug)]
+pub enum LoadError {
+    Obj(PathBuf, wavefront::Error),
+    Mtl(MtlError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Obj(path, e) => write!(f, "can't load model {}: {}", path.display(), e),
+            LoadError::Mtl(e) => write!(f, "can't load materials: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+// Un coin de face : indices dans positions / normals / uvs
+#[derive(Debug, Clone, Copy)]
+pub struct FaceVertex {
+    pub position: usize,
+    pub normal: usize,
+    pub uv: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Face {
+    pub vertices: Vec<FaceVertex>,
+    // Index dans Mesh::materials, None = matériau par défaut du rendu
+    pub material: Option<usize>,
+}
+
+// Triangle "à plat", prêt pour les rasteriseurs et le raytracer
+#[derive(Debug, Clone, Copy)]
+pub struct Triangle {
+    pub positions: [Vec3; 3],
+    pub normals: [Vec3; 3],
+    pub uvs: Option<[(f32, f32); 3]>,
+    pub material: Option<usize>,
+}
+
+impl Triangle {
+    // normal_matrix : model_matrix.normal_matrix(), calculée une fois pour tout l'objet
+    pub fn transform(&self, model_matrix: &Mat4, normal_matrix: &Mat3) -> Triangle {
+        let point = |p: Vec3| Vec3::from_point3d(model_matrix.transform_point(p.to_point3d()));
+        let normal = |n: Vec3| normal_matrix.mul_vec3(n).normalize();
+        Triangle {
+            positions: self.positions.map(point),
+            normals: self.normals.map(normal),
+            ..*self
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<(f32, f32)>,
+    pub faces: Vec<Face>,
+    // Matériaux .mtl et leur texture map_Kd (même index)
+    pub materials: Vec<MtlMaterial>,
+    pub textures: Vec<Option<Texture>>,
+}
+
+#[allow(dead_code)]
+impl Mesh {
+    // Modèle wavefront (avec ses .mtl si with_mtl), ou modèle intégré ("builtin:penger", "builtin:cube").
+    // Les .mtl ou textures illisibles sont renvoyés à part pour que l'appelant puisse avertir sans échouer.
+    pub fn load(path: &Path, with_mtl: bool, crease_angle: f32) -> Result<(Mesh, Vec<MtlError>), LoadError> {
+        match path.to_str() {
+            Some("builtin:penger") => return Ok((Mesh::penger(crease_angle), Vec::new())),
+            Some("builtin:cube") => return Ok((Mesh::cube(crease_angle), Vec::new())),
+            _ => {}
+        }
+
+        // Lecture d'un modele wavefront
+        let model = wavefront::Obj::from_file(path).map_err(|e| LoadError::Obj(path.to_path_buf(), e))?;
+
+        // Matériaux .mtl : un fichier manquant n'empêche pas le rendu
+        let (face_materials, warnings) = FaceMaterials::load(path, with_mtl).map_err(LoadError::Mtl)?;
+
+        Ok((Mesh::from_obj(&model, face_materials, crease_angle), warnings))
+    }
+
+    pub fn from_obj(model: &wavefront::Obj, face_materials: FaceMaterials, crease_angle: f32) -> Mesh {
+        let mut mesh = Mesh {
+            positions: model.positions().iter().map(|p| Vec3::new(p[0], p[1], p[2])).collect(),
+            normals: model.normals().iter().map(|n| Vec3::new(n[0], n[1], n[2])).collect(),
+            uvs: model.uvs().iter().map(|t| (t[0], t[1])).collect(),
+            faces: Vec::new(),
+            materials: Vec::new(),
+            textures: Vec::new(),
+        };
+
+        // Le crate range les polygones par objet et par groupe, dans un ordre quelconque ; leurs
+        // sommets se suivent en revanche dans model.vertices() comme les lignes "f" du fichier.
+        // Normales du fichier (vn) quand elles existent
+        let mut missing: Vec<Vec<bool>> = Vec::new();
+        let mut obj_vertices = model.vertices();
+        for face in &face_materials.faces {
+            let polygon: Vec<wavefront::Vertex> = obj_vertices.by_ref().take(face.vertex_count).collect();
+            let vertices: Vec<FaceVertex> = polygon
+                .iter()
+                .map(|v| FaceVertex {
+                    position: v.position_index(),
+                    normal: v.normal_index().unwrap_or(0),
+                    uv: v.uv_index(),
+                })
+                .collect();
+            missing.push(polygon.iter().map(|v| v.normal_index().is_none()).collect());
+            mesh.faces.push(Face { vertices, material: face.material });
+        }
+
+        mesh.fill_missing_normals(&missing, crease_angle);
+        mesh.materials = face_materials.materials;
+        mesh.textures = face_materials.textures;
+        mesh
+    }
+
+    // Points et faces constants (penger.rs, cube.rs). Les faces de moins de 3 sommets sont ignorées.
+    pub fn from_points<'a, I>(points: &[(f64, f64, f64)], faces: I, crease_angle: f32) -> Mesh
+    where
+        I: IntoIterator<Item = &'a [usize]>,
+    {
+        let mut mesh = Mesh {
+            positions: points.iter().map(|p| Vec3::new(p.0 as f32, p.1 as f32, p.2 as f32)).collect(),
+            ..Mesh::default()
+        };
+        for f in faces.into_iter().filter(|f| f.len() >= 3) {
+            let vertices = f.iter().map(|&position| FaceVertex { position, normal: 0, uv: None }).collect();
+            mesh.faces.push(Face { vertices, material: None });
+        }
+
+        let missing: Vec<Vec<bool>> = mesh.faces.iter().map(|f| vec![true; f.vertices.len()]).collect();
+        mesh.fill_missing_normals(&missing, crease_angle);
+        mesh
+    }
+
+    pub fn penger(crease_angle: f32) -> Mesh {
+        Mesh::from_points(&penger::POINTS, penger::FACES.iter().map(|f| &f[..]), crease_angle)
+    }
+
+    // cube.rs décrit un fil de fer : deux carrés reliés par 4 arêtes.
+    // Chaque paire d'arêtes consécutives donne une face latérale.
+    pub fn cube(crease_angle: f32) -> Mesh {
+        let edges: Vec<&[usize]> = cube::FACES.iter().copied().filter(|f| f.len() == 2).collect();
+        let sides: Vec<Vec<usize>> = (0..edges.len())
+            .map(|i| {
+                let (a, b) = (edges[i], edges[(i + 1) % edges.len()]);
+                vec![a[0], b[0], b[1], a[1]]
+            })
+            .collect();
+
+        // Le cube est convexe : toutes les faces sont orientées vers l'extérieur
+        let center = cube::POINTS.iter().fold((0.0, 0.0, 0.0), |c, p| (c.0 + p.0, c.1 + p.1, c.2 + p.2));
+        let n = cube::POINTS.len() as f64;
+        let center = Vec3::new((center.0 / n) as f32, (center.1 / n) as f32, (center.2 / n) as f32);
+        let point = |i: usize| {
+            let p = cube::POINTS[i];
+            Vec3::new(p.0 as f32, p.1 as f32, p.2 as f32)
+        };
+        let faces: Vec<Vec<usize>> = cube::FACES
+            .iter()
+            .filter(|f| f.len() >= 3)
+            .map(|f| f.to_vec())
+            .chain(sides)
+            .map(|mut f| {
+                let normal = point(f[1]).sub(point(f[0])).cross(point(f[2]).sub(point(f[0])));
+                if normal.dot(point(f[0]).sub(center)) < 0.0 {
+                    f.reverse();
+                }
+                f
+            })
+            .collect();
+
+        Mesh::from_points(&cube::POINTS, faces.iter().map(|f| &f[..]), crease_angle)
+    }
+
+    // Complète les normales absentes (missing[face][coin]) par des normales lissées
+    fn fill_missing_normals(&mut self, missing: &[Vec<bool>], crease_angle: f32) {
+        if !missing.iter().flatten().any(|&m| m) {
+            return;
+        }
+        let faces: Vec<Vec<usize>> = self.faces.iter().map(|f| f.vertices.iter().map(|v| v.position).collect()).collect();
+        let smooth = compute_smooth_normals(&self.positions, &faces, crease_angle);
+
+        for ((face, smooth), missing) in self.faces.iter_mut().zip(smooth).zip(missing) {
+            for ((v, n), &m) in face.vertices.iter_mut().zip(smooth).zip(missing) {
+                if m {
+                    v.normal = self.normals.len();
+                    self.normals.push(n);
+                }
+            }
+        }
+    }
+
+    // Triangles en éventail, dans l'ordre des faces
+    pub fn triangles(&self) -> impl Iterator<Item = Triangle> + '_ {
+        self.faces.iter().flat_map(move |face| {
+            let v = &face.vertices;
+            (1..v.len().saturating_sub(1)).map(move |i| {
+                let corners = [v[0], v[i], v[i + 1]];
+                let uvs = match corners.map(|c| c.uv) {
+                    [Some(a), Some(b), Some(c)] => Some([self.uvs[a], self.uvs[b], self.uvs[c]]),
+                    _ => None,
+                };
+                Triangle {
+                    positions: corners.map(|c| self.positions[c.position]),
+                    normals: corners.map(|c| self.normals[c.normal]),
+                    uvs,
+                    material: face.material,
+                }
+            })
+        })
+    }
+
+    // Matériau de rasterisation d'un triangle, sinon celui par défaut
+    pub fn material(&self, id: Option<usize>, default: &Material) -> Material {
+        id.map_or(*default, |i| self.materials[i].to_material())
+    }
+
+    pub fn texture(&self, id: Option<usize>) -> Option<&Texture> {
+        self.textures.get(id?)?.as_ref()
+    }
+
+    // Matériaux raytrace indexés par material_index : 0 = défaut, i + 1 = materials[i]
+    pub fn raytrace_materials(&self, default: &MaterialRaytrace) -> Vec<MaterialRaytrace> {
+        std::iter::once(*default)
+            .chain(self.materials.iter().map(|m| m.to_raytrace()))
+            .collect()
+    }
+
+    // Taille de la liste renvoyée par raytrace_materials
+    pub fn raytrace_material_count(&self) -> usize {
+        1 + self.materials.len()
+    }
+
+    // Textures indexées comme raytrace_materials (pas de texture pour le matériau par défaut)
+    pub fn raytrace_textures(&self) -> Vec<Option<&Texture>> {
+        std::iter::once(None).chain(self.textures.iter().map(|t| t.as_ref())).collect()
+    }
+
+    pub fn material_index(id: Option<usize>) -> usize {
+        id.map_or(0, |i| i + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // La première face porte des normales vn (volontairement différentes de la géométrie),
+    // la seconde n'en a pas : seules ses normales sont calculées
+    #[test]
+    fn obj_normals_untouched() {
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nvn 1 0 0\nvn 0 1 0\n\
+                   f 1//1 2//1 3//2\nf 2 4 3\n";
+        let path = std::env::temp_dir().join(format!("sixel-3d-normals-{}.obj", std::process::id()));
+        std::fs::write(&path, obj).unwrap();
+        let loaded = Mesh::load(&path, false, 60f32.to_radians());
+        std::fs::remove_file(&path).unwrap();
+        let (mesh, warnings) = loaded.unwrap();
+        assert!(warnings.is_empty());
+
+        let normal = |face: usize, corner: usize| mesh.normals[mesh.faces[face].vertices[corner].normal];
+        let close = |a: Vec3, b: Vec3| a.sub(b).length() < 1e-6;
+        assert!(close(normal(0, 0), Vec3::new(1.0, 0.0, 0.0)));
+        assert!(close(normal(0, 1), Vec3::new(1.0, 0.0, 0.0)));
+        assert!(close(normal(0, 2), Vec3::new(0.0, 1.0, 0.0)));
+        for corner in 0..3 {
+            assert!(close(normal(1, corner), Vec3::new(0.0, 0.0, 1.0)), "{:?}", normal(1, corner));
+        }
+        // Les normales du fichier sont gardées telles quelles, les calculées ajoutées après
+        assert_eq!(mesh.normals.len(), 2 + 3);
+    }
+}
@@ -0,0 +1,428 @@
+// Matériaux wavefront (.mtl) et association face -> matériau.
+// Le crate wavefront ignore mtllib/usemtl : on relit le .obj pour retrouver,
+// pour chaque face (dans l'ordre du fichier), le matériau actif au moment de sa déclaration.
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::math_3d::{Material, MaterialRaytrace};
+use crate::texture::{Texture, TextureError};
+
+#[derive(Debug)]
+pub enum MtlError {
+    Io(PathBuf, std::io::Error),
+    // (fichier, ligne, message)
+    Parse(PathBuf, usize, String),
+    Texture(TextureError),
+}
+
+impl fmt::Display for MtlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MtlError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            MtlError::Parse(path, line, msg) => write!(f, "{}:{}: {}", path.display(), line, msg),
+            MtlError::Texture(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for MtlError {}
+
+// Un matériau tel que décrit dans le .mtl
+#[derive(Debug, Clone)]
+pub struct MtlMaterial {
+    pub name: String,
+    pub ka: (f32, f32, f32),
+    pub kd: (f32, f32, f32),
+    pub ks: (f32, f32, f32),
+    pub ns: f32,
+    pub d: f32,     // Opacité (dissolve), 1.0 = opaque
+    pub ni: f32,    // Indice de réfraction
+    pub illum: u32, // Modèle d'éclairage
+    pub ke: (f32, f32, f32), // Émission
+    pub map_kd: Option<PathBuf>, // Texture diffuse, chemin résolu par rapport au .mtl
+}
+
+impl MtlMaterial {
+    fn new(name: &str) -> Self {
+        // Valeurs par défaut de la spécification
+        Self {
+            name: name.to_string(),
+            ka: (0.2, 0.2, 0.2),
+            kd: (0.8, 0.8, 0.8),
+            ks: (1.0, 1.0, 1.0),
+            ns: 0.0,
+            d: 1.0,
+            ni: 1.0,
+            illum: 2,
+            ke: (0.0, 0.0, 0.0),
+            map_kd: None,
+        }
+    }
+
+    pub fn to_material(&self) -> Material {
+        Material {
+            ka: self.ka,
+            kd: self.kd,
+            // illum 0 et 1 : pas de reflet spéculaire
+            ks: if self.illum < 2 { (0.0, 0.0, 0.0) } else { self.ks },
+            ns: self.ns.max(1.0),
+        }
+    }
+
+    pub fn to_raytrace(&self) -> MaterialRaytrace {
+        // illum 3 à 9 : réflexion "raytrace", Ks sert de couleur de miroir
+        let reflectivity = if (3..=9).contains(&self.illum) {
+            ((self.ks.0 + self.ks.1 + self.ks.2) / 3.0).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        MaterialRaytrace {
+            material: self.to_material(),
+            reflectivity,
+            transparency: (1.0 - self.d).clamp(0.0, 1.0),
+            refractive_index: self.ni,
+            emission: self.ke,
+        }
+    }
+}
+
+fn parse_floats(path: &Path, line: usize, key: &str, terms: &[&str], n: usize) -> Result<Vec<f32>, MtlError> {
+    let values: Vec<f32> = terms
+        .iter()
+        .take(n)
+        .map(|t| t.parse::<f32>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| MtlError::Parse(path.to_path_buf(), line, format!("{}: {}", key, e)))?;
+    if values.len() < n {
+        return Err(MtlError::Parse(path.to_path_buf(), line, format!("{}: {} valeur(s) attendue(s)", key, n)));
+    }
+    Ok(values)
+}
+
+fn parse_rgb(path: &Path, line: usize, key: &str, terms: &[&str]) -> Result<(f32, f32, f32), MtlError> {
+    if terms.first().is_some_and(|t| t.starts_with(|c: char| c.is_ascii_alphabetic())) {
+        // "Kd spectral ..." / "Kd xyz ..." non gérés
+        return Err(MtlError::Parse(path.to_path_buf(), line, format!("{}: seul le format RGB est géré", key)));
+    }
+    // "Kd 0.5" est équivalent à "Kd 0.5 0.5 0.5"
+    let v = parse_floats(path, line, key, terms, 1)?;
+    if terms.len() >= 3 {
+        let v = parse_floats(path, line, key, terms, 3)?;
+        Ok((v[0], v[1], v[2]))
+    } else {
+        Ok((v[0], v[0], v[0]))
+    }
+}
+
+// "map_Kd -s 1 1 1 -o 0 0 0 vieux bois.tga" : les options et leurs valeurs sont ignorées, le
+// reste est le nom du fichier, espaces compris
+fn map_file(args: &[&str]) -> Option<String> {
+    let mut rest = args;
+    while let Some((option, tail)) = rest.split_first().filter(|(t, _)| t.starts_with('-')) {
+        // -imfchan r|g|b|m|l|z ; les autres options prennent des nombres ou on/off
+        let values = if *option == "-imfchan" {
+            tail.len().min(1)
+        } else {
+            tail.iter().take_while(|v| v.parse::<f32>().is_ok() || matches!(**v, "on" | "off")).count()
+        };
+        rest = &tail[values..];
+    }
+    (!rest.is_empty()).then(|| rest.join(" "))
+}
+
+pub fn parse_mtl(path: &Path, text: &str) -> Result<Vec<MtlMaterial>, MtlError> {
+    let mut materials: Vec<MtlMaterial> = Vec::new();
+
+    for (i, raw) in text.lines().enumerate() {
+        let line_num = i + 1;
+        let line = raw.split('#').next().unwrap_or("").trim();
+        let terms: Vec<&str> = line.split_ascii_whitespace().collect();
+        let Some((&key, args)) = terms.split_first() else { continue };
+
+        if key == "newmtl" {
+            let name = args.join(" ");
+            if name.is_empty() {
+                return Err(MtlError::Parse(path.to_path_buf(), line_num, "newmtl: nom manquant".to_string()));
+            }
+            materials.push(MtlMaterial::new(&name));
+            continue;
+        }
+
+        let Some(current) = materials.last_mut() else {
+            return Err(MtlError::Parse(path.to_path_buf(), line_num, format!("{} avant tout newmtl", key)));
+        };
+
+        match key {
+            "Ka" => current.ka = parse_rgb(path, line_num, key, args)?,
+            "Kd" => current.kd = parse_rgb(path, line_num, key, args)?,
+            "Ks" => current.ks = parse_rgb(path, line_num, key, args)?,
+            "Ke" => current.ke = parse_rgb(path, line_num, key, args)?,
+            "Ns" => current.ns = parse_floats(path, line_num, key, args, 1)?[0],
+            "Ni" => current.ni = parse_floats(path, line_num, key, args, 1)?[0],
+            "d" => {
+                // "d -halo 0.5" : on ignore l'option
+                let args: Vec<&str> = args.iter().copied().filter(|a| *a != "-halo").collect();
+                current.d = parse_floats(path, line_num, key, &args, 1)?[0];
+            }
+            "Tr" => current.d = 1.0 - parse_floats(path, line_num, key, args, 1)?[0],
+            "illum" => {
+                current.illum = args
+                    .first()
+                    .and_then(|v| v.parse().ok())
+                    .ok_or_else(|| MtlError::Parse(path.to_path_buf(), line_num, "illum: entier attendu".to_string()))?;
+            }
+            "map_Kd" => {
+                let file = map_file(args).ok_or_else(|| {
+                    MtlError::Parse(path.to_path_buf(), line_num, "map_Kd: fichier manquant".to_string())
+                })?;
+                current.map_kd = Some(path.parent().unwrap_or(Path::new("")).join(file));
+            }
+            // Le reste (Tf, map_Ks, bump, ...) n'est pas (encore) utilisé
+            _ => {}
+        }
+    }
+
+    Ok(materials)
+}
+
+pub fn load_mtl(path: &Path) -> Result<Vec<MtlMaterial>, MtlError> {
+    let text = std::fs::read_to_string(path).map_err(|e| MtlError::Io(path.to_path_buf(), e))?;
+    parse_mtl(path, &text)
+}
+
+// Matériau de chaque face du .obj, dans l'ordre des lignes "f"
+#[derive(Debug, Clone, Copy)]
+pub struct ObjFace {
+    pub vertex_count: usize,
+    pub material: Option<usize>,
+}
+
+// Matériaux d'un modèle et matériau de chaque face
+#[derive(Debug, Default)]
+pub struct FaceMaterials {
+    pub materials: Vec<MtlMaterial>,
+    // Texture map_Kd de chaque matériau (même index que materials)
+    pub textures: Vec<Option<Texture>>,
+    // Toutes les faces du fichier : deux faces sur les mêmes sommets gardent chacune le leur
+    pub faces: Vec<ObjFace>,
+}
+
+impl FaceMaterials {
+    // Lit les directives mtllib/usemtl du .obj (ignorées sans with_mtl). Les .mtl sont cherchés
+    // à côté du .obj. Les .mtl illisibles et les usemtl inconnus sont renvoyés à part pour que
+    // l'appelant puisse avertir sans échouer.
+    pub fn load(obj_path: &Path, with_mtl: bool) -> Result<(FaceMaterials, Vec<MtlError>), MtlError> {
+        let text = std::fs::read_to_string(obj_path).map_err(|e| MtlError::Io(obj_path.to_path_buf(), e))?;
+        let (mut result, mut warnings) = FaceMaterials::parse(obj_path, &text, with_mtl, load_mtl);
+
+        // Une texture illisible laisse le matériau sans texture
+        result.textures = result
+            .materials
+            .iter()
+            .map(|m| {
+                let path = m.map_kd.as_ref()?;
+                Texture::load(path).map_err(|e| warnings.push(MtlError::Texture(e))).ok()
+            })
+            .collect();
+
+        Ok((result, warnings))
+    }
+
+    // Texte du .obj ; read_mtl lit un .mtl désigné par mtllib. Les textures ne sont pas chargées.
+    fn parse(
+        obj_path: &Path,
+        text: &str,
+        with_mtl: bool,
+        mut read_mtl: impl FnMut(&Path) -> Result<Vec<MtlMaterial>, MtlError>,
+    ) -> (FaceMaterials, Vec<MtlError>) {
+        let dir = obj_path.parent().unwrap_or(Path::new(""));
+
+        let mut result = FaceMaterials::default();
+        let mut warnings = Vec::new();
+        let mut by_name: HashMap<String, usize> = HashMap::new();
+        // usemtl peut précéder mtllib : les noms (ligne, nom) sont résolus à la fin,
+        // pending associe chaque face à son usemtl
+        let mut usemtl: Vec<(usize, String)> = Vec::new();
+        let mut pending: Vec<(usize, usize)> = Vec::new();
+        let mut mtl_missing = false;
+
+        for (i, raw) in text.lines().enumerate() {
+            let mut terms = raw.split_ascii_whitespace();
+            match terms.next() {
+                Some("mtllib") if with_mtl => {
+                    for file in terms {
+                        match read_mtl(&dir.join(file)) {
+                            Ok(list) => {
+                                for m in list {
+                                    by_name.insert(m.name.clone(), result.materials.len());
+                                    result.materials.push(m);
+                                }
+                            }
+                            Err(e) => {
+                                warnings.push(e);
+                                mtl_missing = true;
+                            }
+                        }
+                    }
+                }
+                Some("usemtl") if with_mtl => {
+                    let name: Vec<&str> = terms.collect();
+                    usemtl.push((i + 1, name.join(" ")));
+                }
+                Some("f") => {
+                    if !usemtl.is_empty() {
+                        pending.push((result.faces.len(), usemtl.len() - 1));
+                    }
+                    result.faces.push(ObjFace { vertex_count: terms.count(), material: None });
+                }
+                _ => {}
+            }
+        }
+
+        // Nom absent des .mtl : la face garde le matériau par défaut. Pas d'avertissement si un
+        // .mtl n'a pas pu être lu, le nom y est sans doute.
+        let resolved: Vec<Option<usize>> = usemtl
+            .iter()
+            .map(|(line, name)| {
+                let material = by_name.get(name).copied();
+                if material.is_none() && !mtl_missing {
+                    let msg = format!("usemtl: matériau '{}' introuvable", name);
+                    warnings.push(MtlError::Parse(obj_path.to_path_buf(), *line, msg));
+                }
+                material
+            })
+            .collect();
+        for (face, directive) in pending {
+            result.faces[face].material = resolved[directive];
+        }
+
+        (result, warnings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: (f32, f32, f32), b: (f32, f32, f32)) -> bool {
+        (a.0 - b.0).abs() < 1e-6 && (a.1 - b.1).abs() < 1e-6 && (a.2 - b.2).abs() < 1e-6
+    }
+
+    #[test]
+    fn parse_material_keys() {
+        let text = "# matériaux\n\
+                    newmtl bois clair\n\
+                    Ka 0.1 0.2 0.3\n\
+                    Kd 0.5\n\
+                    Ks 1 0.5 0.25 # reflet\n\
+                    Ns 96\n\
+                    d -halo 0.75\n\
+                    illum 3\n\
+                    map_Kd -s 1 1 1 -o 0 0 0 -clamp on -imfchan r vieux bois.tga\n\
+                    \n\
+                    newmtl verre\n\
+                    Tr 0.9\n\
+                    Ni 1.5\n\
+                    Ke 0 0 2\n\
+                    bump ignoré.png\n";
+        let materials = parse_mtl(Path::new("models/test.mtl"), text).unwrap();
+        assert_eq!(materials.len(), 2);
+
+        let m = &materials[0];
+        assert_eq!(m.name, "bois clair");
+        assert!(close(m.ka, (0.1, 0.2, 0.3)));
+        assert!(close(m.kd, (0.5, 0.5, 0.5)));
+        assert!(close(m.ks, (1.0, 0.5, 0.25)));
+        assert_eq!(m.ns, 96.0);
+        assert_eq!(m.d, 0.75);
+        assert_eq!(m.illum, 3);
+        assert_eq!(m.map_kd.as_deref(), Some(Path::new("models/vieux bois.tga")));
+
+        let m = &materials[1];
+        assert!((m.d - 0.1).abs() < 1e-6);
+        assert_eq!(m.ni, 1.5);
+        assert!(close(m.ke, (0.0, 0.0, 2.0)));
+        // Valeurs par défaut
+        assert!(close(m.kd, (0.8, 0.8, 0.8)));
+        assert_eq!(m.illum, 2);
+        assert!(m.map_kd.is_none());
+    }
+
+    fn parse_error(text: &str) -> (usize, String) {
+        match parse_mtl(Path::new("test.mtl"), text) {
+            Err(MtlError::Parse(_, line, msg)) => (line, msg),
+            other => panic!("erreur de lecture attendue : {:?}", other.map(|m| m.len())),
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse_error("Kd 1 1 1\n").0, 1);
+        let (line, msg) = parse_error("newmtl a\nKd 1 x 1\n");
+        assert_eq!(line, 2);
+        assert!(msg.starts_with("Kd"), "{}", msg);
+        assert_eq!(parse_error("newmtl a\nNs\n").0, 2);
+        assert_eq!(parse_error("newmtl a\n\nillum deux\n").0, 3);
+        assert_eq!(parse_error("newmtl a\nKd spectral foo.spd\n").0, 2);
+        assert_eq!(parse_error("newmtl a\nmap_Kd -s 1 1 1\n").0, 2);
+        assert_eq!(parse_error("newmtl\n").0, 1);
+    }
+
+    fn materials(text: &str) -> Result<Vec<MtlMaterial>, MtlError> {
+        parse_mtl(Path::new("m.mtl"), text)
+    }
+
+    #[test]
+    fn face_materials() {
+        // usemtl avant mtllib, faces sans usemtl, nom inconnu
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\n\
+                   f 1 2 3\n\
+                   usemtl rouge\n\
+                   mtllib m.mtl\n\
+                   f 1 2 3 4\n\
+                   usemtl bleu\n\
+                   f 2 4 3\n\
+                   f 1 2 3\n\
+                   usemtl vert\n\
+                   f 1 3 4\n";
+        let mut read = Vec::new();
+        let (faces, warnings) = FaceMaterials::parse(Path::new("models/a.obj"), obj, true, |path| {
+            read.push(path.to_path_buf());
+            materials("newmtl bleu\nKd 0 0 1\nnewmtl rouge\nKd 1 0 0\n")
+        });
+        assert_eq!(read, [Path::new("models/m.mtl")]);
+        assert_eq!(faces.materials.len(), 2);
+        let mapping: Vec<(usize, Option<usize>)> = faces.faces.iter().map(|f| (f.vertex_count, f.material)).collect();
+        assert_eq!(mapping, [(3, None), (4, Some(1)), (3, Some(0)), (3, Some(0)), (3, None)]);
+
+        // Seul le nom inconnu est signalé, avec sa ligne dans le .obj
+        assert_eq!(warnings.len(), 1);
+        match &warnings[0] {
+            MtlError::Parse(path, line, msg) => {
+                assert_eq!(path, Path::new("models/a.obj"));
+                assert_eq!(*line, 12);
+                assert!(msg.contains("vert"), "{}", msg);
+            }
+            e => panic!("{}", e),
+        }
+
+        // Sans les .mtl : aucune lecture, aucun matériau, aucun avertissement
+        let (faces, warnings) = FaceMaterials::parse(Path::new("a.obj"), obj, false, |_| panic!());
+        assert!(faces.materials.is_empty() && warnings.is_empty());
+        assert!(faces.faces.iter().all(|f| f.material.is_none()));
+    }
+
+    // Un .mtl illisible est signalé une fois, pas chaque usemtl qui y renvoyait
+    #[test]
+    fn missing_mtl_file() {
+        let obj = "mtllib absent.mtl\nusemtl rouge\nf 1 2 3\nusemtl bleu\nf 1 2 3\n";
+        let (faces, warnings) = FaceMaterials::parse(Path::new("a.obj"), obj, true, |path| {
+            Err(MtlError::Io(path.to_path_buf(), std::io::ErrorKind::NotFound.into()))
+        });
+        assert_eq!(faces.faces.len(), 2);
+        assert!(faces.faces.iter().all(|f| f.material.is_none()));
+        assert_eq!(warnings.len(), 1);
+        assert!(matches!(warnings[0], MtlError::Io(..)));
+    }
+}
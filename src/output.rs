@@ -0,0 +1,230 @@
+// Sortie des images vers le terminal, ou vers un fichier (-o, --sequence) : sixel (encodeur
+// choisi par --sixel-encoder), protocole graphique de kitty, images en ligne d'iTerm2, ou
+// caractères colorés quand le terminal n'affiche pas d'images
+use std::fs::File;
+use std::io::{Write, stdout};
+use std::path::Path;
+
+use clap::ValueEnum;
+
+use crate::AppError;
+use crate::cli::RenderArgs;
+use crate::frame_buffer::FrameBuffer;
+use crate::terminal::Capabilities;
+use crate::text::{self, TextMode};
+
+// Largeur des modes texte quand la sortie n'est pas un terminal
+const TEXT_COLUMNS: usize = 80;
+
+#[cfg(not(any(feature = "libsixel", feature = "native-sixel")))]
+compile_error!("il faut au moins un encodeur sixel : feature libsixel ou native-sixel");
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Protocol {
+    Auto,
+    Sixel,
+    Kitty,
+    Iterm,
+    Blocks,
+    Braille,
+    Ascii,
+}
+
+impl Protocol {
+    // Auto : d'après les variables d'environnement du terminal, puis sa réponse à DA1 si
+    // elle est connue ; sixel à défaut
+    pub fn resolve(self, capabilities: Option<&Capabilities>) -> Protocol {
+        match self {
+            Protocol::Auto => detect(|name| std::env::var(name).ok(), capabilities),
+            protocol => protocol,
+        }
+    }
+}
+
+fn detect(var: impl Fn(&str) -> Option<String>, capabilities: Option<&Capabilities>) -> Protocol {
+    // Texte à défaut d'images : demi-blocs si le terminal transmet les couleurs 24 bits,
+    // braille sinon
+    let text = || match var("COLORTERM").as_deref() {
+        Some("truecolor" | "24bit") => Protocol::Blocks,
+        _ => Protocol::Braille,
+    };
+    let term = var("TERM").unwrap_or_default();
+    let program = var("TERM_PROGRAM").unwrap_or_default();
+    if var("KITTY_WINDOW_ID").is_some() || term.contains("kitty") || program == "WezTerm" || program == "ghostty" {
+        Protocol::Kitty
+    } else if program == "iTerm.app" || var("LC_TERMINAL").as_deref() == Some("iTerm2") {
+        Protocol::Iterm
+    } else if var("TMUX").is_some() || term.starts_with("screen") || term.starts_with("tmux") {
+        // Le multiplexeur ne laisse pas passer les images
+        text()
+    } else if term == "linux" {
+        // Console du noyau : 16 couleurs et police sans braille
+        Protocol::Ascii
+    } else if capabilities.is_some_and(|c| !c.sixel) {
+        text()
+    } else {
+        Protocol::Sixel
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum SixelEncoder {
+    Libsixel,
+    Native,
+}
+
+impl SixelEncoder {
+    // libsixel quand il est compilé, sinon l'encodeur natif
+    #[cfg(feature = "libsixel")]
+    pub const DEFAULT: SixelEncoder = SixelEncoder::Libsixel;
+    #[cfg(not(feature = "libsixel"))]
+    pub const DEFAULT: SixelEncoder = SixelEncoder::Native;
+
+    // Présent dans ce binaire (features cargo)
+    pub fn available(self) -> bool {
+        match self {
+            SixelEncoder::Libsixel => cfg!(feature = "libsixel"),
+            SixelEncoder::Native => cfg!(feature = "native-sixel"),
+        }
+    }
+}
+
+// Sans fichier, les images vont sur la sortie standard ; libsixel gère lui-même son fichier
+pub enum Sink {
+    #[cfg(feature = "libsixel")]
+    Libsixel(sixel_rs::encoder::Encoder),
+    #[cfg(feature = "native-sixel")]
+    Native { encoder: crate::sixel::Encoder, file: Option<File> },
+    Kitty { zlib: bool, file: Option<File> },
+    Iterm { file: Option<File> },
+    Text { mode: TextMode, columns: usize, file: Option<File> },
+}
+
+impl Sink {
+    // Moins de 256 registres de couleur annoncés par le terminal : palette sixel réduite d'autant
+    pub fn new(args: &RenderArgs, capabilities: Option<&Capabilities>) -> Result<Sink, AppError> {
+        let colors = capabilities.and_then(|c| c.color_registers).filter(|&n| n < 256).map(|n| n.max(2));
+        let mut sink = match (args.format.protocol.resolve(capabilities), args.format.sixel_encoder) {
+            (Protocol::Kitty, _) => Sink::Kitty { zlib: args.format.kitty_zlib, file: None },
+            (Protocol::Iterm, _) => Sink::Iterm { file: None },
+            (protocol @ (Protocol::Blocks | Protocol::Braille | Protocol::Ascii), _) => {
+                let mode = match protocol {
+                    Protocol::Blocks => TextMode::Blocks,
+                    Protocol::Braille => TextMode::Braille,
+                    _ => TextMode::Ascii,
+                };
+                let columns = args.format.columns.or_else(text::terminal_columns).unwrap_or(TEXT_COLUMNS);
+                Sink::Text { mode, columns, file: None }
+            }
+            #[cfg(feature = "libsixel")]
+            (_, SixelEncoder::Libsixel) => {
+                let encoder = sixel_rs::encoder::Encoder::new()?;
+                if let Some(colors) = colors {
+                    encoder.set_num_colors(colors as u8)?;
+                }
+                Sink::Libsixel(encoder)
+            }
+            #[cfg(feature = "native-sixel")]
+            (_, SixelEncoder::Native) => {
+                let encoder = crate::sixel::Encoder { colors, ..Default::default() };
+                Sink::Native { encoder, file: None }
+            }
+            #[allow(unreachable_patterns)]
+            (_, encoder) => unreachable!("encodeur {:?} absent, refusé par la ligne de commande", encoder),
+        };
+        if let Some(path) = &args.output {
+            sink.set_output(path)?;
+        }
+        Ok(sink)
+    }
+
+    // Extension des fichiers de --sequence
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Sink::Kitty { .. } => "kitty",
+            Sink::Iterm { .. } => "iterm",
+            Sink::Text { .. } => "txt",
+            #[allow(unreachable_patterns)]
+            _ => "six",
+        }
+    }
+
+    pub fn set_output(&mut self, path: &Path) -> Result<(), AppError> {
+        let file = match self {
+            #[cfg(feature = "libsixel")]
+            Sink::Libsixel(encoder) => return Ok(encoder.set_output(path)?),
+            #[cfg(feature = "native-sixel")]
+            Sink::Native { file, .. } => file,
+            Sink::Kitty { file, .. } | Sink::Iterm { file } | Sink::Text { file, .. } => file,
+        };
+        *file = Some(File::create(path)?);
+        Ok(())
+    }
+
+    pub fn write(&mut self, fb: &FrameBuffer) -> Result<(), AppError> {
+        match self {
+            #[cfg(feature = "libsixel")]
+            Sink::Libsixel(encoder) => {
+                encoder.encode_bytes_ext(fb.width, fb.height, &fb.pixels, sixel_sys::PixelFormat::RGB888)?
+            }
+            #[cfg(feature = "native-sixel")]
+            Sink::Native { encoder, file } => fb.write_sixel(encoder, &mut destination(file))?,
+            Sink::Kitty { zlib, file } => fb.write_kitty(*zlib, &mut destination(file))?,
+            Sink::Iterm { file } => fb.write_iterm(&mut destination(file))?,
+            Sink::Text { mode, columns, file } => fb.write_text(*mode, *columns, &mut destination(file))?,
+        }
+        Ok(())
+    }
+}
+
+fn destination(file: &mut Option<File>) -> Box<dyn Write + '_> {
+    match file {
+        Some(file) => Box::new(file),
+        None => Box::new(stdout().lock()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Environnement simulé : paires (variable, valeur)
+    fn detect_with(vars: &[(&str, &str)], capabilities: Option<&Capabilities>) -> Protocol {
+        detect(|name| vars.iter().find(|(n, _)| *n == name).map(|(_, v)| v.to_string()), capabilities)
+    }
+
+    #[test]
+    fn detect_kitty_and_iterm() {
+        assert_eq!(detect_with(&[("KITTY_WINDOW_ID", "1"), ("TERM", "xterm-256color")], None), Protocol::Kitty);
+        assert_eq!(detect_with(&[("TERM", "xterm-kitty")], None), Protocol::Kitty);
+        assert_eq!(detect_with(&[("TERM_PROGRAM", "WezTerm")], None), Protocol::Kitty);
+        assert_eq!(detect_with(&[("TERM_PROGRAM", "ghostty"), ("TERM", "xterm-ghostty")], None), Protocol::Kitty);
+        assert_eq!(detect_with(&[("TERM_PROGRAM", "iTerm.app")], None), Protocol::Iterm);
+        assert_eq!(detect_with(&[("LC_TERMINAL", "iTerm2"), ("TERM", "xterm-256color")], None), Protocol::Iterm);
+    }
+
+    #[test]
+    fn detect_text_fallbacks() {
+        // Multiplexeurs : couleurs 24 bits ou non
+        assert_eq!(detect_with(&[("TERM", "screen-256color")], None), Protocol::Braille);
+        assert_eq!(detect_with(&[("TERM", "tmux-256color"), ("COLORTERM", "truecolor")], None), Protocol::Blocks);
+        assert_eq!(detect_with(&[("TMUX", "/tmp/tmux"), ("TERM", "xterm"), ("COLORTERM", "24bit")], None), Protocol::Blocks);
+        assert_eq!(detect_with(&[("TERM", "linux")], None), Protocol::Ascii);
+
+        // Réponse DA1 sans sixel
+        let no_sixel = Capabilities { sixel: false, ..Capabilities::default() };
+        assert_eq!(detect_with(&[("TERM", "xterm-256color")], Some(&no_sixel)), Protocol::Braille);
+        assert_eq!(detect_with(&[("TERM", "xterm-256color"), ("COLORTERM", "truecolor")], Some(&no_sixel)), Protocol::Blocks);
+    }
+
+    #[test]
+    fn detect_sixel_default() {
+        assert_eq!(detect_with(&[], None), Protocol::Sixel);
+        assert_eq!(detect_with(&[("TERM", "xterm-256color")], None), Protocol::Sixel);
+        let sixel = Capabilities { sixel: true, ..Capabilities::default() };
+        assert_eq!(detect_with(&[("TERM", "foot")], Some(&sixel)), Protocol::Sixel);
+        // Les variables du terminal passent avant sa réponse
+        let no_sixel = Capabilities { sixel: false, ..Capabilities::default() };
+        assert_eq!(detect_with(&[("KITTY_WINDOW_ID", "3")], Some(&no_sixel)), Protocol::Kitty);
+    }
+}
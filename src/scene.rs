@@ -0,0 +1,69 @@
+// Graphe de scène : des noeuds avec une transformation relative à leur parent,
+// un maillage (optionnel) et un matériau (optionnel, hérité du parent sinon).
+// Les moteurs de rendu ne voient que la liste "à plat" des instances.
+use crate::math_3d::{Mat4, MaterialRaytrace, Transform};
+use crate::mesh::Mesh;
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub transform: Transform,
+    // Index dans Scene::meshes
+    pub mesh: Option<usize>,
+    // None : matériau du parent, sinon celui du rendu (--material)
+    pub material: Option<MaterialRaytrace>,
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    pub fn new(mesh: Option<usize>) -> Node {
+        Node { transform: Transform::IDENTITY, mesh, material: None, children: Vec::new() }
+    }
+}
+
+// Un maillage placé dans le monde
+pub struct Instance<'a> {
+    pub mesh: &'a Mesh,
+    pub model_matrix: Mat4,
+    pub material: Option<MaterialRaytrace>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Scene {
+    pub meshes: Vec<Mesh>,
+    pub roots: Vec<Node>,
+}
+
+#[allow(dead_code)]
+impl Scene {
+    // Scène d'un seul modèle, sans transformation propre
+    pub fn from_mesh(mesh: Mesh) -> Scene {
+        Scene { meshes: vec![mesh], roots: vec![Node::new(Some(0))] }
+    }
+
+    pub fn add_mesh(&mut self, mesh: Mesh) -> usize {
+        self.meshes.push(mesh);
+        self.meshes.len() - 1
+    }
+
+    // Instances de la scène ; root est appliquée après les transformations des noeuds
+    // (transformations de la ligne de commande, animation)
+    pub fn instances(&self, root: &Mat4) -> Vec<Instance<'_>> {
+        let mut out = Vec::new();
+        for node in &self.roots {
+            self.collect(node, root, None, &mut out);
+        }
+        out
+    }
+
+    fn collect<'a>(&'a self, node: &Node, parent: &Mat4, material: Option<MaterialRaytrace>, out: &mut Vec<Instance<'a>>) {
+        // Vecteurs colonnes : la transformation du noeud s'applique avant celle du parent
+        let world = parent.mul(&node.transform.to_mat4());
+        let material = node.material.or(material);
+        if let Some(mesh) = node.mesh.and_then(|m| self.meshes.get(m)) {
+            out.push(Instance { mesh, model_matrix: world, material });
+        }
+        for child in &node.children {
+            self.collect(child, &world, material, out);
+        }
+    }
+}
@@ -0,0 +1,507 @@
+// Fichier de description de scène (TOML) : modèles et leurs transformations,
+// matériaux (préréglages ou coefficients), lumière, caméra et moteur de rendu.
+// Les chemins des modèles sont relatifs au fichier de scène.
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::cli::{self, Backend, RenderArgs};
+use crate::environment::EnvironmentSpec;
+use crate::light::{Light, NO_ATTENUATION};
+use crate::math_3d::{Material, MaterialRaytrace, Transform, Vec3};
+use crate::mesh::{LoadError, Mesh};
+use crate::mtl::MtlError;
+use crate::scene::{Node, Scene};
+use crate::shadow_map;
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(PathBuf, std::io::Error),
+    // (fichier, ligne, message)
+    Parse(PathBuf, usize, String),
+    Mesh(PathBuf, usize, LoadError),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            SceneError::Parse(path, line, msg) => write!(f, "{}:{}: {}", path.display(), line, msg),
+            SceneError::Mesh(path, line, e) => write!(f, "{}:{}: {}", path.display(), line, e),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+// Scène chargée et réglages de rendu équivalents à la ligne de commande
+pub struct SceneDescription {
+    pub scene: Scene,
+    pub backend: Backend,
+    pub args: RenderArgs,
+    // .mtl ou textures illisibles
+    pub warnings: Vec<MtlError>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    renderer: Option<Backend>,
+    width: Option<usize>,
+    height: Option<usize>,
+    background: Option<[u8; 3]>,
+    // Matériau des objets qui n'en ont pas
+    material: Option<Spanned<String>>,
+    crease_angle: Option<f32>,
+    shadow_samples: Option<u32>,
+    aa: Option<u32>,
+    spp: Option<u32>,
+    seed: Option<u64>,
+    shadow_map_size: Option<u32>,
+    shadow_pcf: Option<u32>,
+    shadow_bias: Option<f32>,
+    camera: Option<Spanned<CameraDesc>>,
+    environment: Option<EnvironmentDesc>,
+    // Remplacent la lumière par défaut
+    #[serde(default)]
+    lights: Vec<LightDesc>,
+    #[serde(default)]
+    materials: HashMap<String, Spanned<MaterialDesc>>,
+    #[serde(default)]
+    objects: Vec<ObjectDesc>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDesc {
+    eye: Option<[f32; 3]>,
+    target: Option<[f32; 3]>,
+    up: Option<[f32; 3]>,
+    fov: Option<f32>,
+    focal: Option<f32>,
+    ortho: Option<f32>,
+    near: Option<f32>,
+    far: Option<f32>,
+}
+
+// Mêmes sources que --light ; direction : vers la lumière (directional), axe du cône (spot)
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum LightDesc {
+    Directional {
+        direction: [f32; 3],
+        #[serde(default = "white")]
+        color: [f32; 3],
+        #[serde(default = "one")]
+        intensity: f32,
+    },
+    Point {
+        position: [f32; 3],
+        #[serde(default = "white")]
+        color: [f32; 3],
+        #[serde(default = "one")]
+        intensity: f32,
+        #[serde(default = "no_attenuation")]
+        attenuation: [f32; 3],
+    },
+    Spot {
+        position: [f32; 3],
+        direction: [f32; 3],
+        #[serde(default = "white")]
+        color: [f32; 3],
+        #[serde(default = "one")]
+        intensity: f32,
+        #[serde(default = "no_attenuation")]
+        attenuation: [f32; 3],
+        // Degrés, demi-ouverture du cône
+        #[serde(default = "spot_inner")]
+        inner: f32,
+        #[serde(default = "spot_outer")]
+        outer: f32,
+    },
+    Area {
+        position: [f32; 3],
+        u: [f32; 3],
+        v: [f32; 3],
+        #[serde(default = "white")]
+        color: [f32; 3],
+        #[serde(default = "one")]
+        intensity: f32,
+        #[serde(default = "no_attenuation")]
+        attenuation: [f32; 3],
+    },
+}
+
+// Mêmes choix que --env ; file : relatif au fichier de scène
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum EnvironmentDesc {
+    Sky,
+    Solid {
+        color: [f32; 3],
+    },
+    Gradient {
+        bottom: [f32; 3],
+        top: [f32; 3],
+    },
+    Map {
+        file: PathBuf,
+        #[serde(default = "one")]
+        intensity: f32,
+        // Degrés autour de y
+        #[serde(default)]
+        rotate: f32,
+    },
+}
+
+// Préréglage éventuel, puis coefficients qui le remplacent
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialDesc {
+    preset: Option<String>,
+    ka: Option<[f32; 3]>,
+    kd: Option<[f32; 3]>,
+    ks: Option<[f32; 3]>,
+    ns: Option<f32>,
+    reflectivity: Option<f32>,
+    transparency: Option<f32>,
+    refractive_index: Option<f32>,
+    emission: Option<[f32; 3]>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TransformDesc {
+    #[serde(default = "one")]
+    scale: f32,
+    // Degrés
+    #[serde(default)]
+    rotate: [f32; 3],
+    #[serde(default)]
+    translate: [f32; 3],
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+fn white() -> [f32; 3] {
+    [1.0; 3]
+}
+
+fn no_attenuation() -> [f32; 3] {
+    let (c, l, q) = NO_ATTENUATION;
+    [c, l, q]
+}
+
+fn spot_inner() -> f32 {
+    20.0
+}
+
+fn spot_outer() -> f32 {
+    30.0
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ObjectDesc {
+    // Sans modèle, le noeud sert seulement à grouper ses enfants
+    model: Option<Spanned<String>>,
+    transform: Option<TransformDesc>,
+    material: Option<Spanned<String>>,
+    #[serde(default)]
+    no_mtl: bool,
+    crease_angle: Option<f32>,
+    #[serde(default)]
+    children: Vec<ObjectDesc>,
+}
+
+impl LightDesc {
+    fn to_light(&self) -> Light {
+        let vec3 = |v: [f32; 3]| Vec3::new(v[0], v[1], v[2]);
+        let rgb = |c: [f32; 3]| (c[0], c[1], c[2]);
+        match *self {
+            LightDesc::Directional { direction, color, intensity } => {
+                Light::Directional { direction: vec3(direction), color: rgb(color), intensity }
+            }
+            LightDesc::Point { position, color, intensity, attenuation } => Light::Point {
+                position: vec3(position),
+                color: rgb(color),
+                intensity,
+                attenuation: rgb(attenuation),
+            },
+            LightDesc::Spot { position, direction, color, intensity, attenuation, inner, outer } => Light::Spot {
+                position: vec3(position),
+                direction: vec3(direction),
+                color: rgb(color),
+                intensity,
+                attenuation: rgb(attenuation),
+                inner: inner.to_radians(),
+                outer: outer.to_radians(),
+            },
+            LightDesc::Area { position, u, v, color, intensity, attenuation } => Light::Area {
+                position: vec3(position),
+                u: vec3(u),
+                v: vec3(v),
+                color: rgb(color),
+                intensity,
+                attenuation: rgb(attenuation),
+            },
+        }
+    }
+}
+
+impl EnvironmentDesc {
+    fn to_spec(&self, dir: &Path) -> EnvironmentSpec {
+        let rgb = |c: [f32; 3]| (c[0], c[1], c[2]);
+        match self {
+            EnvironmentDesc::Sky => EnvironmentSpec::SKY,
+            EnvironmentDesc::Solid { color } => EnvironmentSpec::Solid(rgb(*color)),
+            EnvironmentDesc::Gradient { bottom, top } => EnvironmentSpec::Gradient { bottom: rgb(*bottom), top: rgb(*top) },
+            EnvironmentDesc::Map { file, intensity, rotate } => {
+                EnvironmentSpec::Map { path: dir.join(file), intensity: *intensity, rotation: *rotate }
+            }
+        }
+    }
+}
+
+impl TransformDesc {
+    fn to_transform(&self) -> Transform {
+        let [x, y, z] = self.rotate;
+        Transform {
+            scale: self.scale,
+            rotation: (x.to_radians(), y.to_radians(), z.to_radians()),
+            translation: (self.translate[0], self.translate[1], self.translate[2]),
+        }
+    }
+}
+
+// Numéro de ligne (1-based) d'une position dans le texte
+fn line_of(text: &str, offset: usize) -> usize {
+    text[..offset.min(text.len())].matches('\n').count() + 1
+}
+
+struct Loader<'a> {
+    path: &'a Path,
+    text: &'a str,
+    dir: PathBuf,
+    crease_angle: f32,
+    materials: HashMap<String, MaterialRaytrace>,
+    scene: Scene,
+    // Un même modèle n'est chargé qu'une fois : (chemin, avec .mtl, angle de pli) -> maillage
+    meshes: HashMap<(PathBuf, bool, u32), usize>,
+    warnings: Vec<MtlError>,
+}
+
+impl Loader<'_> {
+    fn error<T>(&self, span: std::ops::Range<usize>, msg: String) -> Result<T, SceneError> {
+        Err(SceneError::Parse(self.path.to_path_buf(), line_of(self.text, span.start), msg))
+    }
+
+    fn material(&self, name: &Spanned<String>) -> Result<MaterialRaytrace, SceneError> {
+        match self.materials.get(name.get_ref()).copied().or_else(|| MaterialRaytrace::from_name(name.get_ref())) {
+            Some(m) => Ok(m),
+            None => self.error(name.span(), format!("matériau inconnu '{}'", name.get_ref())),
+        }
+    }
+
+    fn node(&mut self, object: &ObjectDesc) -> Result<Node, SceneError> {
+        let mesh = match &object.model {
+            Some(model) => {
+                let name = model.get_ref();
+                let path = if name.starts_with("builtin:") { PathBuf::from(name) } else { self.dir.join(name) };
+                let crease_angle = object.crease_angle.unwrap_or(self.crease_angle);
+                let key = (path.clone(), !object.no_mtl, crease_angle.to_bits());
+                match self.meshes.get(&key) {
+                    Some(&id) => Some(id),
+                    None => {
+                        let (mesh, warnings) = Mesh::load(&path, !object.no_mtl, crease_angle.to_radians())
+                            .map_err(|e| SceneError::Mesh(self.path.to_path_buf(), line_of(self.text, model.span().start), e))?;
+                        self.warnings.extend(warnings);
+                        let id = self.scene.add_mesh(mesh);
+                        self.meshes.insert(key, id);
+                        Some(id)
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let material = object.material.as_ref().map(|m| self.material(m)).transpose()?;
+        let children = object.children.iter().map(|c| self.node(c)).collect::<Result<_, _>>()?;
+
+        Ok(Node {
+            transform: object.transform.as_ref().map_or(Transform::IDENTITY, |t| t.to_transform()),
+            mesh,
+            material,
+            children,
+        })
+    }
+}
+
+fn custom_material(desc: &MaterialDesc) -> Option<MaterialRaytrace> {
+    let mut m = match &desc.preset {
+        Some(preset) => MaterialRaytrace::from_name(preset)?,
+        None => MaterialRaytrace {
+            material: Material::white_plastic(),
+            reflectivity: 0.0,
+            transparency: 0.0,
+            refractive_index: 1.0,
+            emission: (0.0, 0.0, 0.0),
+        },
+    };
+    let rgb = |c: [f32; 3]| (c[0], c[1], c[2]);
+    if let Some(ka) = desc.ka { m.material.ka = rgb(ka); }
+    if let Some(kd) = desc.kd { m.material.kd = rgb(kd); }
+    if let Some(ks) = desc.ks { m.material.ks = rgb(ks); }
+    if let Some(ns) = desc.ns { m.material.ns = ns; }
+    if let Some(r) = desc.reflectivity { m.reflectivity = r; }
+    if let Some(t) = desc.transparency { m.transparency = t; }
+    if let Some(n) = desc.refractive_index { m.refractive_index = n; }
+    if let Some(e) = desc.emission { m.emission = rgb(e); }
+    Some(m)
+}
+
+pub fn parse_scene(path: &Path, text: &str) -> Result<SceneDescription, SceneError> {
+    let file: SceneFile = toml::from_str(text).map_err(|e| {
+        let line = e.span().map_or(1, |s| line_of(text, s.start));
+        SceneError::Parse(path.to_path_buf(), line, e.message().to_string())
+    })?;
+
+    // Réglages par défaut de la ligne de commande, remplacés par ceux du fichier
+    let mut args = RenderArgs::defaults(path.to_path_buf());
+    if let Some(width) = file.width { args.width = width.max(1); }
+    if let Some(height) = file.height { args.height = height.max(1); }
+    args.default_size = file.width.is_none() && file.height.is_none();
+    if let Some([r, g, b]) = file.background { args.background = (r, g, b); }
+    if let Some(crease_angle) = file.crease_angle { args.crease_angle = crease_angle; }
+    if let Some(samples) = file.shadow_samples { args.shadow_samples = samples.max(1); }
+    if let Some(aa) = file.aa { args.aa = aa.clamp(1, 8); }
+    if let Some(spp) = file.spp { args.spp = spp.max(1); }
+    if file.seed.is_some() { args.seed = file.seed; }
+    if let Some(size) = file.shadow_map_size { args.shadow_map_size = size.min(shadow_map::MAX_SIZE); }
+    if let Some(pcf) = file.shadow_pcf { args.shadow_pcf = pcf; }
+    if let Some(bias) = file.shadow_bias { args.shadow_bias = bias; }
+    if let Some(environment) = &file.environment {
+        args.environment = Some(environment.to_spec(path.parent().unwrap_or(Path::new(""))));
+    }
+    if !file.lights.is_empty() {
+        args.lights = file.lights.iter().map(LightDesc::to_light).collect();
+    }
+
+    let mut loader = Loader {
+        path,
+        text,
+        dir: path.parent().unwrap_or(Path::new("")).to_path_buf(),
+        crease_angle: args.crease_angle,
+        materials: HashMap::new(),
+        scene: Scene::default(),
+        meshes: HashMap::new(),
+        warnings: Vec::new(),
+    };
+
+    if let Some(camera) = &file.camera {
+        let c = camera.get_ref();
+        if [c.fov.is_some(), c.focal.is_some(), c.ortho.is_some()].iter().filter(|&&b| b).count() > 1 {
+            return loader.error(camera.span(), "camera : fov, focal et ortho sont exclusifs".to_string());
+        }
+        if let Err(e) = apply_camera(c, &mut args) {
+            return loader.error(camera.span(), format!("camera : {}", e));
+        }
+    }
+
+    for (name, desc) in &file.materials {
+        match custom_material(desc.get_ref()) {
+            Some(m) => {
+                loader.materials.insert(name.clone(), m);
+            }
+            None => {
+                let preset = desc.get_ref().preset.clone().unwrap_or_default();
+                return loader.error(desc.span(), format!("materials.{} : préréglage inconnu '{}'", name, preset));
+            }
+        }
+    }
+
+    let default_material = file.material.as_ref().map(|m| loader.material(m)).transpose()?;
+    for object in &file.objects {
+        let mut node = loader.node(object)?;
+        node.material = node.material.or(default_material);
+        loader.scene.roots.push(node);
+    }
+
+    Ok(SceneDescription {
+        scene: loader.scene,
+        backend: file.renderer.unwrap_or(Backend::Gouraud),
+        args,
+        warnings: loader.warnings,
+    })
+}
+
+// Réglages [camera], avec les mêmes contrôles que la ligne de commande
+fn apply_camera(c: &CameraDesc, args: &mut RenderArgs) -> Result<(), String> {
+    if let Some([x, y, z]) = c.eye { args.eye = (x, y, z); }
+    if let Some([x, y, z]) = c.target { args.target = (x, y, z); }
+    if let Some([x, y, z]) = c.up { args.up = Vec3::new(x, y, z); }
+    args.fov = c.fov.map(cli::check_fov).transpose()?;
+    args.focal = c.focal.map(cli::check_focal).transpose()?;
+    args.ortho = c.ortho.map(cli::check_ortho).transpose()?;
+    if let Some(near) = c.near { args.near = cli::check_near(near)?; }
+    if let Some(far) = c.far { args.far = far; }
+    if args.near.partial_cmp(&args.far) != Some(Ordering::Less) {
+        return Err(format!("near ({}) doit être inférieur à far ({})", args.near, args.far));
+    }
+    Ok(())
+}
+
+pub fn load_scene(path: &Path) -> Result<SceneDescription, SceneError> {
+    let text = std::fs::read_to_string(path).map_err(|e| SceneError::Io(path.to_path_buf(), e))?;
+    parse_scene(path, &text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Ligne et message de l'erreur de lecture de `text`
+    fn parse_error(text: &str) -> (usize, String) {
+        match parse_scene(Path::new("test.toml"), text) {
+            Err(SceneError::Parse(_, line, msg)) => (line, msg),
+            Err(e) => panic!("erreur inattendue : {}", e),
+            Ok(_) => panic!("scène acceptée"),
+        }
+    }
+
+    #[test]
+    fn unknown_key() {
+        let (line, msg) = parse_error("renderer = \"phong\"\nwidth = 64\nsamples = 4\n");
+        assert_eq!(line, 3);
+        assert!(msg.contains("samples"), "{}", msg);
+    }
+
+    #[test]
+    fn missing_value() {
+        let (line, msg) = parse_error("width = 64\n\n[[lights]]\ntype = \"point\"\ncolor = [1, 0.5, 0]\n");
+        assert_eq!(line, 3);
+        assert!(msg.contains("position"), "{}", msg);
+    }
+
+    #[test]
+    fn bad_number() {
+        let (line, msg) = parse_error("width = 64\nheight = 64\nspp = -3\n");
+        assert_eq!(line, 3);
+        assert!(msg.contains("-3"), "{}", msg);
+    }
+
+    #[test]
+    fn bad_camera() {
+        for (camera, expected) in [("fov = 180", "fov"), ("ortho = 0", "ortho"), ("near = -1", "near"), ("near = 5\nfar = 2", "far")] {
+            let (line, msg) = parse_error(&format!("width = 64\n\n[camera]\n{}\n", camera));
+            assert_eq!(line, 3, "{}", camera);
+            assert!(msg.contains(expected), "{}", msg);
+        }
+    }
+}
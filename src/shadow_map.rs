@@ -0,0 +1,317 @@
+// Ombres des rasteriseurs : la scène est rendue en profondeur seule depuis chaque source
+// (même convention que le z-buffer : plus grand = plus proche), puis chaque point éclairé
+// est comparé à cette carte avec un filtrage PCF et un biais de profondeur.
+use rayon::prelude::*;
+
+use crate::light::{Incident, Light};
+use crate::math_3d::{Camera, Mat4, Point3d, Projection, Vec3};
+use crate::scene::Scene;
+
+// Côté maximal d'une carte : 64 Mo de profondeurs par vue, six vues par source ponctuelle
+pub const MAX_SIZE: u32 = 4096;
+
+// Une vue depuis la source : caméra et carte de profondeur carrée
+struct ShadowView {
+    camera: Camera,
+    depth: Vec<f32>,
+}
+
+// Directionnelle : une vue orthographique englobant la scène. Projecteur : une vue perspective
+// couvrant le cône. Ponctuelle ou surfacique (depuis son centre) : six faces d'un cube à 90°.
+struct ShadowMap {
+    views: Vec<ShadowView>,
+    position: Option<Vec3>,
+}
+
+pub struct ShadowMaps {
+    // Même index que les lumières, None : pas d'ombre pour cette source
+    maps: Vec<Option<ShadowMap>>,
+    size: usize,
+    // Rayon du noyau PCF en texels (1 : 3x3)
+    pcf: i32,
+    // Biais en texels, augmenté sur les surfaces rasantes
+    bias: f32,
+}
+
+impl ShadowMaps {
+    // Aucune ombre
+    pub fn none() -> ShadowMaps {
+        ShadowMaps { maps: Vec::new(), size: 0, pcf: 0, bias: 0.0 }
+    }
+
+    // root : transformation appliquée à toute la scène ; size = 0 désactive les ombres
+    pub fn build(scene: &Scene, root: &Mat4, lights: &[Light], size: usize, pcf: u32, bias: f32) -> ShadowMaps {
+        if size == 0 || lights.is_empty() {
+            return ShadowMaps::none();
+        }
+
+        // Triangles dans le repère monde, une fois pour toutes les vues
+        let mut triangles: Vec<[Point3d; 3]> = Vec::new();
+        for instance in scene.instances(root) {
+            let normal_matrix = instance.model_matrix.normal_matrix();
+            triangles.extend(
+                instance
+                    .mesh
+                    .triangles()
+                    .map(|t| t.transform(&instance.model_matrix, &normal_matrix).positions.map(|p| p.to_point3d())),
+            );
+        }
+        if triangles.is_empty() {
+            return ShadowMaps::none();
+        }
+
+        // Sphère englobante de la scène
+        let (mut min, mut max) = (Vec3::new(f32::MAX, f32::MAX, f32::MAX), Vec3::new(f32::MIN, f32::MIN, f32::MIN));
+        for p in triangles.iter().flatten() {
+            min = Vec3::new(min.x.min(p.0), min.y.min(p.1), min.z.min(p.2));
+            max = Vec3::new(max.x.max(p.0), max.y.max(p.1), max.z.max(p.2));
+        }
+        let center = min.add(max).mul(0.5);
+        let radius = max.sub(min).length().max(1e-3) * 0.5;
+
+        // Vues indépendantes : rendues en parallèle, sources comme faces du cube
+        let maps = lights
+            .par_iter()
+            .map(|light| {
+                let cameras = light_cameras(light, center, radius);
+                let views = cameras
+                    .into_par_iter()
+                    .map(|camera| {
+                        let mut depth = vec![f32::NEG_INFINITY; size * size];
+                        for tri in &triangles {
+                            let polygon = project_clipped(&camera, tri, size as f32);
+                            for i in 1..polygon.len().saturating_sub(1) {
+                                rasterize_depth([polygon[0], polygon[i], polygon[i + 1]], &mut depth, size);
+                            }
+                        }
+                        ShadowView { camera, depth }
+                    })
+                    .collect();
+                let position = match *light {
+                    Light::Directional { .. } => None,
+                    _ => light.sample_point(0.5, 0.5),
+                };
+                Some(ShadowMap { views, position })
+            })
+            .collect();
+
+        ShadowMaps { maps, size, pcf: pcf as i32, bias }
+    }
+
+    // Part visible (0.0 à 1.0) de la lumière d'index `light` depuis le point p de normale n
+    pub fn visibility(&self, light: usize, p: Vec3, n: Vec3, incident: &Incident) -> f32 {
+        let Some(Some(map)) = self.maps.get(light) else { return 1.0 };
+
+        // Face du cube : axe dominant de la direction lumière -> point
+        let view = match map.position {
+            Some(position) if map.views.len() == 6 => {
+                let d = p.sub(position);
+                let (ax, ay, az) = (d.x.abs(), d.y.abs(), d.z.abs());
+                let face = if ax >= ay && ax >= az {
+                    if d.x >= 0.0 { 0 } else { 1 }
+                } else if ay >= az {
+                    if d.y >= 0.0 { 2 } else { 3 }
+                } else if d.z >= 0.0 {
+                    4
+                } else {
+                    5
+                };
+                &map.views[face]
+            }
+            _ => &map.views[0],
+        };
+
+        let size = self.size as f32;
+        let Some((x, y, depth)) = view.camera.project(p.to_point3d(), size, size) else { return 1.0 };
+        let distance = linear_depth(&view.camera, depth);
+
+        // Biais en unités monde : taille d'un texel à cette distance, plus grand quand la surface est rasante
+        let texel = match view.camera.projection {
+            Projection::Perspective { fov_y } => distance * 2.0 * (fov_y * 0.5).tan() / size,
+            Projection::Orthographic { height } => height / size,
+        };
+        let cos = n.normalize().dot(incident.direction).abs().clamp(0.05, 1.0);
+        let slope = ((1.0 - cos * cos).sqrt() / cos).min(4.0);
+        let bias = self.bias * texel * (1.0 + slope);
+
+        // PCF : moyenne des tests sur le voisinage du texel
+        let (cx, cy) = (x.floor() as i32, y.floor() as i32);
+        let (mut lit, mut total) = (0, 0);
+        for dy in -self.pcf..=self.pcf {
+            for dx in -self.pcf..=self.pcf {
+                total += 1;
+                let (tx, ty) = (cx + dx, cy + dy);
+                if tx < 0 || ty < 0 || tx >= self.size as i32 || ty >= self.size as i32 {
+                    lit += 1;
+                    continue;
+                }
+                let stored = view.depth[ty as usize * self.size + tx as usize];
+                if stored == f32::NEG_INFINITY || distance - bias <= linear_depth(&view.camera, stored) {
+                    lit += 1;
+                }
+            }
+        }
+        lit as f32 / total as f32
+    }
+}
+
+// Distance le long de l'axe de la caméra à partir de la profondeur du z-buffer
+fn linear_depth(camera: &Camera, depth: f32) -> f32 {
+    match camera.projection {
+        Projection::Perspective { .. } => 1.0 / depth,
+        Projection::Orthographic { .. } => -depth,
+    }
+}
+
+fn light_cameras(light: &Light, center: Vec3, radius: f32) -> Vec<Camera> {
+    let near = radius * 1e-3;
+    match *light {
+        Light::Directional { direction, .. } => {
+            let eye = center.add(direction.normalize().mul(radius * 2.0));
+            vec![Camera {
+                near: 0.0,
+                far: radius * 4.0,
+                ..Camera::orthographic(eye.to_point3d(), center.to_point3d(), radius * 2.0)
+            }]
+        }
+        Light::Spot { position, direction, outer, .. } => {
+            let fov = (2.0 * outer).min(170f32.to_radians());
+            vec![Camera {
+                near,
+                ..Camera::perspective(position.to_point3d(), position.add(direction).to_point3d(), fov)
+            }]
+        }
+        Light::Point { position, .. } | Light::Area { position, .. } => {
+            // Ordre utilisé par visibility : +x, -x, +y, -y, +z, -z
+            let axes = [
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(-1.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                Vec3::new(0.0, -1.0, 0.0),
+                Vec3::new(0.0, 0.0, 1.0),
+                Vec3::new(0.0, 0.0, -1.0),
+            ];
+            axes.iter()
+                .map(|&axis| Camera {
+                    near,
+                    ..Camera::perspective(position.to_point3d(), position.add(axis).to_point3d(), 90f32.to_radians())
+                })
+                .collect()
+        }
+    }
+}
+
+// Triangle du monde -> polygone projeté dans la carte, découpé contre les plans near et far :
+// un triangle qui passe derrière la source garde la partie qu'elle voit
+fn project_clipped(camera: &Camera, tri: &[Point3d; 3], size: f32) -> Vec<Point3d> {
+    let mut polygon: Vec<Vec3> = tri.iter().map(|&p| camera.view_space(p)).collect();
+    polygon = clip(&polygon, |v| v.z - camera.near);
+    if camera.far.is_finite() {
+        polygon = clip(&polygon, |v| camera.far - v.z);
+    }
+    polygon.into_iter().map(|v| camera.project_view(v, size, size)).collect()
+}
+
+// Sutherland-Hodgman : partie du polygone où distance(v) >= 0
+fn clip(polygon: &[Vec3], distance: impl Fn(Vec3) -> f32) -> Vec<Vec3> {
+    let mut out = Vec::with_capacity(polygon.len() + 1);
+    for (i, &a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        let (da, db) = (distance(a), distance(b));
+        if da >= 0.0 {
+            out.push(a);
+        }
+        if (da >= 0.0) != (db >= 0.0) {
+            out.push(a.add(b.sub(a).mul(da / (da - db))));
+        }
+    }
+    out
+}
+
+// Triangle projeté -> profondeur seule (test "plus proche" comme le z-buffer)
+fn rasterize_depth(v: [Point3d; 3], depth: &mut [f32], size: usize) {
+    let den = (v[1].1 - v[2].1) * (v[0].0 - v[2].0) + (v[2].0 - v[1].0) * (v[0].1 - v[2].1);
+    if den.abs() < 1e-6 {
+        return;
+    }
+    let max = size as i32 - 1;
+    let min_x = (v.iter().map(|p| p.0).fold(f32::INFINITY, f32::min).floor() as i32).clamp(0, max);
+    let max_x = (v.iter().map(|p| p.0).fold(f32::NEG_INFINITY, f32::max).ceil() as i32).clamp(0, max);
+    let min_y = (v.iter().map(|p| p.1).fold(f32::INFINITY, f32::min).floor() as i32).clamp(0, max);
+    let max_y = (v.iter().map(|p| p.1).fold(f32::NEG_INFINITY, f32::max).ceil() as i32).clamp(0, max);
+
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let (fx, fy) = (x as f32 + 0.5, y as f32 + 0.5);
+            let w0 = ((v[1].1 - v[2].1) * (fx - v[2].0) + (v[2].0 - v[1].0) * (fy - v[2].1)) / den;
+            let w1 = ((v[2].1 - v[0].1) * (fx - v[2].0) + (v[0].0 - v[2].0) * (fy - v[2].1)) / den;
+            let w2 = 1.0 - w0 - w1;
+            if w0 >= 0.0 && w1 >= 0.0 && w2 >= 0.0 {
+                let z = v[0].2 * w0 + v[1].2 * w1 + v[2].2 * w2;
+                let offset = y as usize * size + x as usize;
+                if z > depth[offset] {
+                    depth[offset] = z;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math_3d::Transform;
+    use crate::mesh::Mesh;
+
+    // Sol de 10 x 10 en y = 0 et, au-dessus du centre, un carré de 2 x 2 en y = 2
+    fn scene() -> Scene {
+        let points = [
+            (-5.0, 0.0, -5.0),
+            (5.0, 0.0, -5.0),
+            (5.0, 0.0, 5.0),
+            (-5.0, 0.0, 5.0),
+            (-1.0, 2.0, -1.0),
+            (1.0, 2.0, -1.0),
+            (1.0, 2.0, 1.0),
+            (-1.0, 2.0, 1.0),
+        ];
+        let faces: [&[usize]; 2] = [&[0, 3, 2, 1], &[4, 7, 6, 5]];
+        Scene::from_mesh(Mesh::from_points(&points, faces, 0.0))
+    }
+
+    fn visibility(shadows: &ShadowMaps, light: &Light, p: Vec3) -> f32 {
+        let incident = light.incident(p).unwrap();
+        shadows.visibility(0, p, Vec3::new(0.0, 1.0, 0.0), &incident)
+    }
+
+    #[test]
+    fn occluded_and_lit() {
+        let root = Transform::IDENTITY.to_mat4();
+        let lights = [
+            Light::directional(Vec3::new(0.0, 1.0, 0.0)),
+            Light::Point {
+                position: Vec3::new(0.0, 6.0, 0.0),
+                color: (1.0, 1.0, 1.0),
+                intensity: 1.0,
+                attenuation: crate::light::NO_ATTENUATION,
+            },
+        ];
+        for light in lights {
+            let shadows = ShadowMaps::build(&scene(), &root, std::slice::from_ref(&light), 256, 1, 1.5);
+            // Sous le carré
+            assert_eq!(visibility(&shadows, &light, Vec3::new(0.0, 0.0, 0.0)), 0.0, "{:?}", light);
+            assert_eq!(visibility(&shadows, &light, Vec3::new(0.3, 0.0, -0.4)), 0.0, "{:?}", light);
+            // Sol dégagé et dessus du carré (pas d'acné d'ombre)
+            assert_eq!(visibility(&shadows, &light, Vec3::new(4.0, 0.0, 0.0)), 1.0, "{:?}", light);
+            assert_eq!(visibility(&shadows, &light, Vec3::new(-3.0, 0.0, 3.5)), 1.0, "{:?}", light);
+            assert_eq!(visibility(&shadows, &light, Vec3::new(0.5, 2.0, 0.5)), 1.0, "{:?}", light);
+        }
+    }
+
+    #[test]
+    fn disabled() {
+        let light = Light::directional(Vec3::new(0.0, 1.0, 0.0));
+        let shadows = ShadowMaps::build(&scene(), &Transform::IDENTITY.to_mat4(), &[light], 0, 1, 1.5);
+        assert_eq!(visibility(&shadows, &light, Vec3::new(0.0, 0.0, 0.0)), 1.0);
+    }
+}
@@ -0,0 +1,437 @@
+// Encodeur sixel natif, sans libsixel : quantification median cut, diffusion d'erreur
+// Floyd-Steinberg optionnelle et compression RLE. Les étapes reprennent celles de libsixel
+// (histogramme sur 5 bits par composante, découpe à la médiane, palette réordonnée par
+// première utilisation, découpage en bandes de 6 lignes) : avec les mêmes réglages, la
+// sortie est identique octet pour octet.
+use std::io::{self, Write};
+
+// Histogramme : au-delà de ce nombre de pixels, l'image est échantillonnée
+const MAX_SAMPLES: usize = 18383;
+// Au-delà de 3 caractères identiques, une répétition "!N" est plus courte
+const RLE_MIN: usize = 4;
+
+pub struct Encoder {
+    // Taille maximale de la palette (2 à 256). Sans valeur, 256 couleurs et, comme libsixel
+    // sans nombre de couleurs demandé, diffusion même quand l'histogramme tient dans la palette
+    pub colors: Option<usize>,
+    // Diffusion d'erreur Floyd-Steinberg
+    pub dither: bool,
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Encoder { colors: None, dither: true }
+    }
+}
+
+impl Encoder {
+    // pixels : RGB888, ligne par ligne
+    pub fn encode(&self, pixels: &[u8], width: usize, height: usize, out: &mut impl Write) -> io::Result<()> {
+        let size = width * height * 3;
+        if width == 0 || height == 0 || pixels.len() < size {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "image vide ou tronquée"));
+        }
+
+        // La diffusion modifie les pixels au fil du parcours
+        let mut data = pixels[..size].to_vec();
+        let (palette, exact) = make_palette(&data, self.colors.unwrap_or(256).clamp(2, 256));
+        // Palette exacte : il n'y a pas d'erreur à diffuser
+        let dither = self.dither && !(exact && self.colors.is_some());
+        let (indices, palette) = apply_palette(&mut data, width, height, &palette, dither);
+
+        let mut sixel = Vec::with_capacity(size / 4);
+        write_header(&mut sixel, width, height);
+        write_palette(&mut sixel, &palette);
+        write_body(&mut sixel, &indices, width, height, palette.len());
+        sixel.extend_from_slice(b"\x1b\\");
+        out.write_all(&sixel)
+    }
+}
+
+// Couleur réduite à 5 bits par composante, rangée en 15 bits (clé d'histogramme et de cache)
+fn hash(pixel: &[u8]) -> usize {
+    ((pixel[0] as usize >> 3) << 10) | ((pixel[1] as usize >> 3) << 5) | (pixel[2] as usize >> 3)
+}
+
+struct Bucket {
+    count: u32,
+    color: [u32; 3],
+}
+
+// Couleurs présentes (5 bits par composante) avec leur nombre de pixels, dans l'ordre d'apparition
+fn histogram(data: &[u8]) -> Vec<Bucket> {
+    let length = data.len();
+    let step = if length < MAX_SAMPLES * 3 { 18 } else { (length / 3 / MAX_SAMPLES * 3).max(3) };
+
+    let mut counts = vec![0u16; 1 << 15];
+    let mut order = Vec::new();
+    // Comme libsixel, le dernier pixel n'est jamais compté, sauf s'il est le seul
+    let end = if length > 3 { length - 3 } else { length };
+    for i in (0..end).step_by(step) {
+        let key = hash(&data[i..i + 3]);
+        if counts[key] == 0 {
+            order.push(key);
+        }
+        counts[key] = counts[key].saturating_add(1);
+    }
+
+    order
+        .into_iter()
+        .map(|key| Bucket {
+            count: counts[key] as u32,
+            color: [((key >> 10) & 0x1f) as u32 * 8, ((key >> 5) & 0x1f) as u32 * 8, (key & 0x1f) as u32 * 8],
+        })
+        .collect()
+}
+
+// Boîte de la découpe : couleurs buckets[start..start + len], sum pixels
+struct ColorBox {
+    start: usize,
+    len: usize,
+    sum: u32,
+}
+
+// Palette d'au plus `colors` couleurs : l'histogramme tel quel s'il tient (palette exacte),
+// sinon median cut (Heckbert) : on coupe la boîte la plus peuplée à la médiane de sa plus
+// grande dimension
+fn make_palette(data: &[u8], colors: usize) -> (Vec<[u8; 3]>, bool) {
+    let mut buckets = histogram(data);
+    if buckets.len() <= colors {
+        return (buckets.iter().map(|b| b.color.map(|c| c as u8)).collect(), true);
+    }
+
+    let total = buckets.iter().map(|b| b.count).sum();
+    let mut boxes = vec![ColorBox { start: 0, len: buckets.len(), sum: total }];
+
+    while boxes.len() < colors {
+        let Some(bi) = boxes.iter().position(|b| b.len >= 2) else {
+            break;
+        };
+        let ColorBox { start, len, sum } = boxes[bi];
+        let slice = &mut buckets[start..start + len];
+
+        // Dimension de plus grande étendue (la première en cas d'égalité)
+        let mut plane = 0;
+        let mut largest = 0;
+        for p in 0..3 {
+            let min = slice.iter().map(|b| b.color[p]).min().unwrap_or(0);
+            let max = slice.iter().map(|b| b.color[p]).max().unwrap_or(0);
+            if max - min > largest {
+                largest = max - min;
+                plane = p;
+            }
+        }
+        // Tri stable, comme le qsort (tri fusion) de la glibc utilisé par libsixel
+        slice.sort_by_key(|b| b.color[plane]);
+
+        // Médiane en nombre de pixels, chaque moitié gardant au moins une couleur
+        let mut lower = slice[0].count;
+        let mut median = 1;
+        while median < len - 1 && lower < sum / 2 {
+            lower += slice[median].count;
+            median += 1;
+        }
+
+        boxes[bi] = ColorBox { start, len: median, sum: lower };
+        boxes.push(ColorBox { start: start + median, len: len - median, sum: sum - lower });
+        boxes.sort_by_key(|b| std::cmp::Reverse(b.sum));
+    }
+
+    // Représentant : centre de la boîte ; les entrées sans boîte restent noires
+    let mut palette = vec![[0u8; 3]; colors];
+    for (entry, b) in palette.iter_mut().zip(&boxes) {
+        let slice = &buckets[b.start..b.start + b.len];
+        for (p, component) in entry.iter_mut().enumerate() {
+            let min = slice.iter().map(|c| c.color[p]).min().unwrap_or(0);
+            let max = slice.iter().map(|c| c.color[p]).max().unwrap_or(0);
+            *component = ((min + max) / 2) as u8;
+        }
+    }
+    (palette, false)
+}
+
+// Erreur d'une composante répartie sur un voisin (coefficient numerator / denominator)
+fn diffuse(data: &mut [u8], pos: usize, error: i32, numerator: i32, denominator: i32) {
+    let c = data[pos] as i32 + error * numerator / denominator;
+    data[pos] = c.clamp(0, 255) as u8;
+}
+
+// Floyd-Steinberg : 7/16 à droite, 3/16 en bas à gauche, 5/16 en bas, 1/16 en bas à droite.
+// Ni la dernière colonne ni la dernière ligne ne diffusent ; en colonne 0, la part « en bas à
+// gauche » tombe sur la fin de la ligne courante, comme dans libsixel
+fn diffuse_fs(data: &mut [u8], width: usize, height: usize, x: usize, y: usize, channel: usize, error: i32) {
+    if x + 1 < width && y + 1 < height {
+        let pos = y * width + x;
+        diffuse(data, (pos + 1) * 3 + channel, error, 7, 16);
+        diffuse(data, (pos + width - 1) * 3 + channel, error, 3, 16);
+        diffuse(data, (pos + width) * 3 + channel, error, 5, 16);
+        diffuse(data, (pos + width + 1) * 3 + channel, error, 1, 16);
+    }
+}
+
+// Indice de palette de chaque pixel, et palette réduite aux couleurs utilisées, dans
+// l'ordre de leur première apparition
+fn apply_palette(data: &mut [u8], width: usize, height: usize, palette: &[[u8; 3]], dither: bool) -> (Vec<u8>, Vec<[u8; 3]>) {
+    // Plus proche couleur, mémorisée par couleur réduite à 5 bits par composante
+    let mut cache = vec![0u16; 1 << 15];
+    let mut lookup = |pixel: &[u8]| -> usize {
+        let key = hash(pixel);
+        if cache[key] != 0 {
+            return cache[key] as usize - 1;
+        }
+        let mut best = 0;
+        let mut best_distance = i32::MAX;
+        for (i, c) in palette.iter().enumerate() {
+            let distance: i32 = (0..3).map(|n| (pixel[n] as i32 - c[n] as i32).pow(2)).sum();
+            if distance < best_distance {
+                best_distance = distance;
+                best = i;
+            }
+        }
+        cache[key] = best as u16 + 1;
+        best
+    };
+
+    let mut indices = vec![0u8; width * height];
+    let mut used = Vec::new();
+    let mut migration = [0u16; 256];
+    for y in 0..height {
+        for x in 0..width {
+            let pos = y * width + x;
+            let index = lookup(&data[pos * 3..pos * 3 + 3]);
+            if migration[index] == 0 {
+                used.push(palette[index]);
+                migration[index] = used.len() as u16;
+            }
+            indices[pos] = (migration[index] - 1) as u8;
+
+            if dither {
+                for channel in 0..3 {
+                    let error = data[pos * 3 + channel] as i32 - palette[index][channel] as i32;
+                    diffuse_fs(data, width, height, x, y, channel, error);
+                }
+            }
+        }
+    }
+
+    (indices, used)
+}
+
+fn write_header(out: &mut Vec<u8>, width: usize, height: usize) {
+    // DCS, rapport d'aspect 1:1, taille de l'image (raster attributes)
+    write!(out, "\x1bPq\"1;1;{};{}", width, height).unwrap();
+}
+
+fn write_palette(out: &mut Vec<u8>, palette: &[[u8; 3]]) {
+    for (n, c) in palette.iter().enumerate() {
+        let percent = |v: u8| (v as u32 * 100 + 127) / 255;
+        write!(out, "#{};2;{};{};{}", n, percent(c[0]), percent(c[1]), percent(c[2])).unwrap();
+    }
+}
+
+// Suite de caractères sixel avec compression RLE (DECGRI, "!N")
+struct Run<'a> {
+    out: &'a mut Vec<u8>,
+    byte: u8,
+    count: usize,
+}
+
+impl Run<'_> {
+    fn push(&mut self, bits: u8) {
+        let byte = b'?' + bits.min(63);
+        if byte == self.byte {
+            self.count += 1;
+        } else {
+            self.flush();
+            self.byte = byte;
+            self.count = 1;
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.count >= RLE_MIN {
+            write!(self.out, "!{}", self.count).unwrap();
+            self.out.push(self.byte);
+        } else {
+            self.out.extend(std::iter::repeat_n(self.byte, self.count));
+        }
+        self.count = 0;
+        self.byte = 0;
+    }
+}
+
+// Segment d'une bande : couleur pal, colonnes sx..mx
+struct Span {
+    pal: usize,
+    sx: usize,
+    mx: usize,
+}
+
+// Bandes de 6 lignes ; dans chaque bande, les segments de chaque couleur sont écrits de
+// gauche à droite, avec un retour chariot ('$') quand il faut repartir de la colonne 0
+fn write_body(out: &mut Vec<u8>, indices: &[u8], width: usize, height: usize, ncolors: usize) {
+    let mut map = vec![0u8; ncolors * width];
+    let mut spans: Vec<Span> = Vec::new();
+    let mut active = None;
+    let mut row = 0;
+
+    for y in 0..height {
+        for x in 0..width {
+            let pix = indices[y * width + x] as usize;
+            if pix < ncolors {
+                map[pix * width + x] |= 1 << row;
+            }
+        }
+
+        row += 1;
+        if row < 6 && y + 1 < height {
+            continue;
+        }
+
+        for c in 0..ncolors {
+            let line = &map[c * width..(c + 1) * width];
+            let mut sx = 0;
+            while sx < width {
+                if line[sx] == 0 {
+                    sx += 1;
+                    continue;
+                }
+
+                // Un segment absorbe les trous de moins de 10 colonnes
+                let mut mx = sx + 1;
+                while mx < width {
+                    if line[mx] != 0 {
+                        mx += 1;
+                        continue;
+                    }
+                    let mut n = 1;
+                    while mx + n < width && line[mx + n] == 0 {
+                        n += 1;
+                    }
+                    if n >= 10 || mx + n >= width {
+                        break;
+                    }
+                    mx += n;
+                }
+
+                // Triés par début, puis du plus long au plus court
+                let at = spans
+                    .iter()
+                    .position(|s| sx < s.sx || (sx == s.sx && mx > s.mx))
+                    .unwrap_or(spans.len());
+                spans.insert(at, Span { pal: c, sx, mx });
+                sx = mx;
+            }
+        }
+
+        // DECGNL, sauf avant la première bande d'une image d'au moins 6 lignes
+        if y != 5 {
+            out.push(b'-');
+        }
+
+        let mut x = 0;
+        while !spans.is_empty() {
+            if x > spans[0].sx {
+                out.push(b'$');
+                x = 0;
+            }
+            // Premier segment, puis le premier de la liste qui commence après la fin du précédent
+            let mut next = Some(0);
+            while let Some(i) = next {
+                let span = spans.remove(i);
+                if active != Some(span.pal) {
+                    write!(out, "#{}", span.pal).unwrap();
+                    active = Some(span.pal);
+                }
+                let line = &map[span.pal * width..(span.pal + 1) * width];
+                let mut run = Run { out, byte: 0, count: 0 };
+                while x < span.mx {
+                    run.push(if x < span.sx { 0 } else { line[x] });
+                    x += 1;
+                }
+                run.flush();
+                next = spans.iter().position(|s| s.sx >= x);
+            }
+        }
+
+        row = 0;
+        map.fill(0);
+    }
+}
+
+#[cfg(all(test, feature = "libsixel"))]
+mod tests {
+    use super::Encoder;
+    use sixel_rs::encoder::Encoder as Libsixel;
+    use sixel_rs::optflags::DiffusionMethod;
+    use sixel_sys::PixelFormat;
+
+    // Dégradés croisés et motif à haute fréquence : bien plus de 256 couleurs
+    fn image(width: usize, height: usize) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(width * height * 3);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.push((x * 255 / width) as u8);
+                pixels.push((y * 255 / height) as u8);
+                pixels.push(((x * 7 + y * 13) % 256) as u8);
+            }
+        }
+        pixels
+    }
+
+    // Quelques aplats : la palette est l'histogramme lui-même
+    fn flat(width: usize, height: usize) -> Vec<u8> {
+        let colors = [[255, 0, 0], [0, 128, 255], [20, 20, 40], [250, 250, 250]];
+        (0..width * height).flat_map(|i| colors[(i / width / 4 + i % width / 9) % colors.len()]).collect()
+    }
+
+    fn libsixel(pixels: &[u8], width: usize, height: usize, colors: u8, diffusion: DiffusionMethod) -> Vec<u8> {
+        let encoder = Libsixel::new().unwrap();
+        encoder.set_num_colors(colors).unwrap();
+        encoder.set_diffusion(diffusion).unwrap();
+        let mut out = Vec::new();
+        encoder.encode_to(&mut out, width, height, pixels, PixelFormat::RGB888).unwrap();
+        out
+    }
+
+    fn native(pixels: &[u8], width: usize, height: usize, colors: usize, dither: bool) -> Vec<u8> {
+        let mut out = Vec::new();
+        Encoder { colors: Some(colors), dither }.encode(pixels, width, height, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn same_bytes_as_libsixel() {
+        // Tailles multiples de 6 ou non, grande image échantillonnée, image plus petite qu'une bande
+        for (width, height) in [(64, 48), (61, 37), (200, 150), (17, 4)] {
+            let pixels = image(width, height);
+            for (colors, dither) in [(255, true), (255, false), (16, true), (2, false)] {
+                let diffusion = if dither { DiffusionMethod::FS } else { DiffusionMethod::None };
+                let expected = libsixel(&pixels, width, height, colors, diffusion);
+                let encoded = native(&pixels, width, height, colors as usize, dither);
+                assert!(encoded == expected, "{}x{}, {} couleurs, diffusion {}", width, height, colors, dither);
+            }
+        }
+    }
+
+    #[test]
+    fn exact_palette_matches_libsixel() {
+        // Diffusion demandée, mais l'histogramme tient dans la palette : libsixel n'en fait pas
+        let (width, height) = (90, 31);
+        let pixels = flat(width, height);
+        let expected = libsixel(&pixels, width, height, 16, DiffusionMethod::FS);
+        assert!(native(&pixels, width, height, 16, true) == expected);
+    }
+
+    #[test]
+    fn default_settings_match_libsixel() {
+        // Sans nombre de couleurs, libsixel diffuse même une palette exacte
+        for (width, height, pixels) in [(64, 48, image(64, 48)), (90, 31, flat(90, 31))] {
+            let mut expected = Vec::new();
+            Libsixel::new().unwrap().encode_to(&mut expected, width, height, &pixels, PixelFormat::RGB888).unwrap();
+            let mut encoded = Vec::new();
+            Encoder::default().encode(&pixels, width, height, &mut encoded).unwrap();
+            assert!(encoded == expected, "{}x{}", width, height);
+        }
+    }
+}
@@ -0,0 +1,252 @@
+// Interrogation du terminal : DA1 (attribut 4 = sixel), XTWINOPS 14t / 16t (taille de la
+// fenêtre et d'une cellule en pixels) et XTSMGRAPHICS (registres de couleur et taille maximale
+// d'une image sixel). Les questions partent ensemble, DA1 en dernier : tous les terminaux y
+// répondent, dans l'ordre, donc sa réponse clôt l'échange. Sans elle, abandon après le délai.
+use std::fs::OpenOptions;
+use std::io;
+use std::os::fd::{AsRawFd, RawFd};
+use std::time::{Duration, Instant};
+
+// Délai de réponse, y compris à travers SSH
+pub const QUERY_TIMEOUT: Duration = Duration::from_millis(300);
+
+// XTSMGRAPHICS registres puis géométrie, XTWINOPS fenêtre puis cellule, DA1
+const QUERIES: &[u8] = b"\x1b[?1;1;0S\x1b[?2;1;0S\x1b[14t\x1b[16t\x1b[c";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Capabilities {
+    pub sixel: bool,
+    // Largeur, hauteur en pixels
+    pub window: Option<(usize, usize)>,
+    pub cell: Option<(usize, usize)>,
+    pub color_registers: Option<usize>,
+    // Image sixel la plus grande que le terminal accepte
+    pub sixel_geometry: Option<(usize, usize)>,
+}
+
+impl Capabilities {
+    // Plus grand carré affichable sous forme d'image, en gardant une ligne pour l'invite ;
+    // à défaut de réponse à 14t / 16t, d'après la taille donnée par le noyau
+    pub fn image_side(&self) -> Option<usize> {
+        let size = window_size();
+        let cell = self.cell.or(size.and_then(|s| {
+            (s.ws_col > 0 && s.ws_row > 0 && s.ws_xpixel > 0)
+                .then(|| (s.ws_xpixel as usize / s.ws_col as usize, s.ws_ypixel as usize / s.ws_row as usize))
+        }));
+        let window = self.window.or(size.and_then(|s| {
+            if s.ws_xpixel > 0 && s.ws_ypixel > 0 {
+                Some((s.ws_xpixel as usize, s.ws_ypixel as usize))
+            } else {
+                cell.map(|(w, h)| (w * s.ws_col as usize, h * s.ws_row as usize))
+            }
+        }))?;
+        let side = window.0.min(window.1.saturating_sub(cell.map_or(0, |c| c.1)));
+        (side > 0).then_some(side)
+    }
+}
+
+// Le terminal de contrôle (/dev/tty), même si l'entrée standard est redirigée
+pub fn query_tty(timeout: Duration) -> io::Result<Capabilities> {
+    let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+    query(tty.as_raw_fd(), timeout)
+}
+
+// Taille du terminal de la sortie standard (cellules, et pixels si le terminal la renseigne)
+pub fn window_size() -> Option<libc::winsize> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    (unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0).then_some(size)
+}
+
+// La sortie standard est un terminal
+pub fn stdout_is_tty() -> bool {
+    unsafe { libc::isatty(libc::STDOUT_FILENO) == 1 }
+}
+
+// Pose les questions sur fd (un terminal) et lit les réponses jusqu'à celle de DA1
+pub fn query(fd: RawFd, timeout: Duration) -> io::Result<Capabilities> {
+    let _raw = NoEcho::enable(fd)?;
+    write_all(fd, QUERIES)?;
+
+    let deadline = Instant::now() + timeout;
+    let mut answers = Vec::new();
+    let mut buf = [0u8; 256];
+    loop {
+        let (capabilities, done) = parse(&answers);
+        if done {
+            return Ok(capabilities);
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let mut poll = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+        let ready = unsafe { libc::poll(&mut poll, 1, remaining.as_millis() as libc::c_int) };
+        if ready < 0 {
+            let error = io::Error::last_os_error();
+            if error.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(error);
+        }
+        if ready == 0 {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "le terminal ne répond pas à DA1"));
+        }
+        let n = unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+        if n <= 0 {
+            return Err(io::Error::last_os_error());
+        }
+        answers.extend_from_slice(&buf[..n as usize]);
+    }
+}
+
+fn write_all(fd: RawFd, mut bytes: &[u8]) -> io::Result<()> {
+    while !bytes.is_empty() {
+        let n = unsafe { libc::write(fd, bytes.as_ptr() as *const libc::c_void, bytes.len()) };
+        if n < 0 {
+            let error = io::Error::last_os_error();
+            if error.kind() != io::ErrorKind::Interrupted {
+                return Err(error);
+            }
+            continue;
+        }
+        bytes = &bytes[n as usize..];
+    }
+    Ok(())
+}
+
+// Réponses reçues jusqu'ici, et si celle de DA1 en fait partie
+fn parse(answers: &[u8]) -> (Capabilities, bool) {
+    let mut capabilities = Capabilities::default();
+    let mut i = 0;
+    while i + 1 < answers.len() {
+        if answers[i] != 0x1b || answers[i + 1] != b'[' {
+            i += 1;
+            continue;
+        }
+        // CSI [?] paramètres ; séparés par des points-virgules, puis l'octet final
+        let private = answers.get(i + 2) == Some(&b'?');
+        let start = i + 2 + private as usize;
+        let Some(end) = answers[start..].iter().position(|b| !(b.is_ascii_digit() || *b == b';')) else {
+            break;
+        };
+        let params: Vec<usize> = std::str::from_utf8(&answers[start..start + end])
+            .unwrap_or_default()
+            .split(';')
+            .map(|p| p.parse().unwrap_or(0))
+            .collect();
+        match (private, answers[start + end], params.as_slice()) {
+            (true, b'c', [_, attributes @ ..]) => {
+                capabilities.sixel = attributes.contains(&4);
+                return (capabilities, true);
+            }
+            (true, b'S', [1, 0, registers, ..]) => capabilities.color_registers = Some(*registers),
+            (true, b'S', [2, 0, width, height, ..]) => capabilities.sixel_geometry = Some((*width, *height)),
+            (false, b't', [4, height, width, ..]) => capabilities.window = Some((*width, *height)),
+            (false, b't', [6, height, width, ..]) => capabilities.cell = Some((*width, *height)),
+            _ => {}
+        }
+        i = start + end + 1;
+    }
+    (capabilities, false)
+}
+
+// Garde : sans écho ni mode canonique le temps de l'échange, les réponses n'apparaissent pas
+// à l'écran et arrivent sans attendre de fin de ligne
+struct NoEcho {
+    fd: RawFd,
+    original: libc::termios,
+}
+
+impl NoEcho {
+    fn enable(fd: RawFd) -> io::Result<NoEcho> {
+        let mut original: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(fd, &mut original) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut raw = original;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(NoEcho { fd, original })
+    }
+}
+
+impl Drop for NoEcho {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(self.fd, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    // Pseudo-terminal dont le côté maître joue un terminal scripté : il attend les questions
+    // jusqu'à DA1, puis envoie `reply` (rien si None)
+    fn scripted(reply: Option<&'static [u8]>, timeout: Duration) -> io::Result<Capabilities> {
+        let (mut master, mut slave) = (0, 0);
+        let opened = unsafe {
+            libc::openpty(&mut master, &mut slave, std::ptr::null_mut(), std::ptr::null(), std::ptr::null())
+        };
+        assert_eq!(opened, 0, "openpty");
+
+        let terminal = thread::spawn(move || {
+            let mut received = Vec::new();
+            let mut buf = [0u8; 64];
+            while !received.ends_with(b"\x1b[c") {
+                let n = unsafe { libc::read(master, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+                assert!(n > 0, "lecture du maître");
+                received.extend_from_slice(&buf[..n as usize]);
+            }
+            if let Some(reply) = reply {
+                write_all(master, reply).unwrap();
+            }
+            received
+        });
+
+        let capabilities = query(slave, timeout);
+        let received = terminal.join().unwrap();
+        assert_eq!(received, QUERIES);
+        unsafe {
+            libc::close(slave);
+            libc::close(master);
+        }
+        capabilities
+    }
+
+    #[test]
+    fn reads_every_answer() {
+        // Réponses d'un xterm configuré en VT340, dans l'ordre des questions
+        let reply = b"\x1b[?1;0;1024S\x1b[?2;0;1000;1000S\x1b[4;768;1024t\x1b[6;16;8t\x1b[?63;1;2;4;6;9;15;22c";
+        let capabilities = scripted(Some(reply), Duration::from_secs(5)).unwrap();
+        assert_eq!(
+            capabilities,
+            Capabilities {
+                sixel: true,
+                window: Some((1024, 768)),
+                cell: Some((8, 16)),
+                color_registers: Some(1024),
+                sixel_geometry: Some((1000, 1000)),
+            }
+        );
+    }
+
+    #[test]
+    fn terminal_without_sixel() {
+        // Questions inconnues ignorées, XTSMGRAPHICS en erreur, DA1 sans l'attribut 4
+        let reply = b"\x1b[?1;3;0S\x1b[?62;1;22c";
+        let capabilities = scripted(Some(reply), Duration::from_secs(5)).unwrap();
+        assert_eq!(capabilities, Capabilities::default());
+    }
+
+    #[test]
+    fn silent_terminal_times_out() {
+        let start = Instant::now();
+        let error = scripted(None, Duration::from_millis(100)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
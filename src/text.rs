@@ -0,0 +1,242 @@
+// Affichage en caractères, pour les terminaux sans protocole d'image (tmux sans passthrough,
+// console, SSH) : demi-blocs "▀" en couleurs 24 bits (haut en premier plan, bas en fond), ou
+// braille et rampe ASCII dans les 16 couleurs ANSI
+use std::io::{self, Write};
+
+use crate::terminal;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextMode {
+    Blocks,
+    Braille,
+    Ascii,
+}
+
+// Fin de ligne : retour chariot explicite, le mode brut de --interactive coupe OPOST et le
+// terminal n'ajoute plus le \r de lui-même
+const ROW_END: &[u8] = b"\x1b[0m\r\n";
+
+// Du plus sombre au plus clair
+const RAMP: &[u8] = b" .:-=+*#%@";
+
+// Couleurs ANSI 30-37 puis 90-97, valeurs de xterm
+const ANSI: [[u8; 3]; 16] = [
+    [0, 0, 0],
+    [205, 0, 0],
+    [0, 205, 0],
+    [205, 205, 0],
+    [0, 0, 238],
+    [205, 0, 205],
+    [0, 205, 205],
+    [229, 229, 229],
+    [127, 127, 127],
+    [255, 0, 0],
+    [0, 255, 0],
+    [255, 255, 0],
+    [92, 92, 255],
+    [255, 0, 255],
+    [0, 255, 255],
+    [255, 255, 255],
+];
+
+// Points d'un caractère braille (2 x 4) : bit de chaque point, par ligne puis colonne
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+// Écart de luminance en dessous duquel une cellule est uniforme : tous les points ou aucun
+const FLAT_CELL: u32 = 32;
+// Luminance en dessous de laquelle une cellule uniforme reste vide (premier palier de RAMP)
+const RAMP_DARK: u32 = 256 / RAMP.len() as u32;
+
+// Largeur du terminal en colonnes, si la sortie standard en est un
+pub fn terminal_columns() -> Option<usize> {
+    terminal::window_size().filter(|size| size.ws_col > 0).map(|size| size.ws_col as usize)
+}
+
+// pixels : RGB888, ligne par ligne ; columns : largeur du texte en caractères.
+// Une cellule de terminal est deux fois plus haute que large : demi-blocs et points braille
+// restent carrés, un caractère ASCII couvre deux lignes de l'image réduite
+pub fn encode(
+    pixels: &[u8],
+    width: usize,
+    height: usize,
+    mode: TextMode,
+    columns: usize,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    if width == 0 || height == 0 || pixels.len() < width * height * 3 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "image vide ou tronquée"));
+    }
+
+    let mut text = Vec::new();
+    match mode {
+        TextMode::Blocks => {
+            let columns = columns.clamp(1, width);
+            let rows = grid_height(width, height, columns, 2);
+            let grid = resample(pixels, width, height, columns, rows * 2);
+            for y in 0..rows {
+                let mut colors = Colors::default();
+                for x in 0..columns {
+                    colors.truecolor(&mut text, grid[2 * y * columns + x], grid[(2 * y + 1) * columns + x]);
+                    text.extend_from_slice("▀".as_bytes());
+                }
+                text.extend_from_slice(ROW_END);
+            }
+        }
+        TextMode::Braille => {
+            let columns = columns.clamp(1, width.div_ceil(2));
+            let rows = grid_height(width, height, columns * 2, 4);
+            let grid = resample(pixels, width, height, columns * 2, rows * 4);
+            for y in 0..rows {
+                let mut colors = Colors::default();
+                for x in 0..columns {
+                    let cell: Vec<(u32, [u8; 3])> = (0..8)
+                        .map(|i| grid[(y * 4 + i / 2) * columns * 2 + x * 2 + i % 2])
+                        .map(|c| (luminance(c), c))
+                        .collect();
+                    let (bits, color) = braille_cell(&cell);
+                    if bits == 0 {
+                        text.push(b' ');
+                        continue;
+                    }
+                    colors.ansi(&mut text, color);
+                    let glyph = char::from_u32(0x2800 + bits).expect("caractère braille");
+                    text.extend_from_slice(glyph.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                text.extend_from_slice(ROW_END);
+            }
+        }
+        TextMode::Ascii => {
+            let columns = columns.clamp(1, width);
+            let rows = grid_height(width, height, columns, 2);
+            let grid = resample(pixels, width, height, columns, rows);
+            for y in 0..rows {
+                let mut colors = Colors::default();
+                for x in 0..columns {
+                    let c = grid[y * columns + x];
+                    let level = luminance(c) as usize * RAMP.len() / 256;
+                    if level > 0 {
+                        colors.ansi(&mut text, c);
+                    }
+                    text.push(RAMP[level]);
+                }
+                text.extend_from_slice(ROW_END);
+            }
+        }
+    }
+    out.write_all(&text)
+}
+
+// Nombre de lignes de texte pour une grille de `columns` points de large, chaque caractère
+// faisant `cell` points de haut, en gardant les proportions de l'image
+fn grid_height(width: usize, height: usize, columns: usize, cell: usize) -> usize {
+    (height * columns).div_ceil(width * cell).max(1)
+}
+
+// Réduction en grid_width x grid_height : moyenne des pixels couverts par chaque point
+fn resample(pixels: &[u8], width: usize, height: usize, grid_width: usize, grid_height: usize) -> Vec<[u8; 3]> {
+    let mut grid = Vec::with_capacity(grid_width * grid_height);
+    for gy in 0..grid_height {
+        let y0 = (gy * height / grid_height).min(height - 1);
+        let y1 = ((gy + 1) * height / grid_height).clamp(y0 + 1, height);
+        for gx in 0..grid_width {
+            let x0 = (gx * width / grid_width).min(width - 1);
+            let x1 = ((gx + 1) * width / grid_width).clamp(x0 + 1, width);
+            let mut sum = [0usize; 3];
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let idx = (y * width + x) * 3;
+                    for (s, p) in sum.iter_mut().zip(&pixels[idx..idx + 3]) {
+                        *s += *p as usize;
+                    }
+                }
+            }
+            let count = (x1 - x0) * (y1 - y0);
+            grid.push(sum.map(|s| (s / count) as u8));
+        }
+    }
+    grid
+}
+
+// Luminance (Rec. 709) de 0 à 255
+fn luminance(c: [u8; 3]) -> u32 {
+    (2126 * c[0] as u32 + 7152 * c[1] as u32 + 722 * c[2] as u32) / 10000
+}
+
+// Points allumés : ceux plus clairs que la moyenne de la cellule, ou tous si la cellule est
+// uniforme et pas trop sombre ; la couleur est la moyenne des points allumés
+fn braille_cell(cell: &[(u32, [u8; 3])]) -> (u32, [u8; 3]) {
+    let min = cell.iter().map(|(l, _)| *l).min().unwrap_or(0);
+    let max = cell.iter().map(|(l, _)| *l).max().unwrap_or(0);
+    let mean = cell.iter().map(|(l, _)| *l).sum::<u32>() / cell.len() as u32;
+    let lit = |l: u32| if max - min < FLAT_CELL { l >= RAMP_DARK } else { l > mean };
+
+    let mut bits = 0;
+    let mut sum = [0u32; 3];
+    let mut count = 0;
+    for (i, (l, c)) in cell.iter().enumerate() {
+        if lit(*l) {
+            bits |= BRAILLE_DOTS[i / 2][i % 2];
+            for (s, p) in sum.iter_mut().zip(c) {
+                *s += *p as u32;
+            }
+            count += 1;
+        }
+    }
+    (bits, sum.map(|s| (s / count.max(1)) as u8))
+}
+
+// Couleur ANSI la plus proche (distance euclidienne)
+fn nearest_ansi(c: [u8; 3]) -> usize {
+    let distance = |a: &[u8; 3]| a.iter().zip(c).map(|(a, b)| (*a as i32 - b as i32).pow(2)).sum::<i32>();
+    (0..ANSI.len()).min_by_key(|&i| distance(&ANSI[i])).unwrap_or(0)
+}
+
+// Couleurs courantes d'une ligne : les séquences SGR ne sont écrites que quand elles changent
+#[derive(Default)]
+struct Colors {
+    foreground: Option<[u8; 3]>,
+    background: Option<[u8; 3]>,
+    ansi: Option<usize>,
+}
+
+impl Colors {
+    fn truecolor(&mut self, text: &mut Vec<u8>, foreground: [u8; 3], background: [u8; 3]) {
+        if self.foreground != Some(foreground) {
+            let [r, g, b] = foreground;
+            write!(text, "\x1b[38;2;{};{};{}m", r, g, b).unwrap();
+            self.foreground = Some(foreground);
+        }
+        if self.background != Some(background) {
+            let [r, g, b] = background;
+            write!(text, "\x1b[48;2;{};{};{}m", r, g, b).unwrap();
+            self.background = Some(background);
+        }
+    }
+
+    fn ansi(&mut self, text: &mut Vec<u8>, color: [u8; 3]) {
+        let index = nearest_ansi(color);
+        if self.ansi != Some(index) {
+            let code = if index < 8 { 30 + index } else { 90 + index - 8 };
+            write!(text, "\x1b[{}m", code).unwrap();
+            self.ansi = Some(index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_end_with_carriage_return() {
+        let pixels: Vec<u8> = (0..8 * 8 * 3).map(|i| (i * 7 % 256) as u8).collect();
+        for mode in [TextMode::Blocks, TextMode::Braille, TextMode::Ascii] {
+            let mut out = Vec::new();
+            encode(&pixels, 8, 8, mode, 4, &mut out).unwrap();
+            // Chaque saut de ligne suit un retour chariot
+            let rows = out.iter().filter(|&&b| b == b'\n').count();
+            assert!(rows > 0);
+            assert_eq!(out.windows(ROW_END.len()).filter(|w| *w == ROW_END).count(), rows, "{:?}", mode);
+            assert!(out.ends_with(ROW_END), "{:?}", mode);
+        }
+    }
+}
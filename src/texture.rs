@@ -0,0 +1,622 @@
+// Textures RGB chargées depuis des fichiers PPM, TGA ou BMP (sans dépendance externe),
+// ou images HDR (PFM, Radiance .hdr) dont les valeurs peuvent dépasser 1.0
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum TextureError {
+    Io(PathBuf, std::io::Error),
+    Format(PathBuf, String),
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextureError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            TextureError::Format(path, msg) => write!(f, "{}: {}", path.display(), msg),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {}
+
+#[derive(Debug, Clone)]
+pub struct Texture {
+    pub width: usize,
+    pub height: usize,
+    // RGB 0.0..1.0, ligne 0 en haut de l'image
+    pub pixels: Vec<(f32, f32, f32)>,
+}
+
+#[allow(dead_code)]
+impl Texture {
+    pub fn load(path: &Path) -> Result<Texture, TextureError> {
+        let data = std::fs::read(path).map_err(|e| TextureError::Io(path.to_path_buf(), e))?;
+        let format_err = |msg: String| TextureError::Format(path.to_path_buf(), msg);
+
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+
+        if data.starts_with(b"P6") || data.starts_with(b"P3") {
+            decode_ppm(&data).map_err(format_err)
+        } else if data.starts_with(b"PF") || data.starts_with(b"Pf") {
+            decode_pfm(&data).map_err(format_err)
+        } else if data.starts_with(b"#?") {
+            decode_hdr(&data).map_err(format_err)
+        } else if data.starts_with(b"BM") {
+            decode_bmp(&data).map_err(format_err)
+        } else if extension == "tga" {
+            // Le TGA n'a pas de signature en tête de fichier
+            decode_tga(&data).map_err(format_err)
+        } else {
+            Err(format_err("format non reconnu (PPM, TGA, BMP, PFM ou HDR attendu)".to_string()))
+        }
+    }
+
+    fn texel(&self, x: i64, y: i64) -> (f32, f32, f32) {
+        // Répétition (GL_REPEAT)
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+        self.pixels[y * self.width + x]
+    }
+
+    // Échantillonnage bilinéaire, (u, v) en convention OBJ : v = 0 en bas de l'image
+    pub fn sample(&self, u: f32, v: f32) -> (f32, f32, f32) {
+        if self.pixels.is_empty() {
+            return (1.0, 1.0, 1.0);
+        }
+        let fx = u * self.width as f32 - 0.5;
+        let fy = (1.0 - v) * self.height as f32 - 0.5;
+        let (x0, y0) = (fx.floor(), fy.floor());
+        let (tx, ty) = (fx - x0, fy - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let c00 = self.texel(x0, y0);
+        let c10 = self.texel(x0 + 1, y0);
+        let c01 = self.texel(x0, y0 + 1);
+        let c11 = self.texel(x0 + 1, y0 + 1);
+
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let mix = |a: (f32, f32, f32), b: (f32, f32, f32), t: f32| {
+            (lerp(a.0, b.0, t), lerp(a.1, b.1, t), lerp(a.2, b.2, t))
+        };
+        mix(mix(c00, c10, tx), mix(c01, c11, tx), ty)
+    }
+}
+
+// width x height x channels, sans débordement ; une image vide est refusée
+fn image_size(width: usize, height: usize, channels: usize) -> Result<usize, String> {
+    if width == 0 || height == 0 {
+        return Err(format!("dimensions invalides : {}x{}", width, height));
+    }
+    width
+        .checked_mul(height)
+        .and_then(|n| n.checked_mul(channels))
+        .ok_or_else(|| format!("dimensions trop grandes : {}x{}", width, height))
+}
+
+// --- PPM (P3 ASCII / P6 binaire) ---
+fn decode_ppm(data: &[u8]) -> Result<Texture, String> {
+    let mut pos = 2;
+    let mut header = [0usize; 3];
+    for value in header.iter_mut() {
+        // Blancs et commentaires entre les champs
+        loop {
+            while pos < data.len() && data[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if pos < data.len() && data[pos] == b'#' {
+                while pos < data.len() && data[pos] != b'\n' {
+                    pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+        let start = pos;
+        while pos < data.len() && data[pos].is_ascii_digit() {
+            pos += 1;
+        }
+        *value = std::str::from_utf8(&data[start..pos])
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or("en-tête PPM invalide")?;
+    }
+    let [width, height, maxval] = header;
+    if maxval == 0 || maxval > 65535 {
+        return Err(format!("maxval PPM invalide : {}", maxval));
+    }
+    let scale = 1.0 / maxval as f32;
+    let count = image_size(width, height, 3)?;
+
+    let samples: Vec<f32> = if data.starts_with(b"P6") {
+        // Un seul blanc après maxval, puis les données brutes
+        let body = &data[(pos + 1).min(data.len())..];
+        if body.len() / if maxval < 256 { 1 } else { 2 } < count {
+            return Err("données PPM tronquées".to_string());
+        }
+        if maxval < 256 {
+            body.iter().take(count).map(|&b| b as f32 * scale).collect()
+        } else {
+            body.chunks_exact(2).take(count).map(|c| u16::from_be_bytes([c[0], c[1]]) as f32 * scale).collect()
+        }
+    } else {
+        // Au moins un octet par valeur
+        if data.len() - pos < count {
+            return Err("données PPM tronquées".to_string());
+        }
+        std::str::from_utf8(&data[pos..])
+            .map_err(|_| "données PPM invalides")?
+            .split_ascii_whitespace()
+            .take(count)
+            .map(|t| t.parse::<f32>().map(|v| v * scale))
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("données PPM invalides : {}", e))?
+    };
+
+    if samples.len() < count {
+        return Err("données PPM tronquées".to_string());
+    }
+    let pixels = samples.chunks_exact(3).map(|c| (c[0], c[1], c[2])).collect();
+    Ok(Texture { width, height, pixels })
+}
+
+// --- TGA (true color / niveaux de gris, brut ou RLE) ---
+fn decode_tga(data: &[u8]) -> Result<Texture, String> {
+    if data.len() < 18 {
+        return Err("en-tête TGA tronqué".to_string());
+    }
+    let id_length = data[0] as usize;
+    let color_map_type = data[1];
+    let image_type = data[2];
+    let color_map_length = u16::from_le_bytes([data[5], data[6]]) as usize;
+    let color_map_entry_bits = data[7] as usize;
+    let width = u16::from_le_bytes([data[12], data[13]]) as usize;
+    let height = u16::from_le_bytes([data[14], data[15]]) as usize;
+    let bpp = data[16] as usize;
+    let top_down = data[17] & 0x20 != 0;
+
+    let (gray, rle) = match image_type {
+        2 => (false, false),
+        3 => (true, false),
+        10 => (false, true),
+        11 => (true, true),
+        t => return Err(format!("type d'image TGA {} non géré", t)),
+    };
+    let bytes_pp = bpp / 8;
+    if !(gray && bytes_pp == 1) && !(!gray && (bytes_pp == 3 || bytes_pp == 4)) {
+        return Err(format!("profondeur TGA {} bits non gérée", bpp));
+    }
+
+    let mut pos = 18 + id_length;
+    if color_map_type == 1 {
+        pos += color_map_length * color_map_entry_bits.div_ceil(8);
+    }
+
+    // Décompression éventuelle vers des pixels bruts ; un paquet RLE d'au moins 2 octets
+    // donne au plus 128 pixels, la taille est vérifiée avant d'allouer
+    let count = image_size(width, height, 1)?;
+    let remaining = data.len().saturating_sub(pos);
+    let available = if rle { remaining.saturating_mul(64) } else { remaining / bytes_pp };
+    if count > available {
+        return Err("données TGA tronquées".to_string());
+    }
+    let mut raw: Vec<u8> = Vec::with_capacity(count * bytes_pp);
+    if rle {
+        while raw.len() < count * bytes_pp {
+            let header = *data.get(pos).ok_or("données TGA tronquées")?;
+            pos += 1;
+            let n = (header & 0x7f) as usize + 1;
+            if header & 0x80 != 0 {
+                let px = data.get(pos..pos + bytes_pp).ok_or("données TGA tronquées")?;
+                for _ in 0..n {
+                    raw.extend_from_slice(px);
+                }
+                pos += bytes_pp;
+            } else {
+                let px = data.get(pos..pos + n * bytes_pp).ok_or("données TGA tronquées")?;
+                raw.extend_from_slice(px);
+                pos += n * bytes_pp;
+            }
+        }
+        raw.truncate(count * bytes_pp);
+    } else {
+        raw.extend_from_slice(data.get(pos..pos + count * bytes_pp).ok_or("données TGA tronquées")?);
+    }
+
+    let mut pixels = vec![(0.0, 0.0, 0.0); count];
+    for (i, px) in raw.chunks_exact(bytes_pp).enumerate() {
+        let (x, y) = (i % width, i / width);
+        // Par défaut l'origine est en bas à gauche
+        let row = if top_down { y } else { height - 1 - y };
+        pixels[row * width + x] = if gray {
+            let g = px[0] as f32 / 255.0;
+            (g, g, g)
+        } else {
+            (px[2] as f32 / 255.0, px[1] as f32 / 255.0, px[0] as f32 / 255.0)
+        };
+    }
+    Ok(Texture { width, height, pixels })
+}
+
+// --- BMP (24 / 32 bits non compressé) ---
+fn decode_bmp(data: &[u8]) -> Result<Texture, String> {
+    let u32_at = |o: usize| data.get(o..o + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+    let u16_at = |o: usize| data.get(o..o + 2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+
+    let offset = u32_at(10).ok_or("en-tête BMP tronqué")? as usize;
+    let width = u32_at(18).ok_or("en-tête BMP tronqué")? as i32;
+    let height = u32_at(22).ok_or("en-tête BMP tronqué")? as i32;
+    let bpp = u16_at(28).ok_or("en-tête BMP tronqué")? as usize;
+    let compression = u32_at(30).ok_or("en-tête BMP tronqué")?;
+
+    // 0 = BI_RGB, 3 = BI_BITFIELDS (masques BGRA standards supposés)
+    if compression != 0 && compression != 3 {
+        return Err(format!("compression BMP {} non gérée", compression));
+    }
+    if bpp != 24 && bpp != 32 {
+        return Err(format!("profondeur BMP {} bits non gérée", bpp));
+    }
+    if width <= 0 || height == 0 {
+        return Err("dimensions BMP invalides".to_string());
+    }
+
+    // Hauteur négative : lignes stockées de haut en bas
+    let top_down = height < 0;
+    let (width, height) = (width as usize, height.unsigned_abs() as usize);
+    let bytes_pp = bpp / 8;
+    let line = image_size(width, 1, bytes_pp)?;
+    let stride = line.div_ceil(4) * 4;
+    // La dernière ligne n'a pas besoin de son remplissage
+    let end = stride
+        .checked_mul(height - 1)
+        .and_then(|n| n.checked_add(line))
+        .and_then(|n| n.checked_add(offset))
+        .ok_or("dimensions BMP trop grandes")?;
+    if end > data.len() {
+        return Err("données BMP tronquées".to_string());
+    }
+
+    let mut pixels = vec![(0.0, 0.0, 0.0); width * height];
+    for y in 0..height {
+        let start = offset + y * stride;
+        let line = data.get(start..start + width * bytes_pp).ok_or("données BMP tronquées")?;
+        let row = if top_down { y } else { height - 1 - y };
+        for (x, px) in line.chunks_exact(bytes_pp).enumerate() {
+            pixels[row * width + x] = (px[2] as f32 / 255.0, px[1] as f32 / 255.0, px[0] as f32 / 255.0);
+        }
+    }
+    Ok(Texture { width, height, pixels })
+}
+
+// Ligne d'en-tête texte (sans le \n) et position de la suivante
+fn header_line(data: &[u8], pos: usize) -> Option<(&str, usize)> {
+    let end = pos + data.get(pos..)?.iter().position(|&b| b == b'\n')?;
+    let line = std::str::from_utf8(&data[pos..end]).ok()?;
+    Some((line.trim(), end + 1))
+}
+
+// --- PFM (Portable Float Map, couleur "PF" ou niveaux de gris "Pf") ---
+fn decode_pfm(data: &[u8]) -> Result<Texture, String> {
+    let (magic, pos) = header_line(data, 0).ok_or("en-tête PFM invalide")?;
+    let channels = if magic == "PF" { 3 } else { 1 };
+    let (size, pos) = header_line(data, pos).ok_or("en-tête PFM invalide")?;
+    let (scale, pos) = header_line(data, pos).ok_or("en-tête PFM invalide")?;
+
+    let dims: Vec<usize> = size.split_ascii_whitespace().filter_map(|v| v.parse().ok()).collect();
+    let [width, height] = dims[..] else {
+        return Err(format!("dimensions PFM invalides : '{}'", size));
+    };
+    // Échelle négative : little endian
+    let scale: f32 = scale.parse().map_err(|_| format!("échelle PFM invalide : '{}'", scale))?;
+    let little_endian = scale < 0.0;
+
+    let count = image_size(width, height, channels)?;
+    let body = count
+        .checked_mul(4)
+        .and_then(|n| n.checked_add(pos))
+        .and_then(|end| data.get(pos..end))
+        .ok_or("données PFM tronquées")?;
+    let samples: Vec<f32> = body
+        .chunks_exact(4)
+        .map(|b| {
+            let b = [b[0], b[1], b[2], b[3]];
+            if little_endian { f32::from_le_bytes(b) } else { f32::from_be_bytes(b) }
+        })
+        .collect();
+
+    // Lignes stockées de bas en haut
+    let mut pixels = vec![(0.0, 0.0, 0.0); width * height];
+    for (i, px) in samples.chunks_exact(channels).enumerate() {
+        let (x, y) = (i % width, i / width);
+        pixels[(height - 1 - y) * width + x] = if channels == 3 { (px[0], px[1], px[2]) } else { (px[0], px[0], px[0]) };
+    }
+    Ok(Texture { width, height, pixels })
+}
+
+// --- HDR (Radiance RGBE, brut ou RLE par ligne) ---
+fn decode_hdr(data: &[u8]) -> Result<Texture, String> {
+    // En-tête : lignes "CLE=valeur" jusqu'à une ligne vide, puis la résolution
+    let mut pos = 0;
+    loop {
+        let (line, next) = header_line(data, pos).ok_or("en-tête HDR tronqué")?;
+        pos = next;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=")
+            && format != "32-bit_rle_rgbe"
+        {
+            return Err(format!("format HDR {} non géré", format));
+        }
+    }
+    let (resolution, mut pos) = header_line(data, pos).ok_or("résolution HDR manquante")?;
+    // Seule l'orientation standard est gérée : lignes de haut en bas, pixels de gauche à droite
+    let fields: Vec<&str> = resolution.split_ascii_whitespace().collect();
+    let (height, width) = match fields[..] {
+        ["-Y", h, "+X", w] => (
+            h.parse::<usize>().map_err(|_| "résolution HDR invalide")?,
+            w.parse::<usize>().map_err(|_| "résolution HDR invalide")?,
+        ),
+        _ => return Err(format!("orientation HDR '{}' non gérée", resolution)),
+    };
+
+    // Taille minimale d'une ligne : en RLE, l'en-tête puis des paquets de 2 octets pour au plus
+    // 127 pixels par composante ; sinon 4 octets par pixel
+    let count = image_size(width, height, 1)?;
+    let row_min = if (8..0x8000).contains(&width) {
+        4 + 8 * width.div_ceil(127)
+    } else {
+        width.checked_mul(4).ok_or("dimensions HDR trop grandes")?
+    };
+    if height.checked_mul(row_min).is_none_or(|n| n > data.len() - pos) {
+        return Err("données HDR tronquées".to_string());
+    }
+
+    let mut rgbe = vec![[0u8; 4]; count];
+    for row in rgbe.chunks_exact_mut(width) {
+        let rle = data.get(pos..pos + 4).ok_or("données HDR tronquées")?;
+        if (8..0x8000).contains(&width) && rle[0] == 2 && rle[1] == 2 && rle[2] & 0x80 == 0 {
+            if ((rle[2] as usize) << 8 | rle[3] as usize) != width {
+                return Err("longueur de ligne HDR incohérente".to_string());
+            }
+            pos += 4;
+            // Chaque composante (R, G, B, E) est compressée séparément
+            for channel in 0..4 {
+                let mut x = 0;
+                while x < width {
+                    let count = *data.get(pos).ok_or("données HDR tronquées")? as usize;
+                    pos += 1;
+                    if count == 0 {
+                        return Err("RLE HDR invalide".to_string());
+                    }
+                    if count > 128 {
+                        let n = count - 128;
+                        let value = *data.get(pos).ok_or("données HDR tronquées")?;
+                        pos += 1;
+                        for px in row.get_mut(x..x + n).ok_or("RLE HDR invalide")? {
+                            px[channel] = value;
+                        }
+                        x += n;
+                    } else {
+                        let values = data.get(pos..pos + count).ok_or("données HDR tronquées")?;
+                        for (px, &value) in row.get_mut(x..x + count).ok_or("RLE HDR invalide")?.iter_mut().zip(values) {
+                            px[channel] = value;
+                        }
+                        pos += count;
+                        x += count;
+                    }
+                }
+            }
+        } else {
+            // Ligne non compressée
+            let line = data.get(pos..pos + width * 4).ok_or("données HDR tronquées")?;
+            for (px, b) in row.iter_mut().zip(line.chunks_exact(4)) {
+                *px = [b[0], b[1], b[2], b[3]];
+            }
+            pos += width * 4;
+        }
+    }
+
+    let pixels = rgbe
+        .iter()
+        .map(|&[r, g, b, e]| {
+            if e == 0 {
+                return (0.0, 0.0, 0.0);
+            }
+            // Mantisses 8 bits, exposant commun
+            let f = 2f32.powi(e as i32 - 136);
+            (r as f32 * f, g as f32 * f, b as f32 * f)
+        })
+        .collect();
+    Ok(Texture { width, height, pixels })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Rgb = (f32, f32, f32);
+    type Decoder = fn(&[u8]) -> Result<Texture, String>;
+
+    const RED: Rgb = (1.0, 0.0, 0.0);
+    const GREEN: Rgb = (0.0, 1.0, 0.0);
+    const BLUE: Rgb = (0.0, 0.0, 1.0);
+    const WHITE: Rgb = (1.0, 1.0, 1.0);
+
+    fn assert_pixels(texture: &Texture, width: usize, height: usize, expected: &[Rgb]) {
+        assert_eq!((texture.width, texture.height), (width, height));
+        for (p, e) in texture.pixels.iter().zip(expected) {
+            assert!((p.0 - e.0).abs() < 1e-3 && (p.1 - e.1).abs() < 1e-3 && (p.2 - e.2).abs() < 1e-3, "{:?}", texture.pixels);
+        }
+    }
+
+    // Images 2 x 2 : rouge, vert en haut, bleu, blanc en bas
+    fn ppm() -> Vec<u8> {
+        let mut data = b"P6\n# commentaire\n2 2\n255\n".to_vec();
+        data.extend_from_slice(&[255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255]);
+        data
+    }
+
+    fn tga() -> Vec<u8> {
+        let mut data = vec![0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 2, 0, 24, 0];
+        // Origine en bas à gauche, BGR
+        data.extend_from_slice(&[255, 0, 0, 255, 255, 255, 0, 0, 255, 0, 255, 0]);
+        data
+    }
+
+    fn bmp() -> Vec<u8> {
+        // Lignes de 6 octets complétées à 8, de bas en haut, BGR
+        let mut data = b"BM".to_vec();
+        data.extend_from_slice(&[0; 8]);
+        data.extend_from_slice(&54u32.to_le_bytes());
+        data.extend_from_slice(&40u32.to_le_bytes());
+        data.extend_from_slice(&2i32.to_le_bytes());
+        data.extend_from_slice(&2i32.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&24u16.to_le_bytes());
+        data.extend_from_slice(&[0; 24]);
+        data.extend_from_slice(&[255, 0, 0, 255, 255, 255, 0, 0]);
+        data.extend_from_slice(&[0, 0, 255, 0, 255, 0]);
+        data
+    }
+
+    fn pfm() -> Vec<u8> {
+        // Little endian, lignes de bas en haut, valeurs au-delà de 1.0
+        let mut data = b"PF\n2 2\n-1.0\n".to_vec();
+        for v in [0.0f32, 0.0, 4.0, 2.5, 2.5, 2.5, 4.0, 0.0, 0.0, 0.0, 4.0, 0.0] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        data
+    }
+
+    fn hdr() -> Vec<u8> {
+        // Pixels non compressés (largeur < 8), exposant 129 : mantisse / 128
+        let mut data = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 2\n".to_vec();
+        data.extend_from_slice(&[128, 0, 0, 129, 0, 128, 0, 129, 0, 0, 128, 129, 128, 128, 128, 129]);
+        data
+    }
+
+    #[test]
+    fn decode_small_images() {
+        let expected = [RED, GREEN, BLUE, WHITE];
+        assert_pixels(&decode_ppm(&ppm()).unwrap(), 2, 2, &expected);
+        assert_pixels(&decode_tga(&tga()).unwrap(), 2, 2, &expected);
+        assert_pixels(&decode_bmp(&bmp()).unwrap(), 2, 2, &expected);
+        assert_pixels(&decode_hdr(&hdr()).unwrap(), 2, 2, &expected);
+        assert_pixels(&decode_pfm(&pfm()).unwrap(), 2, 2, &[(4.0, 0.0, 0.0), (0.0, 4.0, 0.0), (0.0, 0.0, 4.0), (2.5, 2.5, 2.5)]);
+
+        // PPM ASCII et 16 bits
+        assert_pixels(&decode_ppm(b"P3 2 1 15\n15 0 0  0 0 15\n").unwrap(), 2, 1, &[RED, BLUE]);
+        let mut data = b"P6 1 1 65535\n".to_vec();
+        data.extend_from_slice(&[255, 255, 128, 0, 0, 0]);
+        assert_pixels(&decode_ppm(&data).unwrap(), 1, 1, &[(1.0, 0.5, 0.0)]);
+    }
+
+    #[test]
+    fn decode_rle() {
+        // TGA RLE, origine en haut : un paquet répété de 3 pixels rouges, un paquet brut d'un pixel
+        let mut data = vec![0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 2, 0, 24, 0x20];
+        data.extend_from_slice(&[0x82, 0, 0, 255, 0x00, 255, 0, 0]);
+        assert_pixels(&decode_tga(&data).unwrap(), 2, 2, &[RED, RED, RED, BLUE]);
+
+        // HDR RLE, une ligne de 8 pixels : une répétition par composante
+        let mut data = b"#?RADIANCE\n\n-Y 1 +X 8\n".to_vec();
+        data.extend_from_slice(&[2, 2, 0, 8, 136, 128, 136, 64, 136, 0, 136, 129]);
+        assert_pixels(&decode_hdr(&data).unwrap(), 8, 1, &[(1.0, 0.5, 0.0); 8]);
+    }
+
+    // Toute troncature donne une erreur, jamais une panique. Le PPM garde sa signature,
+    // vérifiée par Texture::load avant l'appel
+    #[test]
+    fn truncated_images() {
+        let decoders: [(Decoder, Vec<u8>, usize); 5] =
+            [(decode_ppm, ppm(), 2), (decode_tga, tga(), 0), (decode_bmp, bmp(), 0), (decode_pfm, pfm(), 0), (decode_hdr, hdr(), 0)];
+        for (decode, data, min) in decoders {
+            for len in min..data.len() {
+                assert!(decode(&data[..len]).is_err(), "{:?}", &data[..len]);
+            }
+        }
+    }
+
+    #[test]
+    fn oversized_headers() {
+        let huge: [(Decoder, Vec<u8>); 7] = [
+            (decode_ppm, b"P6\n4000000000 4000000000\n255\n\0\0\0".to_vec()),
+            (decode_ppm, b"P3\n99999999999999999999999 1\n255\n".to_vec()),
+            (decode_tga, vec![0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255, 24, 0, 0xff, 1, 2, 3]),
+            (decode_bmp, {
+                let mut data = bmp();
+                data[18..22].copy_from_slice(&i32::MAX.to_le_bytes());
+                data[22..26].copy_from_slice(&i32::MIN.to_le_bytes());
+                data
+            }),
+            (decode_pfm, b"PF\n4000000000 4000000000\n-1.0\n\0\0\0\0".to_vec()),
+            (decode_hdr, b"#?RADIANCE\n\n-Y 99999999999 +X 99999999999\n\0\0\0\0".to_vec()),
+            (decode_hdr, b"#?RADIANCE\n\n-Y 4000000000 +X 20\n\x02\x02\x00\x14".to_vec()),
+        ];
+        for (decode, data) in huge {
+            assert!(decode(&data).is_err(), "{:?}", String::from_utf8_lossy(&data));
+        }
+
+        // Dimensions nulles et valeurs d'en-tête invalides
+        assert!(decode_ppm(b"P6\n0 2\n255\n").is_err());
+        assert!(decode_ppm(b"P6\n1 1\n0\n\0\0\0").is_err());
+        assert!(decode_pfm(b"PF\n2\n-1.0\n").is_err());
+        assert!(decode_hdr(b"#?RADIANCE\n\n+Y 1 +X 1\n\0\0\0\0").is_err());
+        assert!(decode_hdr(b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n\0\0\0\0").is_err());
+    }
+
+    #[test]
+    fn load_errors() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("sixel-3d-texture-{}.png", std::process::id()));
+        std::fs::write(&path, b"\x89PNG\r\n\x1a\n").unwrap();
+        let unknown = Texture::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(unknown, Err(TextureError::Format(..))));
+        assert!(matches!(Texture::load(&path), Err(TextureError::Io(..))));
+    }
+
+    fn checker() -> Texture {
+        // Rouge, vert en haut ; bleu, blanc en bas
+        Texture { width: 2, height: 2, pixels: vec![RED, GREEN, BLUE, WHITE] }
+    }
+
+    #[test]
+    fn sample_texel_centers_and_wrap() {
+        let t = checker();
+        let close = |a: Rgb, b: Rgb| (a.0 - b.0).abs() < 1e-5 && (a.1 - b.1).abs() < 1e-5 && (a.2 - b.2).abs() < 1e-5;
+        // v = 0 en bas de l'image
+        assert!(close(t.sample(0.25, 0.75), RED));
+        assert!(close(t.sample(0.75, 0.75), GREEN));
+        assert!(close(t.sample(0.25, 0.25), BLUE));
+        assert!(close(t.sample(0.75, 0.25), WHITE));
+
+        // Répétition dans les deux sens
+        for (du, dv) in [(1.0, 0.0), (-1.0, 0.0), (0.0, 3.0), (-2.0, -5.0)] {
+            assert!(close(t.sample(0.25 + du, 0.75 + dv), RED));
+            assert!(close(t.sample(0.6 + du, 0.3 + dv), t.sample(0.6, 0.3)));
+        }
+    }
+
+    #[test]
+    fn sample_bilinear() {
+        let t = checker();
+        let close = |a: Rgb, b: Rgb| (a.0 - b.0).abs() < 1e-5 && (a.1 - b.1).abs() < 1e-5 && (a.2 - b.2).abs() < 1e-5;
+        // Entre deux texels d'une ligne
+        assert!(close(t.sample(0.5, 0.75), (0.5, 0.5, 0.0)));
+        // Au centre : moyenne des quatre
+        assert!(close(t.sample(0.5, 0.5), (0.5, 0.5, 0.5)));
+        // Un quart du chemin vers la droite
+        assert!(close(t.sample(0.375, 0.75), (0.75, 0.25, 0.0)));
+        // Bord gauche : moitié avec le texel de l'autre bout (répétition)
+        assert!(close(t.sample(0.0, 0.75), (0.5, 0.5, 0.0)));
+
+        // Texture vide : blanc
+        let empty = Texture { width: 0, height: 0, pixels: Vec::new() };
+        assert_eq!(empty.sample(0.5, 0.5), WHITE);
+    }
+}